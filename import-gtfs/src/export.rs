@@ -0,0 +1,369 @@
+// Export of an imported feed as GTFS zip file with the original ids, optionally
+// limited to an area, a date range, agencies or route types
+use chrono::NaiveDate;
+use postgres::types::{ToSql, Type};
+use postgres::{Client, Row, Transaction};
+use std::collections::HashMap;
+use std::fs::File;
+use std::path::Path;
+use std::process::exit;
+use zip::write::{FileOptions, ZipWriter};
+
+use crate::feeds;
+use crate::migrations::SCHEMA_VERSION;
+
+pub struct Filter {
+    // min_lon, min_lat, max_lon, max_lat
+    pub bbox: Option<[f64; 4]>,
+    pub start_date: Option<NaiveDate>,
+    pub end_date: Option<NaiveDate>,
+    pub agencies: Vec<String>,
+    pub route_types: Vec<i32>
+}
+
+// Columns with seconds since midnight, written as HH:MM:SS
+const TIME_COLUMNS: [&str; 4] = ["arrival_time", "departure_time", "start_time", "end_time"];
+
+// (file, query, required), the columns of the queries are named like the GTFS fields
+const FILES: [(&str, &str, bool); 21] = [
+    (
+        "agency.txt",
+        "SELECT agency_id, agency_name, agency_url, agency_timezone, agency_lang, agency_phone, agency_fare_url
+         FROM agency
+         WHERE agency_id IN (SELECT agency_id FROM routes WHERE route_id IN (SELECT route_id FROM export_routes))",
+        true
+    ),
+    (
+        "routes.txt",
+        "SELECT route_id, agency_id, route_short_name, route_long_name, route_desc, route_type, route_color, route_text_color, route_sort_order
+         FROM routes
+         WHERE route_id IN (SELECT route_id FROM export_routes)",
+        true
+    ),
+    (
+        "trips.txt",
+        "SELECT route_id, service_id, trip_id, trip_headsign, direction_id, shape_id, wheelchair_accessible, bikes_allowed
+         FROM trips
+         WHERE trip_id IN (SELECT trip_id FROM export_trips)",
+        true
+    ),
+    (
+        "stops.txt",
+        "SELECT stop_id, stop_name, stop_lat, stop_lon, zone_id, location_type, parent_station, wheelchair_boarding, level_id, platform_code
+         FROM stops
+         WHERE stop_id IN (SELECT stop_id FROM export_stops)",
+        true
+    ),
+    (
+        "stop_times.txt",
+        "SELECT st.trip_id, st.arrival_time, st.departure_time, st.stop_id, st.stop_sequence, st.stop_headsign, st.shape_dist_traveled
+         FROM stop_times st
+         JOIN export_stop_times USING (trip_id, stop_sequence)
+         ORDER BY st.trip_id, st.stop_sequence",
+        true
+    ),
+    (
+        "calendar.txt",
+        "SELECT service_id, monday, tuesday, wednesday, thursday, friday, saturday, sunday,
+            GREATEST(c.start_date, f.start_date) AS start_date, LEAST(c.end_date, f.end_date) AS end_date
+         FROM calendar c, export_filter f
+         WHERE service_id IN (SELECT service_id FROM export_services)
+            AND GREATEST(c.start_date, f.start_date) <= LEAST(c.end_date, f.end_date)",
+        false
+    ),
+    (
+        "calendar_dates.txt",
+        "SELECT service_id, date, exception_type
+         FROM calendar_dates cd, export_filter f
+         WHERE service_id IN (SELECT service_id FROM export_services)
+            AND (f.start_date IS NULL OR cd.date >= f.start_date)
+            AND (f.end_date IS NULL OR cd.date <= f.end_date)
+         ORDER BY service_id, date",
+        false
+    ),
+    (
+        "shapes.txt",
+        "SELECT shape_id, shape_pt_lat, shape_pt_lon, shape_pt_sequence, shape_dist_traveled
+         FROM shapes
+         WHERE shape_id IN (SELECT shape_id FROM trips WHERE trip_id IN (SELECT trip_id FROM export_trips))
+         ORDER BY shape_id, shape_pt_sequence",
+        false
+    ),
+    (
+        "frequencies.txt",
+        "SELECT trip_id, start_time, end_time, headway_secs, exact_times
+         FROM frequencies
+         WHERE trip_id IN (SELECT trip_id FROM export_trips)",
+        false
+    ),
+    (
+        "transfers.txt",
+        "SELECT from_stop_id, to_stop_id, from_route_id, to_route_id, from_trip_id, to_trip_id, transfer_type, min_transfer_time
+         FROM transfers
+         WHERE (from_stop_id IS NULL OR from_stop_id IN (SELECT stop_id FROM export_stops))
+            AND (to_stop_id IS NULL OR to_stop_id IN (SELECT stop_id FROM export_stops))
+            AND (from_route_id IS NULL OR from_route_id IN (SELECT route_id FROM export_routes))
+            AND (to_route_id IS NULL OR to_route_id IN (SELECT route_id FROM export_routes))
+            AND (from_trip_id IS NULL OR from_trip_id IN (SELECT trip_id FROM export_trips))
+            AND (to_trip_id IS NULL OR to_trip_id IN (SELECT trip_id FROM export_trips))",
+        false
+    ),
+    (
+        "levels.txt",
+        "SELECT level_id, level_index, level_name
+         FROM levels
+         WHERE level_id IN (SELECT level_id FROM stops WHERE stop_id IN (SELECT stop_id FROM export_stops))",
+        false
+    ),
+    (
+        "pathways.txt",
+        "SELECT pathway_id, from_stop_id, to_stop_id, pathway_mode, is_bidirectional, length, traversal_time,
+            stair_count, max_slope, min_width, signposted_as, reversed_signposted_as
+         FROM pathways
+         WHERE from_stop_id IN (SELECT stop_id FROM export_stops)
+            AND to_stop_id IN (SELECT stop_id FROM export_stops)",
+        false
+    ),
+    (
+        "fare_attributes.txt",
+        "SELECT fare_id, price, currency_type, payment_method, transfers, agency_id, transfer_duration
+         FROM fare_attributes
+         WHERE (agency_id IS NULL
+                OR agency_id IN (SELECT agency_id FROM routes WHERE route_id IN (SELECT route_id FROM export_routes)))
+            AND (fare_id IN (SELECT fare_id FROM export_fare_rules) OR fare_id NOT IN (SELECT fare_id FROM fare_rules))",
+        false
+    ),
+    (
+        "fare_rules.txt",
+        "SELECT fare_id, route_id, origin_id, destination_id, contains_id FROM export_fare_rules",
+        false
+    ),
+    (
+        "areas.txt",
+        "SELECT area_id, area_name
+         FROM areas
+         WHERE area_id IN (SELECT area_id FROM stop_areas WHERE stop_id IN (SELECT stop_id FROM export_stops))",
+        false
+    ),
+    (
+        "stop_areas.txt",
+        "SELECT area_id, stop_id
+         FROM stop_areas
+         WHERE stop_id IN (SELECT stop_id FROM export_stops)",
+        false
+    ),
+    (
+        "route_networks.txt",
+        "SELECT network_id, route_id
+         FROM route_networks
+         WHERE route_id IN (SELECT route_id FROM export_routes)",
+        false
+    ),
+    (
+        "fare_leg_rules.txt",
+        "SELECT leg_group_id, network_id, from_area_id, to_area_id, fare_product_id, rule_priority
+         FROM export_fare_leg_rules",
+        false
+    ),
+    (
+        "fare_products.txt",
+        "SELECT fare_product_id, fare_product_name, fare_media_id, amount, currency
+         FROM fare_products
+         WHERE fare_product_id IN (SELECT fare_product_id FROM export_fare_leg_rules)",
+        false
+    ),
+    (
+        "fare_media.txt",
+        "SELECT fare_media_id, fare_media_name, fare_media_type
+         FROM fare_media
+         WHERE fare_media_id IN (
+            SELECT fare_media_id FROM fare_products
+            WHERE fare_product_id IN (SELECT fare_product_id FROM export_fare_leg_rules)
+         )",
+        false
+    ),
+    (
+        "translations.txt",
+        "SELECT table_name, field_name, language, translation, record_id, record_sub_id, field_value
+         FROM translations
+         WHERE record_id IS NULL
+            OR (table_name = 'agency' AND record_id IN (SELECT agency_id FROM routes WHERE route_id IN (SELECT route_id FROM export_routes)))
+            OR (table_name = 'routes' AND record_id IN (SELECT route_id FROM export_routes))
+            OR (table_name IN ('trips', 'stop_times') AND record_id IN (SELECT trip_id FROM export_trips))
+            OR (table_name = 'stops' AND record_id IN (SELECT stop_id FROM export_stops))
+            OR (table_name = 'levels' AND record_id IN (SELECT level_id FROM stops WHERE stop_id IN (SELECT stop_id FROM export_stops)))
+            OR (table_name = 'pathways' AND record_id IN (
+                SELECT pathway_id FROM pathways
+                WHERE from_stop_id IN (SELECT stop_id FROM export_stops) AND to_stop_id IN (SELECT stop_id FROM export_stops)
+            ))",
+        false
+    )
+];
+
+fn format_time(seconds: i32) -> String {
+    format!(
+        "{:02}:{:02}:{:02}",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+// A column value as GTFS field, OIDs are replaced by their original ids
+fn field(row: &Row, index: usize, ids: &HashMap<u32, String>) -> String {
+    let column = &row.columns()[index];
+    let ty = column.type_();
+
+    if *ty == Type::OID {
+        row.get::<_, Option<u32>>(index)
+            .map(|id| ids.get(&id).cloned().unwrap_or_else(|| id.to_string()))
+    } else if *ty == Type::INT4 {
+        row.get::<_, Option<i32>>(index).map(|value| {
+            if TIME_COLUMNS.contains(&column.name()) {
+                format_time(value)
+            } else {
+                value.to_string()
+            }
+        })
+    } else if *ty == Type::FLOAT8 {
+        row.get::<_, Option<f64>>(index).map(|v| v.to_string())
+    } else if *ty == Type::FLOAT4 {
+        row.get::<_, Option<f32>>(index).map(|v| v.to_string())
+    } else if *ty == Type::BOOL {
+        row.get::<_, Option<bool>>(index)
+            .map(|v| if v { "1" } else { "0" }.to_string())
+    } else if *ty == Type::DATE {
+        row.get::<_, Option<NaiveDate>>(index)
+            .map(|v| v.format("%Y%m%d").to_string())
+    } else if *ty == Type::TEXT {
+        row.get::<_, Option<String>>(index)
+    } else {
+        panic!("Unsupported type {} of column {}", ty, column.name())
+    }
+    .unwrap_or_default()
+}
+
+fn write_file(
+    zip: &mut ZipWriter<File>,
+    tx: &mut Transaction,
+    ids: &HashMap<u32, String>,
+    (file_name, query, required): (&str, &str, bool),
+    params: &[&(dyn ToSql + Sync)]
+) {
+    let stmt = tx.prepare(query).unwrap();
+    let rows = tx
+        .query(&stmt, params)
+        .unwrap_or_else(|e| panic!("Error querying {}: {}", file_name, e));
+    if rows.is_empty() && !required {
+        return;
+    }
+    println!("Export {} {}...", rows.len(), file_name);
+
+    zip.start_file(file_name, FileOptions::default())
+        .expect("Error writing GTFS zip file");
+    let mut writer = csv::Writer::from_writer(&mut *zip);
+    writer
+        .write_record(stmt.columns().iter().map(|c| c.name()))
+        .expect("Error writing GTFS zip file");
+    for row in &rows {
+        writer
+            .write_record((0..row.len()).map(|i| field(row, i, ids)))
+            .expect("Error writing GTFS zip file");
+    }
+    writer.flush().expect("Error writing GTFS zip file");
+}
+
+pub fn export_feed(conn: &mut Client, feed_uid: &str, output: &Path, filter: &Filter) {
+    feeds::ensure_feed_exists(conn, feed_uid);
+
+    let row = conn
+        .query_one(
+            "SELECT COALESCE(schema_version, 1) AS schema_version FROM public.feeds WHERE feed_uid = $1",
+            &[&feed_uid]
+        )
+        .expect("Error querying feeds");
+    let version: i32 = row.get("schema_version");
+    if version != SCHEMA_VERSION {
+        eprintln!(
+            "Feed {} has schema version {}, run migrate first",
+            feed_uid, version
+        );
+        exit(1);
+    }
+
+    // Nothing is written to the database, the temporary tables are dropped
+    // with the rolled back transaction
+    let mut tx = conn.transaction().unwrap();
+    tx.execute(&format!("SET search_path=\"{}\"", feed_uid), &[])
+        .expect("Error setting default schema path");
+
+    let ids: HashMap<u32, String> = tx
+        .query("SELECT id, gtfs_id FROM ids", &[])
+        .expect("Error querying ids")
+        .iter()
+        .map(|row| (row.get("id"), row.get("gtfs_id")))
+        .collect();
+    if ids.is_empty() {
+        println!("Original ids are unknown, import the feed again to keep them");
+    }
+
+    tx.execute(
+        "CREATE TEMP TABLE export_filter AS
+         SELECT $1::FLOAT8[] AS bbox, $2::DATE AS start_date, $3::DATE AS end_date,
+            $4::TEXT[] AS agencies, $5::INT[] AS route_types",
+        &[
+            &filter.bbox.map(|b| b.to_vec()),
+            &filter.start_date,
+            &filter.end_date,
+            &filter.agencies,
+            &filter.route_types
+        ]
+    )
+    .expect("Error creating export filter");
+    tx.batch_execute(include_str!("sql/export_subset.sql"))
+        .expect("Error selecting exported data");
+
+    // Fare rules of exported routes and zones, leg rules of exported networks and areas
+    tx.batch_execute(
+        "CREATE TEMP TABLE export_fare_rules AS
+         SELECT * FROM fare_rules
+         WHERE (route_id IS NULL OR route_id IN (SELECT route_id FROM export_routes))
+            AND (origin_id IS NULL OR origin_id IN (SELECT zone_id FROM stops WHERE stop_id IN (SELECT stop_id FROM export_stops)))
+            AND (destination_id IS NULL OR destination_id IN (SELECT zone_id FROM stops WHERE stop_id IN (SELECT stop_id FROM export_stops)))
+            AND (contains_id IS NULL OR contains_id IN (SELECT zone_id FROM stops WHERE stop_id IN (SELECT stop_id FROM export_stops)));
+
+         CREATE TEMP TABLE export_fare_leg_rules AS
+         SELECT * FROM fare_leg_rules
+         WHERE (network_id IS NULL OR network_id IN (SELECT network_id FROM route_networks WHERE route_id IN (SELECT route_id FROM export_routes)))
+            AND (from_area_id IS NULL OR from_area_id IN (SELECT area_id FROM stop_areas WHERE stop_id IN (SELECT stop_id FROM export_stops)))
+            AND (to_area_id IS NULL OR to_area_id IN (SELECT area_id FROM stop_areas WHERE stop_id IN (SELECT stop_id FROM export_stops)));"
+    )
+    .expect("Error selecting exported fares");
+
+    let file = File::create(output).expect("Error creating GTFS zip file");
+    let mut zip = ZipWriter::new(file);
+
+    for file in FILES {
+        write_file(&mut zip, &mut tx, &ids, file, &[]);
+    }
+
+    write_file(
+        &mut zip,
+        &mut tx,
+        &ids,
+        (
+            "feed_info.txt",
+            "SELECT feed_publisher_name, feed_publisher_url, feed_lang,
+                GREATEST(feed_start_date, $2) AS feed_start_date, LEAST(feed_end_date, $3) AS feed_end_date, feed_version
+             FROM public.feeds
+             WHERE feed_uid = $1 AND feed_publisher_name <> ''",
+            false
+        ),
+        &[&feed_uid, &filter.start_date, &filter.end_date]
+    );
+
+    zip.finish().expect("Error writing GTFS zip file");
+    tx.rollback().unwrap();
+
+    println!("Exported {} to {}", feed_uid, output.display());
+}
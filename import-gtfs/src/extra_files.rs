@@ -0,0 +1,201 @@
+// Reader for GTFS files which are not supported by gtfs-structures
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use std::fs::{self, File};
+use std::io::{Cursor, Read, Seek, Write};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use zip::write::FileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+pub trait ReadSeek: Read + Seek {}
+impl<T: Read + Seek> ReadSeek for T {}
+
+enum Source {
+    Zip(ZipArchive<Box<dyn ReadSeek>>),
+    Directory(PathBuf),
+    // Feeds converted from other formats have no additional files
+    Empty
+}
+
+pub struct GtfsFiles {
+    source: Source,
+    // Files which should not be imported
+    skip: Vec<String>
+}
+
+impl GtfsFiles {
+    pub fn open(path: &Path, skip: &[String]) -> GtfsFiles {
+        let source = if path.is_dir() {
+            Source::Directory(path.to_path_buf())
+        } else {
+            let file = File::open(path).expect("Error opening GTFS zip file");
+            Source::Zip(
+                ZipArchive::new(Box::new(file) as Box<dyn ReadSeek>)
+                    .expect("Error reading GTFS zip file")
+            )
+        };
+
+        GtfsFiles {
+            source,
+            skip: skip.to_vec()
+        }
+    }
+
+    // Zip file which was read into memory, e.g. from stdin
+    pub fn from_bytes(data: Rc<[u8]>, skip: &[String]) -> GtfsFiles {
+        let cursor = Box::new(Cursor::new(data)) as Box<dyn ReadSeek>;
+
+        GtfsFiles {
+            source: Source::Zip(ZipArchive::new(cursor).expect("Error reading GTFS zip file")),
+            skip: skip.to_vec()
+        }
+    }
+
+    pub fn empty() -> GtfsFiles {
+        GtfsFiles {
+            source: Source::Empty,
+            skip: Vec::new()
+        }
+    }
+
+    // Zip file in memory with the files of the feed except the skipped ones,
+    // which gtfs-structures would parse otherwise. Files of a zip file are
+    // copied without decompressing them, files of a directory are stored.
+    pub fn without_skipped(&mut self) -> Vec<u8> {
+        let skipped = |name: &str| {
+            Path::new(name)
+                .file_name()
+                .is_some_and(|name| self.skip.iter().any(|s| name == s.as_str()))
+        };
+        let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+
+        match &mut self.source {
+            Source::Zip(archive) => {
+                for i in 0..archive.len() {
+                    let file = archive
+                        .by_index_raw(i)
+                        .expect("Error reading GTFS zip file");
+                    if !skipped(file.name()) {
+                        zip.raw_copy_file(file)
+                            .expect("Error copying GTFS zip file");
+                    }
+                }
+            }
+            Source::Directory(directory) => {
+                let options = FileOptions::default().compression_method(CompressionMethod::Stored);
+                for entry in fs::read_dir(directory).expect("Error reading GTFS directory") {
+                    let path = entry.expect("Error reading GTFS directory").path();
+                    let name = path.file_name().unwrap_or_default().to_string_lossy();
+                    if path.is_file() && !skipped(&name) {
+                        let content = fs::read(&path)
+                            .unwrap_or_else(|e| panic!("Error reading {}: {}", name, e));
+                        zip.start_file(name, options)
+                            .and_then(|_| Ok(zip.write_all(&content)?))
+                            .expect("Error copying GTFS file");
+                    }
+                }
+            }
+            Source::Empty => {}
+        }
+
+        zip.finish().expect("Error copying GTFS files").into_inner()
+    }
+
+    // Returns None if the file is not present in the feed or skipped
+    pub fn read_optional<T: DeserializeOwned>(&mut self, file_name: &str) -> Option<Vec<T>> {
+        if self.skip.iter().any(|s| s == file_name) {
+            println!("Skip {}", file_name);
+            return None;
+        }
+
+        let mut content = Vec::new();
+
+        match &mut self.source {
+            Source::Zip(archive) => {
+                // Files may be located in a subdirectory of the zip file
+                let name = archive
+                    .file_names()
+                    .find(|name| Path::new(name).file_name() == Some(file_name.as_ref()))?
+                    .to_string();
+                archive
+                    .by_name(&name)
+                    .and_then(|mut file| Ok(file.read_to_end(&mut content)?))
+                    .unwrap_or_else(|e| panic!("Error reading {}: {}", file_name, e));
+            }
+            Source::Directory(directory) => {
+                let mut file = File::open(directory.join(file_name)).ok()?;
+                file.read_to_end(&mut content)
+                    .unwrap_or_else(|e| panic!("Error reading {}: {}", file_name, e));
+            }
+            Source::Empty => return None
+        }
+
+        let content = content
+            .strip_prefix(&[0xef, 0xbb, 0xbf])
+            .unwrap_or(&content);
+        let mut reader = csv::ReaderBuilder::new()
+            .flexible(true)
+            .trim(csv::Trim::Fields)
+            .from_reader(content);
+
+        // Broken optional files are left out like missing ones
+        match reader.deserialize().collect::<Result<Vec<T>, _>>() {
+            Ok(records) => Some(records),
+            Err(e) => {
+                println!(
+                    "Warning: {} cannot be read and is not imported: {}",
+                    file_name, e
+                );
+                None
+            }
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Transfer {
+    pub from_stop_id: Option<String>,
+    pub to_stop_id: Option<String>,
+    pub from_route_id: Option<String>,
+    pub to_route_id: Option<String>,
+    pub from_trip_id: Option<String>,
+    pub to_trip_id: Option<String>,
+    pub transfer_type: Option<i32>,
+    pub min_transfer_time: Option<i32>
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Level {
+    pub level_id: String,
+    pub level_index: f64,
+    pub level_name: Option<String>
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Pathway {
+    pub pathway_id: String,
+    pub from_stop_id: String,
+    pub to_stop_id: String,
+    pub pathway_mode: i32,
+    pub is_bidirectional: i32,
+    pub length: Option<f32>,
+    pub traversal_time: Option<i32>,
+    pub stair_count: Option<i32>,
+    pub max_slope: Option<f32>,
+    pub min_width: Option<f32>,
+    pub signposted_as: Option<String>,
+    pub reversed_signposted_as: Option<String>
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Translation {
+    pub table_name: String,
+    pub field_name: String,
+    pub language: String,
+    pub translation: String,
+    // Either the translated record or the translated value is given
+    pub record_id: Option<String>,
+    pub record_sub_id: Option<String>,
+    pub field_value: Option<String>
+}
@@ -0,0 +1,317 @@
+// Fares v1 (fare_attributes.txt, fare_rules.txt) and fares v2 (fare_products.txt,
+// fare_leg_rules.txt, fare_media.txt, areas.txt, stop_areas.txt, route_networks.txt)
+use gtfs_structures::{FareAttribute, Transfers};
+use postgres::Transaction;
+use serde::Deserialize;
+use string_interner::Symbol;
+
+use crate::extra_files::GtfsFiles;
+use crate::Interner;
+
+#[derive(Debug, Deserialize)]
+pub struct FareRule {
+    pub fare_id: String,
+    pub route_id: Option<String>,
+    pub origin_id: Option<String>,
+    pub destination_id: Option<String>,
+    pub contains_id: Option<String>
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FareMedia {
+    pub fare_media_id: String,
+    pub fare_media_name: Option<String>,
+    pub fare_media_type: i32
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FareProduct {
+    pub fare_product_id: String,
+    pub fare_product_name: Option<String>,
+    pub fare_media_id: Option<String>,
+    pub amount: f64,
+    pub currency: String
+}
+
+// Timeframes and transfer rules are not supported, only single legs are priced
+#[derive(Debug, Deserialize)]
+pub struct FareLegRule {
+    pub leg_group_id: Option<String>,
+    pub network_id: Option<String>,
+    pub from_area_id: Option<String>,
+    pub to_area_id: Option<String>,
+    pub fare_product_id: String,
+    pub rule_priority: Option<i32>
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Area {
+    pub area_id: String,
+    pub area_name: Option<String>
+}
+
+#[derive(Debug, Deserialize)]
+pub struct StopArea {
+    pub area_id: String,
+    pub stop_id: String
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RouteNetwork {
+    pub network_id: String,
+    pub route_id: String
+}
+
+fn intern(interner: &mut Interner, id: &str) -> u32 {
+    interner.get_or_intern(id).to_usize() as u32
+}
+
+fn intern_optional(interner: &mut Interner, id: &Option<String>) -> Option<u32> {
+    id.as_ref().map(|id| intern(interner, id))
+}
+
+fn insert_fare_attributes(
+    tx: &mut Transaction,
+    interner: &mut Interner,
+    fare_attributes: &Vec<FareAttribute>
+) {
+    println!("Import {} fare attributes...", &fare_attributes.len());
+
+    let stmt = tx.prepare(
+      "INSERT INTO fare_attributes (fare_id, price, currency_type, payment_method, transfers, agency_id, transfer_duration)
+       VALUES ($1, $2, $3, $4, $5, $6, $7)").unwrap();
+
+    for f in fare_attributes {
+        let transfers = match f.transfers {
+            Transfers::Unlimited => None,
+            Transfers::NoTransfer => Some(0),
+            Transfers::UniqueTransfer => Some(1),
+            Transfers::TwoTransfers => Some(2),
+            Transfers::Other(n) => Some(n)
+        };
+
+        tx.execute(
+            &stmt,
+            &[
+                &intern(interner, &f.id),
+                &f.price
+                    .parse::<f64>()
+                    .unwrap_or_else(|_| panic!("Invalid price of fare {}", f.id)),
+                &f.currency,
+                &(f.payment_method as i32),
+                &transfers,
+                &intern_optional(interner, &f.agency_id),
+                &f.transfer_duration.map(|d| d as i32)
+            ]
+        )
+        .unwrap();
+    }
+}
+
+fn insert_fare_rules(tx: &mut Transaction, interner: &mut Interner, fare_rules: &Vec<FareRule>) {
+    println!("Import {} fare rules...", &fare_rules.len());
+
+    let stmt = tx.prepare(
+      "INSERT INTO fare_rules (fare_id, route_id, origin_id, destination_id, contains_id) VALUES ($1, $2, $3, $4, $5)").unwrap();
+
+    for r in fare_rules {
+        tx.execute(
+            &stmt,
+            &[
+                &intern(interner, &r.fare_id),
+                &intern_optional(interner, &r.route_id),
+                &intern_optional(interner, &r.origin_id),
+                &intern_optional(interner, &r.destination_id),
+                &intern_optional(interner, &r.contains_id)
+            ]
+        )
+        .unwrap();
+    }
+}
+
+fn insert_fare_media(tx: &mut Transaction, interner: &mut Interner, fare_media: &Vec<FareMedia>) {
+    println!("Import {} fare media...", &fare_media.len());
+
+    let stmt = tx
+        .prepare(
+            "INSERT INTO fare_media (fare_media_id, fare_media_name, fare_media_type) VALUES ($1, $2, $3)"
+        )
+        .unwrap();
+
+    for m in fare_media {
+        tx.execute(
+            &stmt,
+            &[
+                &intern(interner, &m.fare_media_id),
+                &m.fare_media_name,
+                &m.fare_media_type
+            ]
+        )
+        .unwrap();
+    }
+}
+
+fn insert_fare_products(
+    tx: &mut Transaction,
+    interner: &mut Interner,
+    fare_products: &Vec<FareProduct>
+) {
+    println!("Import {} fare products...", &fare_products.len());
+
+    let stmt = tx.prepare(
+      "INSERT INTO fare_products (fare_product_id, fare_product_name, fare_media_id, amount, currency) VALUES ($1, $2, $3, $4, $5)").unwrap();
+
+    for p in fare_products {
+        tx.execute(
+            &stmt,
+            &[
+                &intern(interner, &p.fare_product_id),
+                &p.fare_product_name,
+                &intern_optional(interner, &p.fare_media_id),
+                &p.amount,
+                &p.currency
+            ]
+        )
+        .unwrap();
+    }
+}
+
+fn insert_fare_leg_rules(
+    tx: &mut Transaction,
+    interner: &mut Interner,
+    fare_leg_rules: &Vec<FareLegRule>
+) {
+    println!("Import {} fare leg rules...", &fare_leg_rules.len());
+
+    let stmt = tx.prepare(
+      "INSERT INTO fare_leg_rules (leg_group_id, network_id, from_area_id, to_area_id, fare_product_id, rule_priority)
+       VALUES ($1, $2, $3, $4, $5, $6)").unwrap();
+
+    for r in fare_leg_rules {
+        tx.execute(
+            &stmt,
+            &[
+                &intern_optional(interner, &r.leg_group_id),
+                &intern_optional(interner, &r.network_id),
+                &intern_optional(interner, &r.from_area_id),
+                &intern_optional(interner, &r.to_area_id),
+                &intern(interner, &r.fare_product_id),
+                &r.rule_priority
+            ]
+        )
+        .unwrap();
+    }
+}
+
+fn insert_areas(tx: &mut Transaction, interner: &mut Interner, areas: &Vec<Area>) {
+    println!("Import {} areas...", &areas.len());
+
+    let stmt = tx
+        .prepare("INSERT INTO areas (area_id, area_name) VALUES ($1, $2)")
+        .unwrap();
+
+    for a in areas {
+        tx.execute(&stmt, &[&intern(interner, &a.area_id), &a.area_name])
+            .unwrap();
+    }
+}
+
+fn insert_stop_areas(tx: &mut Transaction, interner: &mut Interner, stop_areas: &Vec<StopArea>) {
+    println!("Import {} stop areas...", &stop_areas.len());
+
+    let stmt = tx
+        .prepare("INSERT INTO stop_areas (area_id, stop_id) VALUES ($1, $2)")
+        .unwrap();
+
+    for s in stop_areas {
+        tx.execute(
+            &stmt,
+            &[&intern(interner, &s.area_id), &intern(interner, &s.stop_id)]
+        )
+        .unwrap();
+    }
+}
+
+fn insert_route_networks(
+    tx: &mut Transaction,
+    interner: &mut Interner,
+    route_networks: &Vec<RouteNetwork>
+) {
+    println!("Import {} route networks...", &route_networks.len());
+
+    let stmt = tx
+        .prepare("INSERT INTO route_networks (network_id, route_id) VALUES ($1, $2)")
+        .unwrap();
+
+    for r in route_networks {
+        tx.execute(
+            &stmt,
+            &[
+                &intern(interner, &r.network_id),
+                &intern(interner, &r.route_id)
+            ]
+        )
+        .unwrap();
+    }
+}
+
+// Fare files of a feed, None if the file is not present
+pub struct Fares {
+    pub attributes: Option<Vec<FareAttribute>>,
+    pub rules: Option<Vec<FareRule>>,
+    pub media: Option<Vec<FareMedia>>,
+    pub products: Option<Vec<FareProduct>>,
+    pub leg_rules: Option<Vec<FareLegRule>>,
+    pub areas: Option<Vec<Area>>,
+    pub stop_areas: Option<Vec<StopArea>>,
+    pub route_networks: Option<Vec<RouteNetwork>>
+}
+
+impl Fares {
+    pub fn read(attributes: Option<Vec<FareAttribute>>, files: &mut GtfsFiles) -> Fares {
+        Fares {
+            attributes,
+            rules: files.read_optional("fare_rules.txt"),
+            media: files.read_optional("fare_media.txt"),
+            products: files.read_optional("fare_products.txt"),
+            leg_rules: files.read_optional("fare_leg_rules.txt"),
+            areas: files.read_optional("areas.txt"),
+            stop_areas: files.read_optional("stop_areas.txt"),
+            route_networks: files.read_optional("route_networks.txt")
+        }
+    }
+}
+
+pub fn insert_fares(tx: &mut Transaction, interner: &mut Interner, fares: &Fares) {
+    if let Some(f) = &fares.attributes {
+        insert_fare_attributes(tx, interner, f);
+    }
+
+    if let Some(r) = &fares.rules {
+        insert_fare_rules(tx, interner, r);
+    }
+
+    if let Some(m) = &fares.media {
+        insert_fare_media(tx, interner, m);
+    }
+
+    if let Some(p) = &fares.products {
+        insert_fare_products(tx, interner, p);
+    }
+
+    if let Some(r) = &fares.leg_rules {
+        insert_fare_leg_rules(tx, interner, r);
+    }
+
+    if let Some(a) = &fares.areas {
+        insert_areas(tx, interner, a);
+    }
+
+    if let Some(s) = &fares.stop_areas {
+        insert_stop_areas(tx, interner, s);
+    }
+
+    if let Some(r) = &fares.route_networks {
+        insert_route_networks(tx, interner, r);
+    }
+}
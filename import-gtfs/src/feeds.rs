@@ -0,0 +1,204 @@
+use chrono::{DateTime, Local, NaiveDate};
+use postgres::Client;
+use std::process::exit;
+
+// Feed uids are used as schema names and must be accepted by the REST API
+fn valid_feed_uid(feed_uid: &str) -> bool {
+    !feed_uid.is_empty()
+        && feed_uid
+            .chars()
+            .all(|ch| ch.is_ascii_alphanumeric() || ch == '_')
+}
+
+// The feed table is created by the first import
+fn feeds_table_exists(conn: &mut Client) -> bool {
+    let row = conn
+        .query_one(
+            "SELECT to_regclass('public.feeds') IS NOT NULL AS exists",
+            &[]
+        )
+        .expect("Error querying feeds");
+    row.get("exists")
+}
+
+pub fn ensure_feed_exists(conn: &mut Client, feed_uid: &str) {
+    if !feeds_table_exists(conn) {
+        eprintln!("No feeds imported");
+        exit(1);
+    }
+
+    let row = conn
+        .query_one(
+            "SELECT COUNT(*)::int AS cnt FROM public.feeds WHERE feed_uid = $1",
+            &[&feed_uid]
+        )
+        .expect("Error querying feeds");
+    let cnt: i32 = row.get("cnt");
+    if cnt != 1 {
+        eprintln!("Feed {} not found in database", feed_uid);
+        exit(1);
+    }
+}
+
+pub fn list_feeds(conn: &mut Client) {
+    if !feeds_table_exists(conn) {
+        println!("No feeds imported");
+        return;
+    }
+
+    let rows = conn
+        .query(
+            "SELECT feed_uid, feed_title, feed_publisher_name FROM public.feeds ORDER BY feed_title",
+            &[]
+        )
+        .expect("Error querying feeds");
+
+    if rows.is_empty() {
+        println!("No feeds imported");
+        return;
+    }
+
+    for row in rows {
+        let feed_uid: String = row.get("feed_uid");
+        let feed_title: String = row.get("feed_title");
+        let publisher_name: String = row.get("feed_publisher_name");
+        println!("{}\t{}\t{}", feed_uid, feed_title, publisher_name);
+    }
+}
+
+pub fn delete_feed(conn: &mut Client, feed_uid: &str) {
+    ensure_feed_exists(conn, feed_uid);
+
+    let mut tx = conn.transaction().unwrap();
+    tx.execute(&format!("DROP SCHEMA \"{}\" CASCADE", feed_uid), &[])
+        .expect("Error dropping schema");
+    tx.execute("DELETE FROM public.feeds WHERE feed_uid = $1", &[&feed_uid])
+        .expect("Error deleting feed");
+    tx.commit().unwrap();
+
+    println!("Deleted feed {}", feed_uid);
+}
+
+pub fn rename_feed(conn: &mut Client, feed_uid: &str, title: Option<&str>, slug: Option<&str>) {
+    ensure_feed_exists(conn, feed_uid);
+
+    if title.is_none() && slug.is_none() {
+        eprintln!("Nothing to rename, use --title or --slug");
+        exit(1);
+    }
+
+    let mut tx = conn.transaction().unwrap();
+
+    if let Some(title) = title {
+        tx.execute(
+            "UPDATE public.feeds SET feed_title = $1 WHERE feed_uid = $2",
+            &[&title, &feed_uid]
+        )
+        .expect("Error changing feed title");
+        println!("Changed title of {} to {}", feed_uid, title);
+    }
+
+    if let Some(slug) = slug {
+        if !valid_feed_uid(slug) {
+            eprintln!("Slug must be alphanumeric");
+            exit(1);
+        }
+
+        tx.execute(
+            &format!("ALTER SCHEMA \"{}\" RENAME TO \"{}\"", feed_uid, slug),
+            &[]
+        )
+        .expect("Error renaming schema");
+        tx.execute(
+            "UPDATE public.feeds SET feed_uid = $1 WHERE feed_uid = $2",
+            &[&slug, &feed_uid]
+        )
+        .expect("Error changing feed uid");
+        println!("Changed uid of {} to {}", feed_uid, slug);
+    }
+
+    tx.commit().unwrap();
+}
+
+pub fn inspect_feed(conn: &mut Client, feed_uid: &str) {
+    ensure_feed_exists(conn, feed_uid);
+
+    let feed = conn
+        .query_one(
+            "SELECT feed_title, feed_publisher_name, feed_publisher_url, feed_version, feed_start_date, feed_end_date, imported_at,
+             COALESCE(schema_version, 1) AS schema_version FROM public.feeds WHERE feed_uid = $1",
+            &[&feed_uid]
+        )
+        .expect("Error querying feed");
+    let feed_title: String = feed.get("feed_title");
+    let publisher_name: String = feed.get("feed_publisher_name");
+    let publisher_url: String = feed.get("feed_publisher_url");
+
+    println!("Feed:        {}", feed_uid);
+    println!("Title:       {}", feed_title);
+    println!("Publisher:   {} {}", publisher_name, publisher_url);
+
+    let version: Option<String> = feed.get("feed_version");
+    if let Some(version) = version {
+        println!("Version:     {}", version);
+    }
+    let start_date: Option<NaiveDate> = feed.get("feed_start_date");
+    let end_date: Option<NaiveDate> = feed.get("feed_end_date");
+    if start_date.is_some() || end_date.is_some() {
+        println!(
+            "Valid:       {} - {}",
+            start_date.map(|d| d.to_string()).unwrap_or_default(),
+            end_date.map(|d| d.to_string()).unwrap_or_default()
+        );
+    }
+    let imported_at: Option<DateTime<Local>> = feed.get("imported_at");
+    if let Some(imported_at) = imported_at {
+        println!("Imported:    {}", imported_at.format("%Y-%m-%d %H:%M"));
+    }
+    let schema_version: i32 = feed.get("schema_version");
+    println!("Schema:      version {}", schema_version);
+
+    for table in ["routes", "trips", "stop_times"] {
+        let row = conn
+            .query_one(
+                &format!("SELECT COUNT(*) AS cnt FROM \"{}\".{}", feed_uid, table),
+                &[]
+            )
+            .expect("Error counting rows");
+        let cnt: i64 = row.get("cnt");
+        println!("{:<12} {}", format!("{}:", table), cnt);
+    }
+
+    let calendar_range = conn
+        .query_one(
+            &format!(
+                "SELECT MIN(d) AS first_date, MAX(d) AS last_date FROM
+                 (SELECT start_date AS d FROM \"{0}\".calendar
+                  UNION ALL SELECT end_date FROM \"{0}\".calendar
+                  UNION ALL SELECT date FROM \"{0}\".calendar_dates WHERE exception_type = 1) AS t",
+                feed_uid
+            ),
+            &[]
+        )
+        .expect("Error querying calendar range");
+    let first_date: Option<NaiveDate> = calendar_range.get("first_date");
+    let last_date: Option<NaiveDate> = calendar_range.get("last_date");
+    match (first_date, last_date) {
+        (Some(first_date), Some(last_date)) => {
+            println!("Calendar:    {} - {}", first_date, last_date)
+        }
+        _ => println!("Calendar:    empty")
+    }
+
+    // Tables and materialized views, each including its indices and toast data
+    let size = conn
+        .query_one(
+            "SELECT pg_size_pretty(COALESCE(SUM(pg_total_relation_size(c.oid)), 0)::BIGINT) AS size
+             FROM pg_class AS c JOIN pg_namespace AS n ON n.oid = c.relnamespace
+             WHERE n.nspname = $1 AND c.relkind IN ('r', 'm')",
+            &[&feed_uid]
+        )
+        .expect("Error querying schema size");
+    let size: String = size.get("size");
+    println!("Disk size:   {}", size);
+}
@@ -0,0 +1,450 @@
+// Filters applied while importing, only trips touching the filter and the
+// stops, shapes and services they use are imported
+use gtfs_structures::{Agency, LocationType, RawGtfs};
+use serde_plain::to_string;
+use std::collections::HashSet;
+use std::process::exit;
+
+use crate::default_agency_id;
+use crate::extra_files::{Level, Pathway, Transfer, Translation};
+use crate::fares::Fares;
+
+pub struct Filter {
+    // min_lon, min_lat, max_lon, max_lat
+    pub bbox: Option<[f64; 4]>,
+    pub route_types: Vec<i32>,
+    pub agencies: Vec<String>
+}
+
+impl Filter {
+    pub fn is_empty(&self) -> bool {
+        self.bbox.is_none() && self.route_types.is_empty() && self.agencies.is_empty()
+    }
+
+    fn contains(&self, lat: Option<f64>, lon: Option<f64>) -> bool {
+        match (self.bbox, lat, lon) {
+            (Some([min_lon, min_lat, max_lon, max_lat]), Some(lat), Some(lon)) => {
+                (min_lon..=max_lon).contains(&lon) && (min_lat..=max_lat).contains(&lat)
+            }
+            (Some(_), _, _) => false,
+            (None, _, _) => true
+        }
+    }
+}
+
+pub fn parse_bbox(bbox: &str) -> Result<[f64; 4], String> {
+    let values: Vec<f64> = bbox
+        .split(',')
+        .map(|v| v.trim().parse::<f64>())
+        .collect::<Result<_, _>>()
+        .map_err(|e| e.to_string())?;
+    match values[..] {
+        [min_lon, min_lat, max_lon, max_lat] if min_lon < max_lon && min_lat < max_lat => {
+            Ok([min_lon, min_lat, max_lon, max_lat])
+        }
+        _ => Err("expected min_lon,min_lat,max_lon,max_lat".to_string())
+    }
+}
+
+// Ids of the records which passed the filter. The files which are only read
+// while inserting are filtered with them.
+pub struct Kept {
+    agencies: HashSet<String>,
+    routes: HashSet<String>,
+    trips: HashSet<String>,
+    stops: HashSet<String>,
+    levels: HashSet<String>,
+    zones: HashSet<String>,
+    pathways: HashSet<String>
+}
+
+// Keeps the records accepted by keep and prints how many were kept
+fn retain<T>(name: &str, records: &mut Vec<T>, keep: impl FnMut(&T) -> bool) {
+    let count = records.len();
+    records.retain(keep);
+    println!("Filter kept {} of {} {}", records.len(), count, name);
+}
+
+// Whether the optional reference is empty or points to a kept record
+fn kept_or_none(ids: &HashSet<String>, id: &Option<String>) -> bool {
+    id.as_ref().is_none_or(|id| ids.contains(id))
+}
+
+impl Kept {
+    pub fn transfers(&self, transfers: &mut Vec<Transfer>) {
+        retain("transfers", transfers, |t| {
+            kept_or_none(&self.stops, &t.from_stop_id)
+                && kept_or_none(&self.stops, &t.to_stop_id)
+                && kept_or_none(&self.routes, &t.from_route_id)
+                && kept_or_none(&self.routes, &t.to_route_id)
+                && kept_or_none(&self.trips, &t.from_trip_id)
+                && kept_or_none(&self.trips, &t.to_trip_id)
+        });
+    }
+
+    pub fn levels(&self, levels: &mut Vec<Level>) {
+        retain("levels", levels, |l| self.levels.contains(&l.level_id));
+    }
+
+    pub fn pathways(&mut self, pathways: &mut Vec<Pathway>) {
+        retain("pathways", pathways, |p| {
+            self.stops.contains(&p.from_stop_id) && self.stops.contains(&p.to_stop_id)
+        });
+        self.pathways = pathways.iter().map(|p| p.pathway_id.clone()).collect();
+    }
+
+    // Translations of values are kept, translations of records if the record is
+    pub fn translations(&self, translations: &mut Vec<Translation>) {
+        retain("translations", translations, |t| {
+            let ids = match t.table_name.as_str() {
+                "agency" => &self.agencies,
+                "routes" => &self.routes,
+                "trips" | "stop_times" => &self.trips,
+                "stops" => &self.stops,
+                "levels" => &self.levels,
+                "pathways" => &self.pathways,
+                _ => return true
+            };
+            kept_or_none(ids, &t.record_id)
+        });
+    }
+
+    pub fn fares(&self, fares: &mut Fares) {
+        // Fares v1: rules of kept routes and zones, and the fares which still
+        // have rules or never had any
+        let mut ruled_fares: HashSet<String> = HashSet::new();
+        let mut kept_fares: HashSet<String> = HashSet::new();
+        if let Some(rules) = &mut fares.rules {
+            ruled_fares.extend(rules.iter().map(|r| r.fare_id.clone()));
+            retain("fare rules", rules, |r| {
+                kept_or_none(&self.routes, &r.route_id)
+                    && kept_or_none(&self.zones, &r.origin_id)
+                    && kept_or_none(&self.zones, &r.destination_id)
+                    && kept_or_none(&self.zones, &r.contains_id)
+            });
+            kept_fares.extend(rules.iter().map(|r| r.fare_id.clone()));
+        }
+        if let Some(attributes) = &mut fares.attributes {
+            retain("fare attributes", attributes, |a| {
+                kept_or_none(&self.agencies, &a.agency_id)
+                    && (!ruled_fares.contains(&a.id) || kept_fares.contains(&a.id))
+            });
+        }
+
+        // Fares v2: areas with kept stops, networks with kept routes and the
+        // leg rules, products and media which refer to them
+        let mut kept_areas: HashSet<String> = HashSet::new();
+        if let Some(stop_areas) = &mut fares.stop_areas {
+            retain("stop areas", stop_areas, |s| {
+                self.stops.contains(&s.stop_id)
+            });
+            kept_areas.extend(stop_areas.iter().map(|s| s.area_id.clone()));
+        }
+        if let Some(areas) = &mut fares.areas {
+            retain("areas", areas, |a| kept_areas.contains(&a.area_id));
+        }
+        let mut networks: HashSet<String> = HashSet::new();
+        let mut kept_networks: HashSet<String> = HashSet::new();
+        if let Some(route_networks) = &mut fares.route_networks {
+            networks.extend(route_networks.iter().map(|r| r.network_id.clone()));
+            retain("route networks", route_networks, |r| {
+                self.routes.contains(&r.route_id)
+            });
+            kept_networks.extend(route_networks.iter().map(|r| r.network_id.clone()));
+        }
+        let mut kept_products: HashSet<String> = HashSet::new();
+        if let Some(leg_rules) = &mut fares.leg_rules {
+            retain("fare leg rules", leg_rules, |r| {
+                kept_or_none(&kept_areas, &r.from_area_id)
+                    && kept_or_none(&kept_areas, &r.to_area_id)
+                    && r.network_id
+                        .as_ref()
+                        .is_none_or(|n| !networks.contains(n) || kept_networks.contains(n))
+            });
+            kept_products.extend(leg_rules.iter().map(|r| r.fare_product_id.clone()));
+        }
+        let mut kept_media: HashSet<String> = HashSet::new();
+        if let Some(products) = &mut fares.products {
+            retain("fare products", products, |p| {
+                kept_products.contains(&p.fare_product_id)
+            });
+            kept_media.extend(products.iter().filter_map(|p| p.fare_media_id.clone()));
+        }
+        if let Some(media) = &mut fares.media {
+            retain("fare media", media, |m| {
+                kept_media.contains(&m.fare_media_id)
+            });
+        }
+    }
+}
+
+pub fn filter_gtfs(gtfs: &mut RawGtfs, filter: &Filter) -> Option<Kept> {
+    let (Ok(agencies), Ok(routes), Ok(trips), Ok(stops), Ok(stop_times)) = (
+        gtfs.agencies.as_mut(),
+        gtfs.routes.as_mut(),
+        gtfs.trips.as_mut(),
+        gtfs.stops.as_mut(),
+        gtfs.stop_times.as_mut()
+    ) else {
+        return None;
+    };
+    let counts = (routes.len(), trips.len(), stops.len());
+
+    // Agencies are selected by id or by name, the only agency of a feed may
+    // have no id
+    let matches = |a: &Agency, f: &String| a.id.as_ref() == Some(f) || a.name == *f;
+    if let Some(unknown) = filter
+        .agencies
+        .iter()
+        .find(|f| !agencies.iter().any(|a| matches(a, f)))
+    {
+        eprintln!("No agency with the id or name {}", unknown);
+        exit(1);
+    }
+    let selected_agencies: HashSet<String> = agencies
+        .iter()
+        .filter(|a| filter.agencies.iter().any(|f| matches(a, f)))
+        .map(|a| a.id.clone().unwrap_or_default())
+        .collect();
+
+    // Routes without agency belong to the only agency of the feed
+    let default_agency = default_agency_id(agencies);
+    routes.retain(|r| {
+        let agency = r.agency_id.as_ref().or(default_agency.as_ref());
+        let route_type = to_string(&r.route_type)
+            .ok()
+            .and_then(|t| t.parse::<i32>().ok());
+        (filter.agencies.is_empty() || agency.is_some_and(|a| selected_agencies.contains(a)))
+            && (filter.route_types.is_empty()
+                || route_type.is_some_and(|t| filter.route_types.contains(&t)))
+    });
+    let route_ids: HashSet<String> = routes.iter().map(|r| r.id.clone()).collect();
+    trips.retain(|t| route_ids.contains(&t.route_id));
+
+    // Trips with at least one stop within the bounding box are kept completely
+    if filter.bbox.is_some() {
+        let inside: HashSet<&str> = stops
+            .iter()
+            .filter(|s| filter.contains(s.latitude, s.longitude))
+            .map(|s| s.id.as_str())
+            .collect();
+        let touching: HashSet<String> = stop_times
+            .iter()
+            .filter(|st| inside.contains(st.stop_id.as_str()))
+            .map(|st| st.trip_id.clone())
+            .collect();
+        trips.retain(|t| touching.contains(&t.id));
+    }
+
+    let trip_ids: HashSet<String> = trips.iter().map(|t| t.id.clone()).collect();
+    stop_times.retain(|st| trip_ids.contains(&st.trip_id));
+
+    let route_ids: HashSet<String> = trips.iter().map(|t| t.route_id.clone()).collect();
+    routes.retain(|r| route_ids.contains(&r.id));
+    let agency_ids: HashSet<String> = routes
+        .iter()
+        .filter_map(|r| r.agency_id.clone().or(default_agency.clone()))
+        .collect();
+    agencies.retain(|a| agency_ids.contains(&a.id.clone().unwrap_or_default()));
+
+    // Served stops with their stations, and the entrances, nodes and boarding
+    // areas within them
+    let mut stop_ids: HashSet<String> = stop_times.iter().map(|st| st.stop_id.clone()).collect();
+    loop {
+        let parents: Vec<String> = stops
+            .iter()
+            .filter(|s| stop_ids.contains(&s.id))
+            .filter_map(|s| s.parent_station.clone())
+            .filter(|p| !stop_ids.contains(p))
+            .collect();
+        if parents.is_empty() {
+            break;
+        }
+        stop_ids.extend(parents);
+    }
+    let children: Vec<String> = stops
+        .iter()
+        .filter(|s| {
+            matches!(
+                s.location_type,
+                LocationType::StationEntrance
+                    | LocationType::GenericNode
+                    | LocationType::BoardingArea
+            )
+        })
+        .filter(|s| {
+            s.parent_station
+                .as_ref()
+                .is_some_and(|p| stop_ids.contains(p))
+        })
+        .map(|s| s.id.clone())
+        .collect();
+    stop_ids.extend(children);
+    stops.retain(|s| stop_ids.contains(&s.id));
+
+    let shape_ids: HashSet<String> = trips.iter().filter_map(|t| t.shape_id.clone()).collect();
+    let service_ids: HashSet<String> = trips.iter().map(|t| t.service_id.clone()).collect();
+    let mut shape_counts = (0, 0);
+    let mut service_counts = (0, 0);
+
+    if let Some(Ok(shapes)) = gtfs.shapes.as_mut() {
+        shape_counts.0 = shapes.iter().map(|s| &s.id).collect::<HashSet<_>>().len();
+        shapes.retain(|s| shape_ids.contains(&s.id));
+        shape_counts.1 = shapes.iter().map(|s| &s.id).collect::<HashSet<_>>().len();
+    }
+
+    let mut services: HashSet<String> = HashSet::new();
+    if let Some(Ok(calendar)) = gtfs.calendar.as_mut() {
+        services.extend(calendar.iter().map(|c| c.id.clone()));
+        calendar.retain(|c| service_ids.contains(&c.id));
+    }
+    if let Some(Ok(calendar_dates)) = gtfs.calendar_dates.as_mut() {
+        services.extend(calendar_dates.iter().map(|c| c.service_id.clone()));
+        calendar_dates.retain(|c| service_ids.contains(&c.service_id));
+    }
+    service_counts.0 = services.len();
+    service_counts.1 = services.intersection(&service_ids).count();
+
+    if let Some(Ok(frequencies)) = gtfs.frequencies.as_mut() {
+        frequencies.retain(|f| trip_ids.contains(&f.trip_id));
+    }
+
+    println!(
+        "Filter kept {} of {} routes, {} of {} trips, {} of {} stops, {} of {} shapes and {} of {} services",
+        routes.len(),
+        counts.0,
+        trips.len(),
+        counts.1,
+        stops.len(),
+        counts.2,
+        shape_counts.1,
+        shape_counts.0,
+        service_counts.1,
+        service_counts.0
+    );
+
+    if trips.is_empty() {
+        eprintln!("No trips match the filter");
+        exit(1);
+    }
+
+    Some(Kept {
+        agencies: agencies.iter().filter_map(|a| a.id.clone()).collect(),
+        routes: route_ids,
+        levels: stops.iter().filter_map(|s| s.level_id.clone()).collect(),
+        zones: stops.iter().filter_map(|s| s.zone_id.clone()).collect(),
+        stops: stop_ids,
+        trips: trip_ids,
+        pathways: HashSet::new()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::extra_files::GtfsFiles;
+    use std::path::Path;
+
+    // Bus route R1 from the platform P1 of the station STA to S2 and rail
+    // route R2 from S3 to the platform P4 of the station STB. The only
+    // agency has no agency_id.
+    const FEED: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/testdata/filter");
+
+    fn read_feed() -> RawGtfs {
+        gtfs_structures::GtfsReader::default()
+            .raw()
+            .read_from_path(FEED)
+            .unwrap()
+    }
+
+    fn filter(bbox: Option<[f64; 4]>, route_types: &[i32], agencies: &[&str]) -> Filter {
+        Filter {
+            bbox,
+            route_types: route_types.to_vec(),
+            agencies: agencies.iter().map(|a| a.to_string()).collect()
+        }
+    }
+
+    fn ids<'a>(ids: impl Iterator<Item = &'a String>) -> Vec<&'a str> {
+        let mut ids: Vec<&str> = ids.map(|id| id.as_str()).collect();
+        ids.sort();
+        ids
+    }
+
+    fn stop_ids(gtfs: &RawGtfs) -> Vec<&str> {
+        ids(gtfs.stops.as_ref().unwrap().iter().map(|s| &s.id))
+    }
+
+    #[test]
+    fn bbox_keeps_stations_and_their_entrances() {
+        let mut gtfs = read_feed();
+        let kept = filter_gtfs(
+            &mut gtfs,
+            &filter(Some([10.9, 47.9, 11.05, 48.05]), &[], &[])
+        );
+        assert!(kept.is_some());
+        assert_eq!(
+            ids(gtfs.trips.as_ref().unwrap().iter().map(|t| &t.id)),
+            ["T1"]
+        );
+        assert_eq!(stop_ids(&gtfs), ["BA1", "E1", "P1", "S2", "STA"]);
+        let calendar = gtfs.calendar.as_ref().unwrap().as_ref().unwrap();
+        assert_eq!(ids(calendar.iter().map(|c| &c.id)), ["WD"]);
+    }
+
+    #[test]
+    fn route_types_keep_parents_of_platforms() {
+        let mut gtfs = read_feed();
+        filter_gtfs(&mut gtfs, &filter(None, &[2], &[]));
+        assert_eq!(
+            ids(gtfs.routes.as_ref().unwrap().iter().map(|r| &r.id)),
+            ["R2"]
+        );
+        assert_eq!(stop_ids(&gtfs), ["P4", "S3", "STB"]);
+    }
+
+    #[test]
+    fn agency_without_id_is_selected_by_name() {
+        let mut gtfs = read_feed();
+        filter_gtfs(&mut gtfs, &filter(None, &[], &["City Transit"]));
+        assert_eq!(gtfs.routes.as_ref().unwrap().len(), 2);
+        assert_eq!(gtfs.agencies.as_ref().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn fares_of_kept_routes_and_stops() {
+        let mut gtfs = read_feed();
+        let kept = filter_gtfs(&mut gtfs, &filter(None, &[3], &[])).unwrap();
+        let mut fares = Fares::read(
+            gtfs.fare_attributes.take().and_then(|f| f.ok()),
+            &mut GtfsFiles::open(Path::new(FEED), &[])
+        );
+        kept.fares(&mut fares);
+
+        // Fares v1: F2 only has a rule for the dropped route, F3 has no rules
+        let attributes = fares.attributes.unwrap();
+        assert_eq!(ids(attributes.iter().map(|a| &a.id)), ["F1", "F3"]);
+        let rules = fares.rules.unwrap();
+        assert_eq!(ids(rules.iter().map(|r| &r.fare_id)), ["F1"]);
+
+        // Fares v2: the airport area, network, leg rule, product and medium
+        // are dropped
+        assert_eq!(ids(fares.areas.unwrap().iter().map(|a| &a.area_id)), ["A1"]);
+        let route_networks = fares.route_networks.unwrap();
+        assert_eq!(ids(route_networks.iter().map(|r| &r.network_id)), ["N1"]);
+        let leg_rules = fares.leg_rules.unwrap();
+        assert_eq!(
+            ids(leg_rules.iter().map(|r| &r.fare_product_id)),
+            ["PR1", "PR3"]
+        );
+        let products = fares.products.unwrap();
+        assert_eq!(
+            ids(products.iter().map(|p| &p.fare_product_id)),
+            ["PR1", "PR3"]
+        );
+        assert_eq!(
+            ids(fares.media.unwrap().iter().map(|m| &m.fare_media_id)),
+            ["M1"]
+        );
+    }
+}
@@ -1,45 +1,456 @@
-use gtfs_structures::{Calendar, CalendarDate, RawStopTime, RawTrip, Route, Shape, Stop};
+use chrono::NaiveDate;
+use gtfs_structures::{
+    Agency, Calendar, CalendarDate, RawFrequency, RawGtfs, RawStopTime, RawTrip, Route, Shape, Stop
+};
 use postgres::{Client, Transaction};
 use rgb::RGB8;
 use serde_plain::to_string;
-use std::path::PathBuf;
+use std::collections::HashMap;
+use std::io::{Cursor, Read};
+use std::path::{Path, PathBuf};
+use std::process::exit;
+use std::rc::Rc;
 use string_interner::{backend::StringBackend, symbol::SymbolU32, StringInterner, Symbol};
 use structopt::StructOpt;
 use uuid::Uuid;
 
-type Interner = StringInterner<StringBackend<SymbolU32>>;
+// Maps GTFS ids to OIDs. When several feeds are merged, the ids of each
+// feed are prefixed with its namespace to keep them apart.
+#[derive(Default)]
+pub struct Interner {
+    strings: StringInterner<StringBackend<SymbolU32>>,
+    namespace: Option<String>
+}
+
+impl Interner {
+    pub fn get_or_intern<T: AsRef<str>>(&mut self, id: T) -> SymbolU32 {
+        match &self.namespace {
+            Some(namespace) => self
+                .strings
+                .get_or_intern(format!("{}:{}", namespace, id.as_ref())),
+            None => self.strings.get_or_intern(id)
+        }
+    }
+
+    pub fn set_namespace(&mut self, namespace: &str) {
+        self.namespace = Some(namespace.to_string());
+    }
+
+    // All interned ids with their OIDs
+    pub fn ids(&self) -> impl Iterator<Item = (u32, &str)> {
+        self.strings
+            .into_iter()
+            .map(|(symbol, id)| (symbol.to_usize() as u32, id))
+    }
+}
+
+mod export;
+mod extra_files;
+mod fares;
+mod feeds;
+mod filter;
+mod map_matching;
+mod migrations;
+mod netex;
+mod osm;
+mod shapes;
+mod stations;
+mod validation;
+
+use extra_files::{GtfsFiles, Level, Pathway, Transfer, Translation};
 
 pub fn optional_color_to_string(color: &Option<RGB8>) -> Option<String> {
-    match color {
-        None => None,
-        Some(RGB8 { r, g, b }) => Some(format!("{:02X}{:02X}{:02X}", r, g, b))
+    color
+        .as_ref()
+        .map(|RGB8 { r, g, b }| format!("{:02X}{:02X}{:02X}", r, g, b))
+}
+
+#[derive(Debug, StructOpt)]
+#[structopt(
+    name = "import-gtfs",
+    about = "Import and manage GTFS feeds in postgresql"
+)]
+enum Opt {
+    /// Import a zipped GTFS feed into postgresql
+    Import(ImportOpt),
+
+    /// Import NeTEx XML files (EPIP or Nordic profile) into postgresql
+    ImportNetex(NetexOpt),
+
+    /// List all imported feeds
+    List { database: String },
+
+    /// Delete a feed with all its data
+    Delete { database: String, feed_uid: String },
+
+    /// Change the title or the uid (slug) of a feed
+    Rename {
+        database: String,
+        feed_uid: String,
+
+        #[structopt(short = "t", long = "title")]
+        title: Option<String>,
+
+        #[structopt(short = "s", long = "slug")]
+        slug: Option<String>
+    },
+
+    /// Print statistics of a feed
+    Inspect { database: String, feed_uid: String },
+
+    /// Write a feed as GTFS zip file, optionally only a part of it
+    Export(ExportOpt),
+
+    /// Merge several GTFS feeds into one feed with namespaced ids
+    Merge(MergeOpt),
+
+    /// Upgrade the schema of all feeds or a single feed to the current version
+    Migrate {
+        database: String,
+        feed_uid: Option<String>
     }
 }
 
 #[derive(Debug, StructOpt)]
-#[structopt(name = "Import a zipped GTFS feed into postgresql")]
-struct Opt {
+struct ImportOpt {
+    /// GTFS zip file, unzipped GTFS directory or - to read a zip file from stdin
     #[structopt(parse(from_os_str))]
     input_gtfs_data: PathBuf,
 
     output_database: String,
 
     #[structopt(short = "f", long = "feed-title")]
-    feed_title: String
+    feed_title: String,
+
+    /// Write the validation report as JSON to this file
+    #[structopt(long = "report", parse(from_os_str))]
+    report: Option<PathBuf>,
+
+    /// Abort the import if the validation finds errors
+    #[structopt(long = "strict")]
+    strict: bool,
+
+    /// Do not import an optional file, e.g. --skip transfers.txt
+    #[structopt(long = "skip", number_of_values = 1)]
+    skip: Vec<String>,
+
+    /// Only import trips with a stop within min_lon,min_lat,max_lon,max_lat
+    #[structopt(long = "bbox", parse(try_from_str = filter::parse_bbox))]
+    bbox: Option<[f64; 4]>,
+
+    /// Only import routes of these route types, e.g. --route-types 0,1,2
+    #[structopt(long = "route-types", use_delimiter = true)]
+    route_types: Vec<i32>,
+
+    /// Only import routes of this agency id or name, can be repeated
+    #[structopt(long = "agency", number_of_values = 1)]
+    agencies: Vec<String>,
+
+    /// Generate shapes for trips without shapes by routing over this OpenStreetMap extract (.osm.pbf)
+    #[structopt(long = "osm", parse(from_os_str))]
+    osm: Option<PathBuf>,
+
+    /// Group nearby stops with similar names without a parent station into generated stations
+    #[structopt(long = "cluster-stops")]
+    cluster_stops: bool,
+
+    /// Maximum distance in meters between stops of a generated station
+    #[structopt(long = "stop-distance", default_value = "100")]
+    stop_distance: f64,
+
+    /// Minimum similarity (0 - 1) of the names of stops in a generated station
+    #[structopt(long = "name-similarity", default_value = "0.8")]
+    name_similarity: f64
 }
 
-fn insert_routes(tx: &mut Transaction, interner: &mut Interner, routes: &Vec<Route>) {
+#[derive(Debug, StructOpt)]
+struct NetexOpt {
+    /// NeTEx XML file, directory of XML files or zip file of XML files
+    #[structopt(parse(from_os_str))]
+    input_netex_data: PathBuf,
+
+    output_database: String,
+
+    #[structopt(short = "f", long = "feed-title")]
+    feed_title: String,
+
+    /// Write the validation report as JSON to this file
+    #[structopt(long = "report", parse(from_os_str))]
+    report: Option<PathBuf>,
+
+    /// Abort the import if the validation finds errors
+    #[structopt(long = "strict")]
+    strict: bool,
+
+    /// Time zone of the timetables if the NeTEx data does not set one, e.g. Europe/Oslo
+    #[structopt(long = "timezone")]
+    timezone: Option<String>
+}
+
+#[derive(Debug, StructOpt)]
+struct ExportOpt {
+    database: String,
+    feed_uid: String,
+
+    #[structopt(parse(from_os_str))]
+    output_gtfs_zip: PathBuf,
+
+    /// Only export stops within min_lon,min_lat,max_lon,max_lat and trips between them
+    #[structopt(long = "bbox", parse(try_from_str = filter::parse_bbox))]
+    bbox: Option<[f64; 4]>,
+
+    /// Only export services from this date (YYYY-MM-DD)
+    #[structopt(long = "start-date")]
+    start_date: Option<NaiveDate>,
+
+    /// Only export services until this date (YYYY-MM-DD)
+    #[structopt(long = "end-date")]
+    end_date: Option<NaiveDate>,
+
+    /// Only export routes of this agency id, can be repeated
+    #[structopt(long = "agency", number_of_values = 1)]
+    agencies: Vec<String>,
+
+    /// Only export routes of this route type, can be repeated
+    #[structopt(long = "route-type", number_of_values = 1)]
+    route_types: Vec<i32>
+}
+
+#[derive(Debug, StructOpt)]
+struct MergeOpt {
+    output_database: String,
+
+    #[structopt(short = "f", long = "feed-title")]
+    feed_title: String,
+
+    /// GTFS zip files or directories, optionally prefixed with a namespace: rail=rail.zip
+    #[structopt(required = true, min_values = 2)]
+    inputs: Vec<String>,
+
+    /// Maximum distance in meters between stops of different feeds which are unified
+    #[structopt(long = "stop-distance", default_value = "100")]
+    stop_distance: f64,
+
+    /// Minimum similarity (0 - 1) of the names of stops which are unified
+    #[structopt(long = "name-similarity", default_value = "0.8")]
+    name_similarity: f64,
+
+    /// Abort the import if the validation of a feed finds errors
+    #[structopt(long = "strict")]
+    strict: bool,
+
+    /// Do not import an optional file, e.g. --skip transfers.txt
+    #[structopt(long = "skip", number_of_values = 1)]
+    skip: Vec<String>
+}
+
+const REQUIRED_FILES: [&str; 5] = [
+    "agency.txt",
+    "stops.txt",
+    "routes.txt",
+    "trips.txt",
+    "stop_times.txt"
+];
+
+// Number of OPTIONAL_FILES read by gtfs-structures
+const PARSED_OPTIONAL_FILES: usize = 6;
+
+// Files which can be skipped, the first ones are read by gtfs-structures
+const OPTIONAL_FILES: [&str; 17] = [
+    "calendar.txt",
+    "calendar_dates.txt",
+    "shapes.txt",
+    "fare_attributes.txt",
+    "frequencies.txt",
+    "feed_info.txt",
+    "transfers.txt",
+    "levels.txt",
+    "pathways.txt",
+    "translations.txt",
+    "fare_rules.txt",
+    "fare_media.txt",
+    "fare_products.txt",
+    "fare_leg_rules.txt",
+    "areas.txt",
+    "stop_areas.txt",
+    "route_networks.txt"
+];
+
+fn read_gtfs(input: &Path, skip: &[String]) -> (RawGtfs, GtfsFiles) {
+    if let Some(file) = skip.iter().find(|f| REQUIRED_FILES.contains(&f.as_str())) {
+        eprintln!("{} is required and cannot be skipped", file);
+        exit(1);
+    }
+    if let Some(file) = skip.iter().find(|f| !OPTIONAL_FILES.contains(&f.as_str())) {
+        eprintln!(
+            "{} is not an optional GTFS file, it can be one of {}",
+            file,
+            OPTIONAL_FILES.join(", ")
+        );
+        exit(1);
+    }
+
+    let reader = gtfs_structures::GtfsReader::default().raw();
+
+    let (gtfs, extra_files) = if input == Path::new("-") {
+        println!("Reading GTFS zip file from stdin...");
+        let mut data = Vec::new();
+        std::io::stdin()
+            .read_to_end(&mut data)
+            .expect("Error reading from stdin");
+        let data: Rc<[u8]> = data.into();
+        let mut extra_files = GtfsFiles::from_bytes(data.clone(), skip);
+        if skips_parsed_file(skip) {
+            (
+                reader.read_from_reader(Cursor::new(extra_files.without_skipped())),
+                extra_files
+            )
+        } else {
+            (reader.read_from_reader(Cursor::new(data)), extra_files)
+        }
+    } else if input.exists() {
+        println!("Reading GTFS data...");
+        let mut extra_files = GtfsFiles::open(input, skip);
+        if skips_parsed_file(skip) {
+            (
+                reader.read_from_reader(Cursor::new(extra_files.without_skipped())),
+                extra_files
+            )
+        } else {
+            (
+                reader.read_from_path(input.to_string_lossy().as_ref()),
+                extra_files
+            )
+        }
+    } else {
+        eprintln!("{} does not exist", input.display());
+        exit(1);
+    };
+
+    let mut gtfs = gtfs.unwrap_or_else(|e| {
+        eprintln!("Error reading GTFS data: {}", e);
+        exit(1);
+    });
+
+    let missing_files = [
+        ("agency.txt", gtfs.agencies.as_ref().err()),
+        ("stops.txt", gtfs.stops.as_ref().err()),
+        ("routes.txt", gtfs.routes.as_ref().err()),
+        ("trips.txt", gtfs.trips.as_ref().err()),
+        ("stop_times.txt", gtfs.stop_times.as_ref().err())
+    ]
+    .into_iter()
+    .filter(|(_, e)| matches!(e, Some(gtfs_structures::Error::MissingFile(_))))
+    .map(|(file_name, _)| file_name)
+    .collect::<Vec<_>>();
+    if !missing_files.is_empty() {
+        eprintln!(
+            "Missing required file(s) in {}: {}",
+            input.display(),
+            missing_files.join(", ")
+        );
+        exit(1);
+    }
+    if gtfs.calendar.is_none() && gtfs.calendar_dates.is_none() {
+        eprintln!(
+            "Missing required file in {}: calendar.txt or calendar_dates.txt",
+            input.display()
+        );
+        exit(1);
+    }
+
+    // Trips may still refer to the shapes of a skipped shapes.txt
+    for file in skip {
+        drop_optional_file(&mut gtfs, file);
+    }
+
+    (gtfs, extra_files)
+}
+
+// gtfs-structures parses all of its files, they are left out of the feed it
+// reads when one of them is skipped
+fn skips_parsed_file(skip: &[String]) -> bool {
+    skip.iter()
+        .any(|f| OPTIONAL_FILES[..PARSED_OPTIONAL_FILES].contains(&f.as_str()))
+}
+
+// Removes an optional file read by gtfs-structures as if it was not present
+fn drop_optional_file(gtfs: &mut RawGtfs, file: &str) {
+    match file {
+        "calendar.txt" => gtfs.calendar = None,
+        "calendar_dates.txt" => gtfs.calendar_dates = None,
+        "shapes.txt" => {
+            gtfs.shapes = None;
+            if let Ok(trips) = &mut gtfs.trips {
+                trips.iter_mut().for_each(|t| t.shape_id = None);
+            }
+        }
+        "fare_attributes.txt" => gtfs.fare_attributes = None,
+        "frequencies.txt" => gtfs.frequencies = None,
+        "feed_info.txt" => gtfs.feed_info = None,
+        _ => {}
+    }
+}
+
+fn connect(database: &str) -> Client {
+    Client::connect(database, postgres::NoTls).expect("Cannot connect to postgresql")
+}
+
+// The agency_id may be omitted when a feed contains only one agency
+fn default_agency_id(agencies: &[Agency]) -> Option<String> {
+    match agencies {
+        [agency] => Some(agency.id.clone().unwrap_or_default()),
+        _ => None
+    }
+}
+
+fn insert_agencies(tx: &mut Transaction, interner: &mut Interner, agencies: &Vec<Agency>) {
+    println!("Import {} agencies...", &agencies.len());
+
+    let stmt = tx.prepare(
+      "INSERT INTO agency (agency_id, agency_name, agency_url, agency_timezone, agency_lang, agency_phone, agency_fare_url)
+       VALUES ($1, $2, $3, $4, $5, $6, $7)").unwrap();
+
+    for a in agencies {
+        tx.execute(
+            &stmt,
+            &[
+                &(interner
+                    .get_or_intern(a.id.as_deref().unwrap_or_default())
+                    .to_usize() as u32),
+                &a.name,
+                &a.url,
+                &a.timezone,
+                &a.lang,
+                &a.phone,
+                &a.fare_url
+            ]
+        )
+        .unwrap();
+    }
+}
+
+fn insert_routes(
+    tx: &mut Transaction,
+    interner: &mut Interner,
+    routes: &Vec<Route>,
+    default_agency_id: Option<&str>
+) {
     println!("Import {} routes...", &routes.len());
 
     let stmt = tx.prepare(
-      "INSERT INTO routes (route_id, route_short_name, route_long_name, route_desc, route_type, route_text_color, route_color, route_sort_order)
-       VALUES ($1, $2, $3, $4, $5, $6, $7, $8)").unwrap();
+      "INSERT INTO routes (route_id, agency_id, route_short_name, route_long_name, route_desc, route_type, route_text_color, route_color, route_sort_order)
+       VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)").unwrap();
 
     for r in routes {
         tx.execute(
             &stmt,
             &[
                 &(interner.get_or_intern(&r.id).to_usize() as u32),
+                &r.agency_id
+                    .as_deref()
+                    .or(default_agency_id)
+                    .map(|agency_id| interner.get_or_intern(agency_id).to_usize() as u32),
                 &r.short_name,
                 &r.long_name,
                 &r.desc,
@@ -59,7 +470,8 @@ fn insert_trips(tx: &mut Transaction, interner: &mut Interner, trips: &Vec<RawTr
     println!("Import {} trips...", &trips.len());
 
     let stmt = tx.prepare(
-      "INSERT INTO trips (trip_id, route_id, service_id, shape_id, trip_headsign, direction_id) VALUES ($1, $2, $3, $4, $5, $6)").unwrap();
+      "INSERT INTO trips (trip_id, route_id, service_id, shape_id, trip_headsign, direction_id, wheelchair_accessible, bikes_allowed)
+       VALUES ($1, $2, $3, $4, $5, $6, $7, $8)").unwrap();
 
     for trip in trips {
         tx.execute(
@@ -71,9 +483,15 @@ fn insert_trips(tx: &mut Transaction, interner: &mut Interner, trips: &Vec<RawTr
                 &trip
                     .shape_id
                     .as_ref()
-                    .map(|shape_id| interner.get_or_intern(&shape_id).to_usize() as u32),
+                    .map(|shape_id| interner.get_or_intern(shape_id).to_usize() as u32),
                 &trip.trip_headsign,
                 &to_string(&trip.direction_id)
+                    .ok()
+                    .and_then(|x| x.parse::<i32>().ok()),
+                &to_string(&trip.wheelchair_accessible)
+                    .ok()
+                    .and_then(|x| x.parse::<i32>().ok()),
+                &to_string(&trip.bikes_allowed)
                     .ok()
                     .and_then(|x| x.parse::<i32>().ok())
             ]
@@ -86,21 +504,61 @@ fn insert_stops(tx: &mut Transaction, interner: &mut Interner, stops: &Vec<Stop>
     println!("Import {} stops...", &stops.len());
 
     let stmt = tx.prepare(
-      "INSERT INTO stops (stop_id, stop_name, stop_lat, stop_lon, parent_station, platform_code) VALUES ($1, $2, $3, $4, $5, $6)").unwrap();
+      "INSERT INTO stops (stop_id, stop_name, stop_lat, stop_lon, location_type, parent_station, platform_code, level_id, zone_id, wheelchair_boarding)
+       VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)").unwrap();
+
+    // Generic nodes and boarding areas may omit their coordinates,
+    // we place them at their parent station
+    let stops_by_id: HashMap<&str, &Stop> = stops.iter().map(|s| (s.id.as_str(), s)).collect();
+    let coordinates = |stop: &Stop| {
+        let mut current = Some(stop);
+        while let Some(s) = current {
+            if let (Some(lat), Some(lon)) = (s.latitude, s.longitude) {
+                return Some((lat, lon));
+            }
+            current = s
+                .parent_station
+                .as_ref()
+                .and_then(|parent_id| stops_by_id.get(parent_id.as_str()).copied());
+        }
+        None
+    };
 
     for stop in stops {
+        let (lat, lon) = match coordinates(stop) {
+            Some(c) => c,
+            None => {
+                println!("Skip stop {} without coordinates", stop.id);
+                continue;
+            }
+        };
+
         tx.execute(
             &stmt,
             &[
                 &(interner.get_or_intern(&stop.id).to_usize() as u32),
                 &stop.name,
-                &stop.latitude,
-                &stop.longitude,
+                &lat,
+                &lon,
+                &to_string(&stop.location_type)
+                    .ok()
+                    .and_then(|x| x.parse::<i32>().ok()),
                 &stop
                     .parent_station
                     .as_ref()
-                    .map(|parent_id| interner.get_or_intern(&parent_id).to_usize() as u32),
-                &stop.platform_code
+                    .map(|parent_id| interner.get_or_intern(parent_id).to_usize() as u32),
+                &stop.platform_code,
+                &stop
+                    .level_id
+                    .as_ref()
+                    .map(|level_id| interner.get_or_intern(level_id).to_usize() as u32),
+                &stop
+                    .zone_id
+                    .as_ref()
+                    .map(|zone_id| interner.get_or_intern(zone_id).to_usize() as u32),
+                &to_string(&stop.wheelchair_boarding)
+                    .ok()
+                    .and_then(|x| x.parse::<i32>().ok())
             ]
         )
         .unwrap();
@@ -111,7 +569,7 @@ fn insert_stop_times(tx: &mut Transaction, interner: &mut Interner, stop_times:
     println!("Import {} stop times...", &stop_times.len());
 
     let stmt = tx.prepare(
-      "INSERT INTO stop_times (trip_id, arrival_time, departure_time, stop_id, stop_sequence, shape_dist_traveled) VALUES ($1, $2, $3, $4, $5, $6)").unwrap();
+      "INSERT INTO stop_times (trip_id, arrival_time, departure_time, stop_id, stop_sequence, shape_dist_traveled, stop_headsign) VALUES ($1, $2, $3, $4, $5, $6, $7)").unwrap();
 
     for st in stop_times {
         tx.execute(
@@ -122,7 +580,8 @@ fn insert_stop_times(tx: &mut Transaction, interner: &mut Interner, stop_times:
                 &st.departure_time.map(|x| x as i32),
                 &(interner.get_or_intern(&st.stop_id).to_usize() as u32),
                 &(st.stop_sequence as i32),
-                &st.shape_dist_traveled
+                &st.shape_dist_traveled,
+                &st.stop_headsign
             ]
         )
         .unwrap();
@@ -186,7 +645,7 @@ fn insert_calendar_dates(
 
 fn insert_shapes(tx: &mut Transaction, interner: &mut Interner, shape_points: &Vec<Shape>) {
     println!("Import {} shape points...", &shape_points.len());
-    
+
     let stmt = tx.prepare(
       "INSERT INTO shapes (shape_id, shape_pt_lat, shape_pt_lon, shape_pt_sequence, shape_dist_traveled) VALUES ($1, $2, $3, $4, $5)").unwrap();
     for sp in shape_points {
@@ -204,83 +663,282 @@ fn insert_shapes(tx: &mut Transaction, interner: &mut Interner, shape_points: &V
     }
 }
 
-fn insert_feed(
+fn insert_frequencies(
+    tx: &mut Transaction,
+    interner: &mut Interner,
+    frequencies: &Vec<RawFrequency>
+) {
+    println!("Import {} frequencies...", &frequencies.len());
+
+    let stmt = tx.prepare(
+      "INSERT INTO frequencies (trip_id, start_time, end_time, headway_secs, exact_times) VALUES ($1, $2, $3, $4, $5)").unwrap();
+
+    for f in frequencies {
+        tx.execute(
+            &stmt,
+            &[
+                &(interner.get_or_intern(&f.trip_id).to_usize() as u32),
+                &(f.start_time as i32),
+                &(f.end_time as i32),
+                &(f.headway_secs as i32),
+                &f.exact_times.map_or(0, |x| x as i32)
+            ]
+        )
+        .unwrap();
+    }
+}
+
+fn insert_transfers(tx: &mut Transaction, interner: &mut Interner, transfers: &Vec<Transfer>) {
+    println!("Import {} transfers...", &transfers.len());
+
+    let stmt = tx.prepare(
+      "INSERT INTO transfers (from_stop_id, to_stop_id, from_route_id, to_route_id, from_trip_id, to_trip_id, transfer_type, min_transfer_time)
+       VALUES ($1, $2, $3, $4, $5, $6, $7, $8)").unwrap();
+
+    let mut intern = |id: &Option<String>| {
+        id.as_ref()
+            .map(|id| interner.get_or_intern(id).to_usize() as u32)
+    };
+
+    for t in transfers {
+        tx.execute(
+            &stmt,
+            &[
+                &intern(&t.from_stop_id),
+                &intern(&t.to_stop_id),
+                &intern(&t.from_route_id),
+                &intern(&t.to_route_id),
+                &intern(&t.from_trip_id),
+                &intern(&t.to_trip_id),
+                &t.transfer_type.unwrap_or(0),
+                &t.min_transfer_time
+            ]
+        )
+        .unwrap();
+    }
+}
+
+fn insert_levels(tx: &mut Transaction, interner: &mut Interner, levels: &Vec<Level>) {
+    println!("Import {} levels...", &levels.len());
+
+    let stmt = tx
+        .prepare("INSERT INTO levels (level_id, level_index, level_name) VALUES ($1, $2, $3)")
+        .unwrap();
+
+    for l in levels {
+        tx.execute(
+            &stmt,
+            &[
+                &(interner.get_or_intern(&l.level_id).to_usize() as u32),
+                &l.level_index,
+                &l.level_name
+            ]
+        )
+        .unwrap();
+    }
+}
+
+fn insert_pathways(tx: &mut Transaction, interner: &mut Interner, pathways: &Vec<Pathway>) {
+    println!("Import {} pathways...", &pathways.len());
+
+    let stmt = tx.prepare(
+      "INSERT INTO pathways (pathway_id, from_stop_id, to_stop_id, pathway_mode, is_bidirectional, length, traversal_time, stair_count, max_slope, min_width, signposted_as, reversed_signposted_as)
+       VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)").unwrap();
+
+    for p in pathways {
+        tx.execute(
+            &stmt,
+            &[
+                &(interner.get_or_intern(&p.pathway_id).to_usize() as u32),
+                &(interner.get_or_intern(&p.from_stop_id).to_usize() as u32),
+                &(interner.get_or_intern(&p.to_stop_id).to_usize() as u32),
+                &p.pathway_mode,
+                &(p.is_bidirectional == 1),
+                &p.length,
+                &p.traversal_time,
+                &p.stair_count,
+                &p.max_slope,
+                &p.min_width,
+                &p.signposted_as,
+                &p.reversed_signposted_as
+            ]
+        )
+        .unwrap();
+    }
+}
+
+fn insert_translations(
     tx: &mut Transaction,
-    feed_uid: &str,
-    feed_title: &str,
-    publisher_name: &str,
-    publisher_url: &str
+    interner: &mut Interner,
+    translations: &Vec<Translation>
 ) {
+    println!("Import {} translations...", &translations.len());
+
+    let stmt = tx.prepare(
+      "INSERT INTO translations (table_name, field_name, language, translation, record_id, record_sub_id, field_value)
+       VALUES ($1, $2, $3, $4, $5, $6, $7)").unwrap();
+
+    for t in translations {
+        tx.execute(
+            &stmt,
+            &[
+                &t.table_name,
+                &t.field_name,
+                &t.language,
+                &t.translation,
+                &t.record_id
+                    .as_ref()
+                    .map(|record_id| interner.get_or_intern(record_id).to_usize() as u32),
+                &t.record_sub_id,
+                &t.field_value
+            ]
+        )
+        .unwrap();
+    }
+}
+
+// Contents of feed_info.txt
+#[derive(Default)]
+struct FeedMetadata {
+    publisher_name: String,
+    publisher_url: String,
+    lang: Option<String>,
+    version: Option<String>,
+    start_date: Option<NaiveDate>,
+    end_date: Option<NaiveDate>
+}
+
+fn insert_feed(tx: &mut Transaction, feed_uid: &str, feed_title: &str, feed: &FeedMetadata) {
     let stmt = tx.prepare(
-      "INSERT INTO public.feeds (feed_uid, feed_title, feed_publisher_name, feed_publisher_url) VALUES ($1, $2, $3, $4)").unwrap();
+      "INSERT INTO public.feeds (feed_uid, feed_title, feed_publisher_name, feed_publisher_url, feed_lang, feed_version, feed_start_date, feed_end_date, imported_at, schema_version)
+       VALUES ($1, $2, $3, $4, $5, $6, $7, $8, now(), $9)").unwrap();
     tx.execute(
         &stmt,
-        &[&feed_uid, &feed_title, &publisher_name, &publisher_url]
+        &[
+            &feed_uid,
+            &feed_title,
+            &feed.publisher_name,
+            &feed.publisher_url,
+            &feed.lang,
+            &feed.version,
+            &feed.start_date,
+            &feed.end_date,
+            &migrations::SCHEMA_VERSION
+        ]
     )
     .unwrap();
 }
 
-fn main() {
-    let opt = Opt::from_args();
-
-    let mut interner = Interner::default();
-    let mut conn = Client::connect(&opt.output_database, postgres::NoTls)
-        .expect("Cannot connect to postgresql");
+fn validate_gtfs(gtfs: &mut RawGtfs, report_path: Option<&Path>, strict: bool) {
+    let report = validation::validate(gtfs);
+    report.print_summary();
+    if let Some(path) = report_path {
+        report.write_json(path);
+    }
+    if strict && report.errors > 0 {
+        eprintln!("Validation failed, import aborted");
+        exit(1);
+    }
 
-    println!("Reading GTFS zip file...");
+    // Files which cannot be parsed are not imported, which is only possible
+    // for optional ones
+    let (required, optional): (Vec<&str>, Vec<&str>) = report
+        .unreadable_files()
+        .into_iter()
+        .partition(|file| REQUIRED_FILES.contains(file));
+    if !required.is_empty() {
+        eprintln!("Cannot read {}, import aborted", required.join(", "));
+        exit(1);
+    }
+    for file in &optional {
+        println!("Warning: {} cannot be read and is not imported", file);
+        drop_optional_file(gtfs, file);
+    }
+    if optional.iter().any(|file| file.starts_with("calendar"))
+        && gtfs.calendar.is_none()
+        && gtfs.calendar_dates.is_none()
+    {
+        eprintln!("Cannot read calendar.txt or calendar_dates.txt, import aborted");
+        exit(1);
+    }
+}
 
-    let gtfs = gtfs_structures::GtfsReader::default()
-        .raw()
-        .read(opt.input_gtfs_data.to_str().unwrap())
-        .expect("Error reading GTFS data");
+// An empty or unreadable feed_info.txt is reported by the validation and
+// treated as missing
+fn feed_metadata(gtfs: &RawGtfs) -> FeedMetadata {
+    match &gtfs.feed_info {
+        Some(Ok(feed_info)) if !feed_info.is_empty() => {
+            let fi = &feed_info[0];
+            FeedMetadata {
+                publisher_name: fi.name.clone(),
+                publisher_url: fi.url.clone(),
+                lang: Some(fi.lang.clone()).filter(|l| !l.is_empty()),
+                version: fi.version.clone(),
+                start_date: fi.start_date,
+                end_date: fi.end_date
+            }
+        }
+        _ => FeedMetadata::default()
+    }
+}
 
+fn create_feed_schema(tx: &mut Transaction) -> String {
     let feed_uid = "gtfs_".to_string() + &Uuid::new_v4().to_simple().to_string();
     println!("Feed-UID is {}", feed_uid);
 
     println!("Importing data to postgresql...");
 
-    let mut tx = conn.transaction().unwrap();
+    tx.execute(&format!("CREATE SCHEMA \"{}\"", feed_uid), &[])
+        .expect("Error creating schema");
+    tx.execute(&format!("SET search_path=\"{}\"", feed_uid), &[])
+        .expect("Error setting default schema path");
 
-    if !feed_uid.is_empty() {
-        tx.execute(&format!("CREATE SCHEMA \"{}\"", feed_uid), &[])
-            .expect("Error creating schema");
-        tx.execute(&format!("SET search_path=\"{}\"", feed_uid), &[])
-            .expect("Error setting default schema path");
-    }
+    migrations::create_feeds_table(tx);
 
     let create_tables_query = include_str!("sql/tables.sql");
     tx.batch_execute(create_tables_query)
         .expect("Error creating SQL tables");
 
-    if let Some(fitemp) = gtfs.feed_info {
-        let fitemp2 = fitemp.expect("Error reading GTFS feed info");
-        let fi = fitemp2.first().expect("Empty feed_info.txt");
-        insert_feed(&mut tx, &feed_uid, &opt.feed_title, &fi.name, &fi.url);
-    } else {
-        insert_feed(&mut tx, &feed_uid, &opt.feed_title, "", "");
-    }
+    feed_uid
+}
+
+fn insert_gtfs(
+    tx: &mut Transaction,
+    interner: &mut Interner,
+    mut gtfs: RawGtfs,
+    mut extra_files: GtfsFiles,
+    // Ids kept by the filter to filter the extra files with
+    mut kept: Option<filter::Kept>
+) {
+    shapes::fill_shape_distances(&mut gtfs);
+
+    let agencies = gtfs
+        .agencies
+        .expect("Error reading agencies from GTFS data");
+    insert_agencies(tx, interner, &agencies);
 
     insert_routes(
-        &mut tx,
-        &mut interner,
-        &gtfs.routes.expect("Error reading routes from GTFS data")
+        tx,
+        interner,
+        &gtfs.routes.expect("Error reading routes from GTFS data"),
+        default_agency_id(&agencies).as_deref()
     );
 
     insert_trips(
-        &mut tx,
-        &mut interner,
+        tx,
+        interner,
         &gtfs.trips.expect("Error reading trips from GTFS data")
     );
 
     insert_stops(
-        &mut tx,
-        &mut interner,
+        tx,
+        interner,
         &gtfs.stops.expect("Error reading stops from GTFS data")
     );
 
     insert_stop_times(
-        &mut tx,
-        &mut interner,
+        tx,
+        interner,
         &gtfs
             .stop_times
             .expect("Error reading stop_times from GTFS data")
@@ -288,28 +946,93 @@ fn main() {
 
     if let Some(c) = &gtfs.calendar {
         insert_calendar(
-            &mut tx,
-            &mut interner,
-            &c.as_ref().expect("Error reading GTFS calendar")
+            tx,
+            interner,
+            c.as_ref().expect("Error reading GTFS calendar")
         );
     }
 
     if let Some(cd) = &gtfs.calendar_dates {
         insert_calendar_dates(
-            &mut tx,
-            &mut interner,
-            &cd.as_ref().expect("Error reading GTFS calendar exceptions")
+            tx,
+            interner,
+            cd.as_ref().expect("Error reading GTFS calendar exceptions")
         );
     }
 
     if let Some(s) = &gtfs.shapes {
         insert_shapes(
-            &mut tx,
-            &mut interner,
-            &s.as_ref().expect("Error reading GTFS shape data")
+            tx,
+            interner,
+            s.as_ref().expect("Error reading GTFS shape data")
         );
     }
 
+    if let Some(f) = &gtfs.frequencies {
+        insert_frequencies(
+            tx,
+            interner,
+            f.as_ref().expect("Error reading GTFS frequencies")
+        );
+    }
+
+    if let Some(mut t) = extra_files.read_optional::<Transfer>("transfers.txt") {
+        if let Some(kept) = &kept {
+            kept.transfers(&mut t);
+        }
+        insert_transfers(tx, interner, &t);
+    }
+
+    if let Some(mut l) = extra_files.read_optional::<Level>("levels.txt") {
+        if let Some(kept) = &kept {
+            kept.levels(&mut l);
+        }
+        insert_levels(tx, interner, &l);
+    }
+
+    if let Some(mut p) = extra_files.read_optional::<Pathway>("pathways.txt") {
+        if let Some(kept) = &mut kept {
+            kept.pathways(&mut p);
+        }
+        insert_pathways(tx, interner, &p);
+    }
+
+    if let Some(mut t) = extra_files.read_optional::<Translation>("translations.txt") {
+        if let Some(kept) = &kept {
+            kept.translations(&mut t);
+        }
+        insert_translations(tx, interner, &t);
+    }
+
+    let mut fares = fares::Fares::read(
+        gtfs.fare_attributes
+            .map(|f| f.expect("Error reading GTFS fare attributes")),
+        &mut extra_files
+    );
+    if let Some(kept) = &kept {
+        kept.fares(&mut fares);
+    }
+    fares::insert_fares(tx, interner, &fares);
+}
+
+fn insert_ids(tx: &mut Transaction, interner: &Interner) {
+    let stmt = tx
+        .prepare("INSERT INTO ids (id, gtfs_id) VALUES ($1, $2)")
+        .unwrap();
+    for (id, gtfs_id) in interner.ids() {
+        tx.execute(&stmt, &[&id, &gtfs_id]).unwrap();
+    }
+}
+
+fn finish_import(mut tx: Transaction, interner: &Interner, feed_uid: &str) {
+    println!("Store original ids...");
+    insert_ids(&mut tx, interner);
+
+    println!("Compute calendar range and extent...");
+    let feed_extent_query = include_str!("sql/feed_extent.sql");
+    tx.execute(feed_extent_query, &[&feed_uid])
+        .expect("Error computing feed extent");
+
     println!("Create database indices...");
     let create_indices_query = include_str!("sql/indices.sql");
     tx.batch_execute(create_indices_query)
@@ -324,3 +1047,187 @@ fn main() {
 
     println!("Complete!");
 }
+
+fn import(opt: ImportOpt) {
+    let (mut gtfs, extra_files) = read_gtfs(&opt.input_gtfs_data, &opt.skip);
+    validate_gtfs(&mut gtfs, opt.report.as_deref(), opt.strict);
+
+    let filter = filter::Filter {
+        bbox: opt.bbox,
+        route_types: opt.route_types,
+        agencies: opt.agencies
+    };
+    let kept = if filter.is_empty() {
+        None
+    } else {
+        filter::filter_gtfs(&mut gtfs, &filter)
+    };
+
+    if let Some(osm) = &opt.osm {
+        if let Err(e) = map_matching::generate_shapes(&mut gtfs, osm) {
+            eprintln!("Error reading OpenStreetMap file {}: {}", osm.display(), e);
+            exit(1);
+        }
+    }
+
+    let mut conn = connect(&opt.output_database);
+    let mut tx = conn.transaction().unwrap();
+
+    let feed_uid = create_feed_schema(&mut tx);
+    insert_feed(&mut tx, &feed_uid, &opt.feed_title, &feed_metadata(&gtfs));
+
+    let mut interner = Interner::default();
+    let stops = match (&gtfs.stops, opt.cluster_stops) {
+        (Ok(s), true) => stations::top_level_stops(&mut interner, s, 0),
+        _ => Vec::new()
+    };
+
+    insert_gtfs(&mut tx, &mut interner, gtfs, extra_files, kept);
+
+    if opt.cluster_stops {
+        let clusters =
+            stations::cluster_stops(&stops, opt.stop_distance, opt.name_similarity, false);
+        interner.set_namespace("clustered");
+        stations::insert_parent_stations(&mut tx, &mut interner, &stops, &clusters);
+    }
+
+    finish_import(tx, &interner, &feed_uid);
+}
+
+fn import_netex(opt: NetexOpt) {
+    let mut gtfs = netex::read_netex(&opt.input_netex_data, opt.timezone.as_deref());
+    validate_gtfs(&mut gtfs, opt.report.as_deref(), opt.strict);
+
+    let mut conn = connect(&opt.output_database);
+    let mut tx = conn.transaction().unwrap();
+
+    let feed_uid = create_feed_schema(&mut tx);
+    insert_feed(&mut tx, &feed_uid, &opt.feed_title, &feed_metadata(&gtfs));
+
+    let mut interner = Interner::default();
+    insert_gtfs(&mut tx, &mut interner, gtfs, GtfsFiles::empty(), None);
+
+    finish_import(tx, &interner, &feed_uid);
+}
+
+// Splits "namespace=path" inputs, by default the file name is the namespace
+fn merge_inputs(inputs: &[String]) -> Vec<(String, PathBuf)> {
+    let mut result: Vec<(String, PathBuf)> = Vec::new();
+
+    for input in inputs {
+        let (namespace, path) = match input.split_once('=') {
+            Some((namespace, path)) => (namespace.to_string(), PathBuf::from(path)),
+            None => {
+                let path = PathBuf::from(input);
+                let stem = path
+                    .file_stem()
+                    .map(|s| s.to_string_lossy().to_string())
+                    .unwrap_or_default();
+                (stem, path)
+            }
+        };
+
+        if namespace.is_empty() || namespace.contains(':') {
+            eprintln!("Invalid namespace for {}", input);
+            exit(1);
+        }
+        if result.iter().any(|(n, _)| *n == namespace) {
+            eprintln!(
+                "Namespace {} is used twice, use namespace=path to rename it",
+                namespace
+            );
+            exit(1);
+        }
+
+        result.push((namespace, path));
+    }
+
+    result
+}
+
+fn merge(opt: MergeOpt) {
+    let inputs = merge_inputs(&opt.inputs);
+
+    let mut conn = connect(&opt.output_database);
+    let mut tx = conn.transaction().unwrap();
+    let feed_uid = create_feed_schema(&mut tx);
+
+    let mut interner = Interner::default();
+    let mut feeds: Vec<FeedMetadata> = Vec::new();
+    let mut stops = Vec::new();
+
+    for (source, (namespace, path)) in inputs.iter().enumerate() {
+        println!("Merge {} as {}...", path.display(), namespace);
+
+        let (mut gtfs, extra_files) = read_gtfs(path, &opt.skip);
+        validate_gtfs(&mut gtfs, None, opt.strict);
+
+        feeds.push(feed_metadata(&gtfs));
+
+        interner.set_namespace(namespace);
+        if let Ok(s) = &gtfs.stops {
+            stops.extend(stations::top_level_stops(&mut interner, s, source));
+        }
+
+        insert_gtfs(&mut tx, &mut interner, gtfs, extra_files, None);
+    }
+
+    let clusters = stations::cluster_stops(&stops, opt.stop_distance, opt.name_similarity, true);
+    interner.set_namespace("merged");
+    stations::insert_parent_stations(&mut tx, &mut interner, &stops, &clusters);
+
+    // The publisher of the first feed, valid while all feeds are valid
+    let start_dates: Option<Vec<NaiveDate>> = feeds.iter().map(|f| f.start_date).collect();
+    let end_dates: Option<Vec<NaiveDate>> = feeds.iter().map(|f| f.end_date).collect();
+    let first = feeds.swap_remove(0);
+    let feed = FeedMetadata {
+        start_date: start_dates.and_then(|d| d.into_iter().max()),
+        end_date: end_dates.and_then(|d| d.into_iter().min()),
+        version: None,
+        ..first
+    };
+    insert_feed(&mut tx, &feed_uid, &opt.feed_title, &feed);
+
+    finish_import(tx, &interner, &feed_uid);
+}
+
+fn main() {
+    match Opt::from_args() {
+        Opt::Import(opt) => import(opt),
+        Opt::ImportNetex(opt) => import_netex(opt),
+        Opt::List { database } => feeds::list_feeds(&mut connect(&database)),
+        Opt::Delete { database, feed_uid } => {
+            feeds::delete_feed(&mut connect(&database), &feed_uid)
+        }
+        Opt::Rename {
+            database,
+            feed_uid,
+            title,
+            slug
+        } => feeds::rename_feed(
+            &mut connect(&database),
+            &feed_uid,
+            title.as_deref(),
+            slug.as_deref()
+        ),
+        Opt::Inspect { database, feed_uid } => {
+            feeds::inspect_feed(&mut connect(&database), &feed_uid)
+        }
+        Opt::Export(opt) => export::export_feed(
+            &mut connect(&opt.database),
+            &opt.feed_uid,
+            &opt.output_gtfs_zip,
+            &export::Filter {
+                bbox: opt.bbox,
+                start_date: opt.start_date,
+                end_date: opt.end_date,
+                agencies: opt.agencies,
+                route_types: opt.route_types
+            }
+        ),
+        Opt::Merge(opt) => merge(opt),
+        Opt::Migrate { database, feed_uid } => {
+            migrations::migrate(&mut connect(&database), feed_uid.as_deref())
+        }
+    }
+}
@@ -0,0 +1,405 @@
+// Shapes for trips without shapes, routed over the ways of an OpenStreetMap extract
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::path::Path;
+
+use gtfs_structures::{RawGtfs, Shape};
+use serde_plain::to_string;
+
+use crate::osm::{self, Way};
+use crate::shapes::stop_coordinates;
+use crate::validation::distance;
+
+// Stops farther away from the network are not matched
+const MAX_SNAP_DISTANCE: f64 = 200.0; // m
+const GRID_CELL_SIZE: f64 = 0.002; // degrees
+
+// Routed coordinates between two stops, None if there is no route
+type SegmentPath = Option<Vec<(f64, f64)>>;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+enum Network {
+    Road,
+    Tram,
+    Subway,
+    Rail,
+    Ferry,
+    Funicular
+}
+
+fn network(route_type: i32) -> Option<Network> {
+    match route_type {
+        3 | 11 | 700..=899 => Some(Network::Road),
+        0 | 900..=999 => Some(Network::Tram),
+        1 | 400..=499 => Some(Network::Subway),
+        2 | 100..=199 => Some(Network::Rail),
+        4 | 1000..=1099 | 1200..=1299 => Some(Network::Ferry),
+        7 | 1400..=1499 => Some(Network::Funicular),
+        _ => None
+    }
+}
+
+impl Network {
+    fn matches(self, tags: &HashMap<String, String>) -> bool {
+        let tag = |key: &str| tags.get(key).map(String::as_str);
+        match self {
+            Network::Road => matches!(
+                tag("highway"),
+                Some(
+                    "motorway"
+                        | "motorway_link"
+                        | "trunk"
+                        | "trunk_link"
+                        | "primary"
+                        | "primary_link"
+                        | "secondary"
+                        | "secondary_link"
+                        | "tertiary"
+                        | "tertiary_link"
+                        | "unclassified"
+                        | "residential"
+                        | "living_street"
+                        | "service"
+                        | "road"
+                        | "busway"
+                        | "bus_guideway"
+                )
+            ),
+            Network::Tram => matches!(tag("railway"), Some("tram" | "light_rail")),
+            Network::Subway => matches!(tag("railway"), Some("subway" | "light_rail" | "rail")),
+            Network::Rail => matches!(tag("railway"), Some("rail" | "light_rail" | "narrow_gauge")),
+            Network::Ferry => tag("route") == Some("ferry"),
+            Network::Funicular => tag("railway") == Some("funicular")
+        }
+    }
+
+    // Returns whether the way can be used (forward, backward)
+    fn directions(self, tags: &HashMap<String, String>) -> (bool, bool) {
+        if self != Network::Road {
+            return (true, true);
+        }
+
+        let tag = |key: &str| tags.get(key).map(String::as_str);
+        if matches!(tag("oneway:bus").or(tag("oneway:psv")), Some("no")) {
+            return (true, true);
+        }
+        match tag("oneway") {
+            Some("yes" | "true" | "1") => (true, false),
+            Some("-1") => (false, true),
+            _ if tag("junction") == Some("roundabout") => (true, false),
+            _ => (true, true)
+        }
+    }
+}
+
+struct Graph {
+    coordinates: Vec<(f64, f64)>,
+    edges: Vec<Vec<(usize, f64)>>,
+    grid: HashMap<(i64, i64), Vec<usize>>
+}
+
+fn grid_cell(c: (f64, f64)) -> (i64, i64) {
+    (
+        (c.0 / GRID_CELL_SIZE).floor() as i64,
+        (c.1 * c.0.to_radians().cos() / GRID_CELL_SIZE).floor() as i64
+    )
+}
+
+impl Graph {
+    fn new(network: Network, ways: &[Way], nodes: &HashMap<i64, (f64, f64)>) -> Graph {
+        let mut graph = Graph {
+            coordinates: Vec::new(),
+            edges: Vec::new(),
+            grid: HashMap::new()
+        };
+        let mut indices: HashMap<i64, usize> = HashMap::new();
+
+        for way in ways.iter().filter(|w| network.matches(&w.tags)) {
+            let (forward, backward) = network.directions(&way.tags);
+            let refs: Vec<usize> = way
+                .refs
+                .iter()
+                .filter_map(|id| {
+                    let c = *nodes.get(id)?;
+                    Some(*indices.entry(*id).or_insert_with(|| {
+                        graph.coordinates.push(c);
+                        graph.edges.push(Vec::new());
+                        graph
+                            .grid
+                            .entry(grid_cell(c))
+                            .or_default()
+                            .push(graph.coordinates.len() - 1);
+                        graph.coordinates.len() - 1
+                    }))
+                })
+                .collect();
+
+            for w in refs.windows(2) {
+                let length = distance(graph.coordinates[w[0]], graph.coordinates[w[1]]);
+                if forward {
+                    graph.edges[w[0]].push((w[1], length));
+                }
+                if backward {
+                    graph.edges[w[1]].push((w[0], length));
+                }
+            }
+        }
+
+        graph
+    }
+
+    fn nearest_node(&self, c: (f64, f64)) -> Option<usize> {
+        let (y, x) = grid_cell(c);
+        (-1..=1)
+            .flat_map(|dy| (-1..=1).map(move |dx| (y + dy, x + dx)))
+            .filter_map(|cell| self.grid.get(&cell))
+            .flatten()
+            .map(|i| (*i, distance(c, self.coordinates[*i])))
+            .filter(|(_, d)| *d <= MAX_SNAP_DISTANCE)
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(i, _)| i)
+    }
+
+    // A* search, gives up on detours much longer than the beeline
+    fn route(&self, from: usize, to: usize) -> Option<Vec<usize>> {
+        let target = self.coordinates[to];
+        let max_length = 3.0 * distance(self.coordinates[from], target) + 2000.0;
+
+        let mut lengths: HashMap<usize, f64> = HashMap::new();
+        let mut previous: HashMap<usize, usize> = HashMap::new();
+        let mut queue = BinaryHeap::new();
+        lengths.insert(from, 0.0);
+        queue.push(Reverse((0, from)));
+
+        while let Some(Reverse((_, node))) = queue.pop() {
+            if node == to {
+                let mut path = vec![to];
+                while let Some(p) = previous.get(path.last().unwrap()) {
+                    path.push(*p);
+                }
+                path.reverse();
+                return Some(path);
+            }
+
+            let length = lengths[&node];
+            for (next, edge_length) in &self.edges[node] {
+                let next_length = length + edge_length;
+                if next_length > max_length || lengths.get(next).is_some_and(|l| *l <= next_length)
+                {
+                    continue;
+                }
+                lengths.insert(*next, next_length);
+                previous.insert(*next, node);
+                // The queue is ordered by the estimate in millimeters
+                let estimate = next_length + distance(self.coordinates[*next], target);
+                queue.push(Reverse(((estimate * 1000.0) as u64, *next)));
+            }
+        }
+
+        None
+    }
+}
+
+// Generates shapes for all trips without a shape whose route type has a
+// matching network in the OpenStreetMap extract
+pub fn generate_shapes(gtfs: &mut RawGtfs, osm_path: &Path) -> Result<(), String> {
+    let (Ok(trips), Ok(routes), Ok(stop_times), Ok(stops)) = (
+        gtfs.trips.as_mut(),
+        gtfs.routes.as_ref(),
+        gtfs.stop_times.as_ref(),
+        gtfs.stops.as_ref()
+    ) else {
+        return Ok(());
+    };
+
+    let route_networks: HashMap<&str, Network> = routes
+        .iter()
+        .filter_map(|r| {
+            let route_type = to_string(&r.route_type).ok()?.parse::<i32>().ok()?;
+            Some((r.id.as_str(), network(route_type)?))
+        })
+        .collect();
+
+    let networks: HashSet<Network> = trips
+        .iter()
+        .filter(|t| t.shape_id.is_none())
+        .filter_map(|t| route_networks.get(t.route_id.as_str()).copied())
+        .collect();
+    if networks.is_empty() {
+        return Ok(());
+    }
+
+    println!("Reading OpenStreetMap ways...");
+    let ways = osm::read_ways(osm_path, |tags| networks.iter().any(|n| n.matches(tags)))?;
+    let node_ids: HashSet<i64> = ways.iter().flat_map(|w| w.refs.iter().copied()).collect();
+    println!("Reading {} OpenStreetMap nodes...", node_ids.len());
+    let nodes = osm::read_nodes(osm_path, &node_ids)?;
+
+    let graphs: HashMap<Network, Graph> = networks
+        .iter()
+        .map(|n| (*n, Graph::new(*n, &ways, &nodes)))
+        .collect();
+
+    let coordinates = stop_coordinates(stops);
+    let mut trip_stops: HashMap<&str, Vec<(u16, &str)>> = HashMap::new();
+    for st in stop_times {
+        trip_stops
+            .entry(st.trip_id.as_str())
+            .or_default()
+            .push((st.stop_sequence, st.stop_id.as_str()));
+    }
+    for s in trip_stops.values_mut() {
+        s.sort();
+    }
+
+    let mut generated: HashMap<(Network, Vec<&str>), Option<String>> = HashMap::new();
+    let mut segments: HashMap<(Network, &str, &str), SegmentPath> = HashMap::new();
+    let mut shape_points: Vec<Shape> = Vec::new();
+    let mut matched_trips = 0;
+    let mut unmatched_segments = 0;
+
+    for trip in trips.iter_mut().filter(|t| t.shape_id.is_none()) {
+        let (Some(network), Some(stop_ids)) = (
+            route_networks.get(trip.route_id.as_str()),
+            trip_stops.get(trip.id.as_str())
+        ) else {
+            continue;
+        };
+        let stop_ids: Vec<&str> = stop_ids.iter().map(|(_, id)| *id).collect();
+        let graph = &graphs[network];
+
+        let key = (*network, stop_ids.clone());
+        if !generated.contains_key(&key) {
+            let mut points: Vec<(f64, f64)> = Vec::new();
+            let mut routed = false;
+            for w in stop_ids.windows(2) {
+                let (Some(a), Some(b)) = (coordinates.get(w[0]), coordinates.get(w[1])) else {
+                    continue;
+                };
+                let segment = segments.entry((*network, w[0], w[1])).or_insert_with(|| {
+                    let path = graph.route(graph.nearest_node(*a)?, graph.nearest_node(*b)?)?;
+                    Some(path.iter().map(|i| graph.coordinates[*i]).collect())
+                });
+
+                if points.is_empty() {
+                    points.push(*a);
+                }
+                match segment {
+                    Some(path) => {
+                        points.extend(path.iter());
+                        routed = true;
+                    }
+                    None => unmatched_segments += 1
+                }
+                points.push(*b);
+            }
+
+            // Straight lines are drawn without a shape anyway
+            let shape_id = routed.then(|| {
+                let shape_id = format!("osm_shape_{}", generated.len() + 1);
+                shape_points.extend(points.iter().enumerate().map(|(i, (lat, lon))| Shape {
+                    id: shape_id.clone(),
+                    latitude: *lat,
+                    longitude: *lon,
+                    sequence: i + 1,
+                    dist_traveled: None
+                }));
+                shape_id
+            });
+            generated.insert(key.clone(), shape_id);
+        }
+
+        if let Some(shape_id) = &generated[&key] {
+            trip.shape_id = Some(shape_id.clone());
+            matched_trips += 1;
+        }
+    }
+
+    println!(
+        "Generated {} shapes for {} trips, {} segments without route",
+        generated.values().flatten().count(),
+        matched_trips,
+        unmatched_segments
+    );
+
+    match &mut gtfs.shapes {
+        Some(Ok(shapes)) => shapes.extend(shape_points),
+        _ => gtfs.shapes = Some(Ok(shape_points))
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXTRACT: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/testdata/small.osm.pbf");
+
+    // The extract has a main road from node 1 (48.14, 11.56) east and north to
+    // node 7 (48.15, 11.58), a one-way road north from node 1 via 8 and 9 to
+    // node 4 (48.145, 11.57) and a railway from node 3 to node 7
+    fn graph(network: Network) -> Graph {
+        let path = Path::new(EXTRACT);
+        let ways = osm::read_ways(path, |tags| network.matches(tags)).unwrap();
+        let node_ids: HashSet<i64> = ways.iter().flat_map(|w| w.refs.iter().copied()).collect();
+        let nodes = osm::read_nodes(path, &node_ids).unwrap();
+        Graph::new(network, &ways, &nodes)
+    }
+
+    fn route(graph: &Graph, from: (f64, f64), to: (f64, f64)) -> Option<Vec<(f64, f64)>> {
+        let path = graph.route(graph.nearest_node(from)?, graph.nearest_node(to)?)?;
+        Some(path.iter().map(|i| graph.coordinates[*i]).collect())
+    }
+
+    fn assert_path(actual: Option<Vec<(f64, f64)>>, expected: &[(f64, f64)]) {
+        let actual = actual.expect("no route");
+        assert_eq!(actual.len(), expected.len(), "{:?}", actual);
+        for (a, e) in actual.iter().zip(expected) {
+            assert!(distance(*a, *e) < 1.0, "{:?} != {:?}", actual, expected);
+        }
+    }
+
+    #[test]
+    fn networks_of_route_types() {
+        assert_eq!(network(3), Some(Network::Road));
+        assert_eq!(network(700), Some(Network::Road));
+        assert_eq!(network(0), Some(Network::Tram));
+        assert_eq!(network(109), Some(Network::Rail));
+        assert_eq!(network(6), None);
+    }
+
+    #[test]
+    fn routes_with_one_way_roads() {
+        let graph = graph(Network::Road);
+        assert_eq!(graph.coordinates.len(), 9);
+
+        // Along the one-way road
+        assert_path(
+            route(&graph, (48.1400, 11.5600), (48.1450, 11.5600)),
+            &[(48.1400, 11.5600), (48.1425, 11.5600), (48.1450, 11.5600)]
+        );
+        // Against it over the main road
+        assert_path(
+            route(&graph, (48.1450, 11.5600), (48.1400, 11.5600)),
+            &[
+                (48.1450, 11.5600),
+                (48.1450, 11.5700),
+                (48.1400, 11.5700),
+                (48.1400, 11.5650),
+                (48.1400, 11.5600)
+            ]
+        );
+    }
+
+    #[test]
+    fn routes_on_rail_network() {
+        let graph = graph(Network::Rail);
+        assert_eq!(graph.coordinates.len(), 2);
+        assert_path(
+            route(&graph, (48.1400, 11.5700), (48.1500, 11.5800)),
+            &[(48.1400, 11.5700), (48.1500, 11.5800)]
+        );
+        // Stops too far from the tracks are not matched
+        assert!(route(&graph, (48.1400, 11.5600), (48.1500, 11.5800)).is_none());
+    }
+}
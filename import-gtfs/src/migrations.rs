@@ -0,0 +1,84 @@
+// Upgrades the schemas of imported feeds to the current version of tables.sql,
+// indices.sql and views.sql
+use postgres::{Client, GenericClient};
+use std::process::exit;
+
+include!("schema_version.rs");
+
+// (version, SQL), feeds imported before schema versions were introduced have version 1
+const MIGRATIONS: [(i32, &str); 4] = [
+    (
+        2,
+        include_str!("sql/migrations/002_agencies_stations_fares.sql")
+    ),
+    (3, include_str!("sql/migrations/003_ids.sql")),
+    (4, include_str!("sql/migrations/004_translations.sql")),
+    (5, include_str!("sql/migrations/005_stop_headsign.sql"))
+];
+
+pub fn create_feeds_table(conn: &mut impl GenericClient) {
+    conn.batch_execute(include_str!("sql/feeds_table.sql"))
+        .expect("Error creating feeds table");
+}
+
+fn migrate_feed(conn: &mut Client, feed_uid: &str, version: i32) {
+    let mut tx = conn.transaction().unwrap();
+    tx.execute(&format!("SET search_path=\"{}\"", feed_uid), &[])
+        .expect("Error setting default schema path");
+
+    for (migration_version, migration) in MIGRATIONS.iter().filter(|(v, _)| *v > version) {
+        println!("Migrate {} to version {}...", feed_uid, migration_version);
+        tx.batch_execute(migration)
+            .unwrap_or_else(|e| panic!("Error migrating {}: {}", feed_uid, e));
+    }
+
+    println!("Recreate materialized views of {}...", feed_uid);
+    tx.batch_execute(include_str!("sql/rebuild_views.sql"))
+        .expect("Error dropping materialized views");
+    tx.batch_execute(include_str!("sql/views.sql"))
+        .expect("Error computing trip information");
+    tx.execute(include_str!("sql/feed_extent.sql"), &[&feed_uid])
+        .expect("Error computing feed extent");
+
+    tx.execute(
+        "UPDATE public.feeds SET schema_version = $1 WHERE feed_uid = $2",
+        &[&SCHEMA_VERSION, &feed_uid]
+    )
+    .expect("Error updating schema version");
+    tx.commit().unwrap();
+}
+
+// Migrates a single feed or all feeds
+pub fn migrate(conn: &mut Client, feed_uid: Option<&str>) {
+    create_feeds_table(conn);
+
+    let rows = conn
+        .query(
+            "SELECT feed_uid, COALESCE(schema_version, 1) AS schema_version FROM public.feeds
+             WHERE $1::TEXT IS NULL OR feed_uid = $1 ORDER BY feed_uid",
+            &[&feed_uid]
+        )
+        .expect("Error querying feeds");
+
+    if let (Some(feed_uid), true) = (feed_uid, rows.is_empty()) {
+        eprintln!("Feed {} not found in database", feed_uid);
+        exit(1);
+    }
+
+    for row in rows {
+        let feed_uid: String = row.get("feed_uid");
+        let version: i32 = row.get("schema_version");
+
+        if version == SCHEMA_VERSION {
+            println!("{} is up to date", feed_uid);
+        } else if version > SCHEMA_VERSION {
+            eprintln!(
+                "{} has schema version {}, this importer only supports version {}",
+                feed_uid, version, SCHEMA_VERSION
+            );
+        } else {
+            migrate_feed(conn, &feed_uid, version);
+            println!("Migrated {} to version {}", feed_uid, SCHEMA_VERSION);
+        }
+    }
+}
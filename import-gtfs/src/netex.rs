@@ -0,0 +1,742 @@
+// Conversion of NeTEx (EPIP and Nordic profile) XML files to GTFS structures.
+// Lines, journey patterns, service journeys, stop places, quays, service links
+// and day types are supported, flexible services and headway journeys are not.
+use chrono::{Datelike, Duration, NaiveDate, Weekday};
+use gtfs_structures::{
+    Agency, CalendarDate, DirectionType, Exception, FeedInfo, LocationType, RawGtfs, RawStopTime,
+    RawTrip, Route, RouteType, Shape, Stop
+};
+use rgb::RGB8;
+use roxmltree::{Document, Node};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use std::process::exit;
+use zip::ZipArchive;
+
+struct ScheduledStopPoint {
+    name: String,
+    coordinates: Option<(f64, f64)>
+}
+
+struct StopPointInPattern {
+    id: String,
+    order: u16,
+    scheduled_stop_point: String,
+    destination_display: Option<String>
+}
+
+struct JourneyPattern {
+    route: Option<String>,
+    stop_points: Vec<StopPointInPattern>,
+    // Service links ordered by their position in the pattern
+    links: Vec<String>
+}
+
+struct OperatingPeriod {
+    from: NaiveDate,
+    to: NaiveDate,
+    // UIC operating periods mark every valid day starting from `from`
+    valid_days: Option<Vec<bool>>
+}
+
+struct DayTypeAssignment {
+    day_type: String,
+    date: Option<NaiveDate>,
+    operating_day: Option<String>,
+    operating_period: Option<String>,
+    available: bool
+}
+
+struct PassingTime {
+    stop_point: String,
+    arrival_time: Option<u32>,
+    departure_time: Option<u32>
+}
+
+struct ServiceJourney {
+    id: String,
+    line: Option<String>,
+    journey_pattern: Option<String>,
+    day_types: Vec<String>,
+    passing_times: Vec<PassingTime>
+}
+
+// Collected from all files of a delivery, references are resolved after reading
+#[derive(Default)]
+struct Netex {
+    agencies: Vec<Agency>,
+    stops: Vec<Stop>,
+    scheduled_stop_points: HashMap<String, ScheduledStopPoint>,
+    // Scheduled stop point -> quay or stop place
+    stop_assignments: HashMap<String, String>,
+    lines: Vec<Route>,
+    // Route -> (line, direction)
+    routes: HashMap<String, (String, Option<DirectionType>)>,
+    journey_patterns: HashMap<String, JourneyPattern>,
+    destination_displays: HashMap<String, String>,
+    service_links: HashMap<String, Vec<(f64, f64)>>,
+    day_types: HashMap<String, Vec<Weekday>>,
+    operating_days: HashMap<String, NaiveDate>,
+    operating_periods: HashMap<String, OperatingPeriod>,
+    day_type_assignments: Vec<DayTypeAssignment>,
+    service_journeys: Vec<ServiceJourney>,
+    timezone: Option<String>,
+    language: Option<String>
+}
+
+fn elements<'a, 'input>(
+    node: Node<'a, 'input>,
+    name: &'static str
+) -> impl Iterator<Item = Node<'a, 'input>> {
+    node.descendants()
+        .filter(move |n| n.is_element() && n.tag_name().name() == name)
+}
+
+fn child<'a, 'input>(node: Node<'a, 'input>, name: &str) -> Option<Node<'a, 'input>> {
+    node.children()
+        .find(|n| n.is_element() && n.tag_name().name() == name)
+}
+
+fn child_text(node: Node, name: &str) -> Option<String> {
+    child(node, name)
+        .and_then(|n| n.text())
+        .map(|t| t.trim().to_string())
+        .filter(|t| !t.is_empty())
+}
+
+// The ref attribute of the first child with one of the given names
+fn child_ref(node: Node, names: &[&str]) -> Option<String> {
+    names
+        .iter()
+        .find_map(|name| child(node, name))
+        .and_then(|n| n.attribute("ref"))
+        .map(|r| r.to_string())
+}
+
+fn id(node: Node) -> String {
+    node.attribute("id").unwrap_or_default().to_string()
+}
+
+fn coordinates(node: Node) -> Option<(f64, f64)> {
+    let location = elements(node, "Location").next()?;
+    Some((
+        child_text(location, "Latitude")?.parse().ok()?,
+        child_text(location, "Longitude")?.parse().ok()?
+    ))
+}
+
+// Dates may be given with a time, e.g. 2026-03-02T00:00:00
+fn parse_date(text: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(text.get(..10)?, "%Y-%m-%d").ok()
+}
+
+fn parse_time(time: Option<String>, day_offset: Option<String>) -> Option<u32> {
+    let time = time?;
+    let mut parts = time.split(':').map(|p| p.parse::<u32>());
+    let seconds = match (parts.next(), parts.next(), parts.next()) {
+        (Some(Ok(h)), Some(Ok(m)), Some(Ok(s))) => h * 3600 + m * 60 + s,
+        _ => return None
+    };
+    let day_offset = day_offset.and_then(|d| d.parse::<u32>().ok()).unwrap_or(0);
+    Some(seconds + day_offset * 86400)
+}
+
+fn parse_color(color: Option<String>) -> Option<RGB8> {
+    let color = color?;
+    let value = u32::from_str_radix(color.trim_start_matches('#'), 16).ok()?;
+    Some(RGB8::new(
+        (value >> 16) as u8,
+        (value >> 8) as u8,
+        value as u8
+    ))
+}
+
+fn route_type(transport_mode: Option<&str>) -> RouteType {
+    match transport_mode {
+        Some("tram") => RouteType::Tramway,
+        Some("metro") => RouteType::Subway,
+        Some("rail") => RouteType::Rail,
+        Some("water" | "ferry") => RouteType::Ferry,
+        Some("cableway" | "telecabin") => RouteType::Gondola,
+        Some("funicular") => RouteType::Funicular,
+        Some("trolleyBus") => RouteType::Other(11),
+        Some("coach") => RouteType::Coach,
+        Some("air") => RouteType::Air,
+        Some("taxi") => RouteType::Taxi,
+        _ => RouteType::Bus
+    }
+}
+
+fn days_of_week(days: &str) -> Vec<Weekday> {
+    use Weekday::*;
+    days.split_whitespace()
+        .flat_map(|day| match day {
+            "Monday" => vec![Mon],
+            "Tuesday" => vec![Tue],
+            "Wednesday" => vec![Wed],
+            "Thursday" => vec![Thu],
+            "Friday" => vec![Fri],
+            "Saturday" => vec![Sat],
+            "Sunday" => vec![Sun],
+            "Weekdays" => vec![Mon, Tue, Wed, Thu, Fri],
+            "Weekend" => vec![Sat, Sun],
+            "Everyday" => vec![Mon, Tue, Wed, Thu, Fri, Sat, Sun],
+            _ => vec![]
+        })
+        .collect()
+}
+
+impl Netex {
+    fn read_document(&mut self, doc: &Document) {
+        let root = doc.root();
+
+        if let Some(defaults) = elements(root, "DefaultLocale").next() {
+            self.timezone = self.timezone.take().or(child_text(defaults, "TimeZone"));
+            self.language = self
+                .language
+                .take()
+                .or(child_text(defaults, "DefaultLanguage"));
+        }
+
+        for organisation in elements(root, "Operator").chain(elements(root, "Authority")) {
+            let contact = child(organisation, "ContactDetails")
+                .or(child(organisation, "CustomerServiceContactDetails"));
+            self.agencies.push(Agency {
+                id: Some(id(organisation)),
+                name: child_text(organisation, "Name").unwrap_or_default(),
+                url: contact
+                    .and_then(|c| child_text(c, "Url"))
+                    .unwrap_or_default(),
+                phone: contact.and_then(|c| child_text(c, "Phone")),
+                ..Default::default()
+            });
+        }
+
+        for stop_place in elements(root, "StopPlace") {
+            let name = child_text(stop_place, "Name").unwrap_or_default();
+            let quay_coordinates: Vec<Option<(f64, f64)>> = elements(stop_place, "Quay")
+                .map(|quay| child(quay, "Centroid").and_then(coordinates))
+                .collect();
+            // Stop places without a centroid are placed at the average
+            // position of their quays
+            let station_coordinates =
+                child(stop_place, "Centroid")
+                    .and_then(coordinates)
+                    .or_else(|| {
+                        let known: Vec<&(f64, f64)> = quay_coordinates.iter().flatten().collect();
+                        let n = known.len() as f64;
+                        (!known.is_empty()).then(|| {
+                            (
+                                known.iter().map(|c| c.0).sum::<f64>() / n,
+                                known.iter().map(|c| c.1).sum::<f64>() / n
+                            )
+                        })
+                    });
+            // Stop places without quays are served directly
+            let has_quays = elements(stop_place, "Quay").next().is_some();
+            self.stops.push(Stop {
+                id: id(stop_place),
+                name: name.clone(),
+                latitude: station_coordinates.map(|c| c.0),
+                longitude: station_coordinates.map(|c| c.1),
+                location_type: if has_quays {
+                    LocationType::StopArea
+                } else {
+                    LocationType::StopPoint
+                },
+                ..Default::default()
+            });
+
+            for (quay, quay_coordinates) in elements(stop_place, "Quay").zip(quay_coordinates) {
+                let quay_coordinates = quay_coordinates.or(station_coordinates);
+                self.stops.push(Stop {
+                    id: id(quay),
+                    name: child_text(quay, "Name").unwrap_or_else(|| name.clone()),
+                    latitude: quay_coordinates.map(|c| c.0),
+                    longitude: quay_coordinates.map(|c| c.1),
+                    location_type: LocationType::StopPoint,
+                    parent_station: Some(id(stop_place)),
+                    platform_code: child_text(quay, "PublicCode"),
+                    ..Default::default()
+                });
+            }
+        }
+
+        for point in elements(root, "ScheduledStopPoint") {
+            self.scheduled_stop_points.insert(
+                id(point),
+                ScheduledStopPoint {
+                    name: child_text(point, "Name").unwrap_or_default(),
+                    coordinates: coordinates(point)
+                }
+            );
+        }
+
+        for assignment in elements(root, "PassengerStopAssignment") {
+            if let (Some(point), Some(stop)) = (
+                child_ref(assignment, &["ScheduledStopPointRef"]),
+                child_ref(assignment, &["QuayRef", "StopPlaceRef"])
+            ) {
+                self.stop_assignments.insert(point, stop);
+            }
+        }
+
+        for line in elements(root, "Line") {
+            let presentation = child(line, "Presentation");
+            self.lines.push(Route {
+                id: id(line),
+                short_name: child_text(line, "PublicCode")
+                    .or(child_text(line, "ShortName"))
+                    .unwrap_or_default(),
+                long_name: child_text(line, "Name").unwrap_or_default(),
+                route_type: route_type(child_text(line, "TransportMode").as_deref()),
+                agency_id: child_ref(line, &["OperatorRef", "AuthorityRef"]),
+                route_color: presentation.and_then(|p| parse_color(child_text(p, "Colour"))),
+                route_text_color: presentation
+                    .and_then(|p| parse_color(child_text(p, "TextColour"))),
+                ..Default::default()
+            });
+        }
+
+        for route in elements(root, "Route") {
+            if let Some(line) = child_ref(route, &["LineRef"]) {
+                let direction = match child_text(route, "DirectionType").as_deref() {
+                    Some("outbound" | "clockwise") => Some(DirectionType::Outbound),
+                    Some("inbound" | "anticlockwise") => Some(DirectionType::Inbound),
+                    _ => None
+                };
+                self.routes.insert(id(route), (line, direction));
+            }
+        }
+
+        for pattern in
+            elements(root, "ServiceJourneyPattern").chain(elements(root, "JourneyPattern"))
+        {
+            let mut stop_points: Vec<StopPointInPattern> =
+                elements(pattern, "StopPointInJourneyPattern")
+                    .filter_map(|p| {
+                        Some(StopPointInPattern {
+                            id: id(p),
+                            order: p.attribute("order")?.parse().ok()?,
+                            scheduled_stop_point: child_ref(p, &["ScheduledStopPointRef"])?,
+                            destination_display: child_ref(p, &["DestinationDisplayRef"])
+                        })
+                    })
+                    .collect();
+            stop_points.sort_by_key(|p| p.order);
+
+            let mut links: Vec<(u32, String)> = elements(pattern, "ServiceLinkInJourneyPattern")
+                .filter_map(|l| {
+                    Some((
+                        l.attribute("order")?.parse().ok()?,
+                        child_ref(l, &["ServiceLinkRef"])?
+                    ))
+                })
+                .collect();
+            links.sort();
+
+            self.journey_patterns.insert(
+                id(pattern),
+                JourneyPattern {
+                    route: child_ref(pattern, &["RouteRef"]),
+                    stop_points,
+                    links: links.into_iter().map(|(_, l)| l).collect()
+                }
+            );
+        }
+
+        for display in elements(root, "DestinationDisplay") {
+            if let Some(text) = child_text(display, "FrontText") {
+                self.destination_displays.insert(id(display), text);
+            }
+        }
+
+        // Coordinates are given as "lat lon" pairs in WGS 84
+        for link in elements(root, "ServiceLink") {
+            let values: Vec<f64> = elements(link, "posList")
+                .chain(elements(link, "pos"))
+                .filter_map(|n| n.text())
+                .flat_map(|t| t.split_whitespace())
+                .filter_map(|v| v.parse().ok())
+                .collect();
+            let points = values.chunks_exact(2).map(|c| (c[0], c[1])).collect();
+            self.service_links.insert(id(link), points);
+        }
+
+        for day_type in elements(root, "DayType") {
+            let days = elements(day_type, "DaysOfWeek")
+                .filter_map(|n| n.text())
+                .flat_map(days_of_week)
+                .collect();
+            self.day_types.insert(id(day_type), days);
+        }
+
+        for day in elements(root, "OperatingDay") {
+            if let Some(date) = child_text(day, "CalendarDate").and_then(|d| parse_date(&d)) {
+                self.operating_days.insert(id(day), date);
+            }
+        }
+
+        for period in elements(root, "OperatingPeriod").chain(elements(root, "UicOperatingPeriod"))
+        {
+            let (Some(from), Some(to)) = (
+                child_text(period, "FromDate").and_then(|d| parse_date(&d)),
+                child_text(period, "ToDate").and_then(|d| parse_date(&d))
+            ) else {
+                continue;
+            };
+            self.operating_periods.insert(
+                id(period),
+                OperatingPeriod {
+                    from,
+                    to,
+                    valid_days: child_text(period, "ValidDayBits")
+                        .map(|bits| bits.chars().map(|c| c == '1').collect())
+                }
+            );
+        }
+
+        for assignment in elements(root, "DayTypeAssignment") {
+            let Some(day_type) = child_ref(assignment, &["DayTypeRef"]) else {
+                continue;
+            };
+            self.day_type_assignments.push(DayTypeAssignment {
+                day_type,
+                date: child_text(assignment, "Date").and_then(|d| parse_date(&d)),
+                operating_day: child_ref(assignment, &["OperatingDayRef"]),
+                operating_period: child_ref(
+                    assignment,
+                    &["OperatingPeriodRef", "UicOperatingPeriodRef"]
+                ),
+                available: child_text(assignment, "isAvailable").as_deref() != Some("false")
+            });
+        }
+
+        for journey in elements(root, "ServiceJourney") {
+            let passing_times = elements(journey, "TimetabledPassingTime")
+                .filter_map(|t| {
+                    Some(PassingTime {
+                        stop_point: child_ref(t, &["StopPointInJourneyPatternRef"])?,
+                        arrival_time: parse_time(
+                            child_text(t, "ArrivalTime"),
+                            child_text(t, "ArrivalDayOffset")
+                        ),
+                        departure_time: parse_time(
+                            child_text(t, "DepartureTime"),
+                            child_text(t, "DepartureDayOffset")
+                        )
+                    })
+                })
+                .collect();
+
+            self.service_journeys.push(ServiceJourney {
+                id: id(journey),
+                line: child_ref(journey, &["LineRef"]),
+                journey_pattern: child_ref(
+                    journey,
+                    &["ServiceJourneyPatternRef", "JourneyPatternRef"]
+                ),
+                day_types: elements(journey, "DayTypeRef")
+                    .filter_map(|n| n.attribute("ref"))
+                    .map(|r| r.to_string())
+                    .collect(),
+                passing_times
+            });
+        }
+    }
+
+    // All dates on which a day type is valid
+    fn day_type_dates(&self, day_type: &str) -> BTreeSet<NaiveDate> {
+        let days = self.day_types.get(day_type);
+        let mut dates = BTreeSet::new();
+        let mut removed = BTreeSet::new();
+
+        for assignment in self
+            .day_type_assignments
+            .iter()
+            .filter(|a| a.day_type == day_type)
+        {
+            let mut assigned = Vec::new();
+            assigned.extend(assignment.date);
+            assigned.extend(
+                assignment
+                    .operating_day
+                    .as_ref()
+                    .and_then(|d| self.operating_days.get(d))
+            );
+
+            if let Some(period) = assignment
+                .operating_period
+                .as_ref()
+                .and_then(|p| self.operating_periods.get(p))
+            {
+                let mut date = period.from;
+                let mut i = 0;
+                while date <= period.to {
+                    let valid = match (&period.valid_days, days) {
+                        (Some(bits), _) => bits.get(i).copied().unwrap_or(false),
+                        (None, Some(days)) if !days.is_empty() => days.contains(&date.weekday()),
+                        _ => true
+                    };
+                    if valid {
+                        assigned.push(date);
+                    }
+                    date += Duration::days(1);
+                    i += 1;
+                }
+            }
+
+            if assignment.available {
+                dates.extend(assigned);
+            } else {
+                removed.extend(assigned);
+            }
+        }
+
+        &dates - &removed
+    }
+
+    fn into_gtfs(mut self, default_timezone: Option<&str>) -> RawGtfs {
+        let Some(timezone) = self
+            .timezone
+            .clone()
+            .or(default_timezone.map(|t| t.to_string()))
+        else {
+            eprintln!("The NeTEx data has no DefaultLocale/TimeZone, set one with --timezone");
+            exit(1);
+        };
+
+        // Stop times reference the quay assigned to a scheduled stop point or the
+        // scheduled stop point itself if it is not assigned
+        let mut stops = std::mem::take(&mut self.stops);
+        let mut stop_ids: HashMap<&str, String> = HashMap::new();
+        for (point_id, point) in &self.scheduled_stop_points {
+            match self.stop_assignments.get(point_id) {
+                Some(stop_id) => {
+                    stop_ids.insert(point_id, stop_id.clone());
+                }
+                None => {
+                    if let Some((lat, lon)) = point.coordinates {
+                        stops.push(Stop {
+                            id: point_id.clone(),
+                            name: point.name.clone(),
+                            latitude: Some(lat),
+                            longitude: Some(lon),
+                            ..Default::default()
+                        });
+                        stop_ids.insert(point_id, point_id.clone());
+                    }
+                }
+            }
+        }
+
+        let mut services: HashMap<String, BTreeSet<NaiveDate>> = HashMap::new();
+        let mut trips = Vec::new();
+        let mut stop_times = Vec::new();
+        let mut shape_ids: HashSet<&str> = HashSet::new();
+        let mut missing_stops = 0;
+
+        for journey in &self.service_journeys {
+            let pattern = journey
+                .journey_pattern
+                .as_ref()
+                .and_then(|p| self.journey_patterns.get(p));
+            let route = pattern
+                .and_then(|p| p.route.as_ref())
+                .and_then(|r| self.routes.get(r));
+            let Some(line) = journey.line.clone().or(route.map(|r| r.0.clone())) else {
+                println!("Skip service journey {} without line", journey.id);
+                continue;
+            };
+
+            let mut day_types = journey.day_types.clone();
+            day_types.sort();
+            let service_id = day_types.join("+");
+            if !services.contains_key(&service_id) {
+                let dates = day_types
+                    .iter()
+                    .flat_map(|d| self.day_type_dates(d))
+                    .collect();
+                services.insert(service_id.clone(), dates);
+            }
+
+            let has_shape = pattern.is_some_and(|p| {
+                p.links
+                    .iter()
+                    .any(|l| self.service_links.get(l).is_some_and(|c| !c.is_empty()))
+            });
+            let shape_id = journey.journey_pattern.as_deref().filter(|_| has_shape);
+            shape_ids.extend(shape_id);
+
+            let stop_points: HashMap<&str, &StopPointInPattern> = pattern
+                .map(|p| p.stop_points.iter().map(|s| (s.id.as_str(), s)).collect())
+                .unwrap_or_default();
+            let headsign = pattern
+                .and_then(|p| p.stop_points.first())
+                .and_then(|s| s.destination_display.as_ref())
+                .and_then(|d| self.destination_displays.get(d))
+                .cloned();
+
+            trips.push(RawTrip {
+                id: journey.id.clone(),
+                service_id,
+                route_id: line,
+                shape_id: shape_id.map(|s| s.to_string()),
+                trip_headsign: headsign,
+                direction_id: route.and_then(|r| r.1),
+                ..Default::default()
+            });
+
+            for (i, passing_time) in journey.passing_times.iter().enumerate() {
+                let stop_point = stop_points.get(passing_time.stop_point.as_str());
+                let Some(stop_id) =
+                    stop_point.and_then(|s| stop_ids.get(s.scheduled_stop_point.as_str()))
+                else {
+                    missing_stops += 1;
+                    continue;
+                };
+                stop_times.push(RawStopTime {
+                    trip_id: journey.id.clone(),
+                    arrival_time: passing_time.arrival_time.or(passing_time.departure_time),
+                    departure_time: passing_time.departure_time.or(passing_time.arrival_time),
+                    stop_id: stop_id.clone(),
+                    stop_sequence: stop_point.map_or(i as u16 + 1, |s| s.order),
+                    ..Default::default()
+                });
+            }
+        }
+
+        if missing_stops > 0 {
+            println!("Skip {} passing times without stop", missing_stops);
+        }
+
+        let calendar_dates = services
+            .iter()
+            .flat_map(|(service_id, dates)| {
+                dates.iter().map(move |date| CalendarDate {
+                    service_id: service_id.clone(),
+                    date: *date,
+                    exception_type: Exception::Added
+                })
+            })
+            .collect();
+
+        let mut shapes = Vec::new();
+        for shape_id in shape_ids {
+            let mut points: Vec<(f64, f64)> = Vec::new();
+            for link in &self.journey_patterns[shape_id].links {
+                for point in self.service_links.get(link).into_iter().flatten() {
+                    if points.last() != Some(point) {
+                        points.push(*point);
+                    }
+                }
+            }
+            shapes.extend(points.iter().enumerate().map(|(i, (lat, lon))| Shape {
+                id: shape_id.to_string(),
+                latitude: *lat,
+                longitude: *lon,
+                sequence: i + 1,
+                dist_traveled: None
+            }));
+        }
+
+        let mut agencies = self.agencies;
+        for agency in &mut agencies {
+            agency.timezone = timezone.clone();
+        }
+        let feed_info = agencies.first().map(|a| FeedInfo {
+            name: a.name.clone(),
+            url: a.url.clone(),
+            lang: self.language.clone().unwrap_or_default(),
+            default_lang: None,
+            start_date: None,
+            end_date: None,
+            version: None,
+            contact_email: None,
+            contact_url: None
+        });
+
+        println!(
+            "Converted {} agencies, {} stops, {} lines and {} service journeys",
+            agencies.len(),
+            stops.len(),
+            self.lines.len(),
+            trips.len()
+        );
+
+        RawGtfs {
+            read_duration: 0,
+            calendar: None,
+            calendar_dates: Some(Ok(calendar_dates)),
+            stops: Ok(stops),
+            routes: Ok(self.lines),
+            trips: Ok(trips),
+            agencies: Ok(agencies),
+            shapes: Some(Ok(shapes)),
+            fare_attributes: None,
+            frequencies: None,
+            feed_info: feed_info.map(|f| Ok(vec![f])),
+            stop_times: Ok(stop_times),
+            files: Vec::new(),
+            sha256: None
+        }
+    }
+}
+
+// Contents of all XML files of a single file, a directory or a zip file
+fn read_files(path: &Path) -> Vec<(String, String)> {
+    let mut files = Vec::new();
+    let is_xml = |name: &str| name.to_lowercase().ends_with(".xml");
+
+    if path.is_dir() {
+        let mut paths: Vec<_> = std::fs::read_dir(path)
+            .expect("Error reading NeTEx directory")
+            .filter_map(|e| e.ok().map(|e| e.path()))
+            .filter(|p| is_xml(&p.to_string_lossy()))
+            .collect();
+        paths.sort();
+        for p in paths {
+            let content = std::fs::read_to_string(&p).expect("Error reading NeTEx file");
+            files.push((p.display().to_string(), content));
+        }
+    } else if path.extension().is_some_and(|e| e == "zip") {
+        let file = File::open(path).expect("Error opening NeTEx zip file");
+        let mut archive = ZipArchive::new(file).expect("Error reading NeTEx zip file");
+        for i in 0..archive.len() {
+            let mut entry = archive.by_index(i).expect("Error reading NeTEx zip file");
+            if is_xml(entry.name()) {
+                let mut content = String::new();
+                entry
+                    .read_to_string(&mut content)
+                    .expect("Error reading NeTEx file");
+                files.push((entry.name().to_string(), content));
+            }
+        }
+    } else if path.exists() {
+        let content = std::fs::read_to_string(path).expect("Error reading NeTEx file");
+        files.push((path.display().to_string(), content));
+    } else {
+        eprintln!("{} does not exist", path.display());
+        exit(1);
+    }
+
+    files
+}
+
+// The default time zone is used if the NeTEx data does not specify one
+pub fn read_netex(path: &Path, default_timezone: Option<&str>) -> RawGtfs {
+    let mut netex = Netex::default();
+
+    for (name, content) in read_files(path) {
+        println!("Reading {}...", name);
+        let doc = Document::parse(&content).unwrap_or_else(|e| {
+            eprintln!("Error reading {}: {}", name, e);
+            exit(1);
+        });
+        netex.read_document(&doc);
+    }
+
+    netex.into_gtfs(default_timezone)
+}
@@ -0,0 +1,461 @@
+// Minimal reader for OpenStreetMap PBF extracts (https://wiki.openstreetmap.org/wiki/PBF_Format)
+// Only the parts needed for map matching are decoded: way tags and node refs,
+// and the coordinates of nodes.
+use flate2::read::ZlibDecoder;
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{BufReader, ErrorKind, Read};
+use std::path::Path;
+
+pub struct Way {
+    pub tags: HashMap<String, String>,
+    pub refs: Vec<i64>
+}
+
+// Limits of the PBF format, larger sizes are rejected before allocating
+const MAX_HEADER_SIZE: usize = 64 * 1024;
+const MAX_BLOB_SIZE: usize = 32 * 1024 * 1024;
+
+// A protobuf message as a sequence of (field number, value)
+enum Value<'a> {
+    Varint(u64),
+    Bytes(&'a [u8]),
+    // Fixed size numbers and groups, which are not used in PBF files
+    Other
+}
+
+fn read_varint(data: &[u8], pos: &mut usize) -> Result<u64, String> {
+    let mut result = 0;
+    let mut shift = 0;
+    loop {
+        let Some(byte) = data.get(*pos) else {
+            return Err("truncated varint".to_string());
+        };
+        if shift >= 64 {
+            return Err("varint too long".to_string());
+        }
+        *pos += 1;
+        result |= ((byte & 0x7f) as u64) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(result);
+        }
+        shift += 7;
+    }
+}
+
+fn zigzag(n: u64) -> i64 {
+    (n >> 1) as i64 ^ -((n & 1) as i64)
+}
+
+fn read_bytes<'a>(data: &'a [u8], pos: &mut usize, len: u64) -> Result<&'a [u8], String> {
+    let end = usize::try_from(len)
+        .ok()
+        .and_then(|len| pos.checked_add(len))
+        .filter(|end| *end <= data.len())
+        .ok_or("truncated protobuf message")?;
+    let bytes = &data[*pos..end];
+    *pos = end;
+    Ok(bytes)
+}
+
+// Skips the fields up to the end of the group started before pos
+fn skip_group(data: &[u8], pos: &mut usize) -> Result<(), String> {
+    let mut depth = 1;
+    while depth > 0 {
+        let key = read_varint(data, pos)?;
+        match key & 7 {
+            0 => {
+                read_varint(data, pos)?;
+            }
+            1 => {
+                read_bytes(data, pos, 8)?;
+            }
+            2 => {
+                let len = read_varint(data, pos)?;
+                read_bytes(data, pos, len)?;
+            }
+            3 => depth += 1,
+            4 => depth -= 1,
+            5 => {
+                read_bytes(data, pos, 4)?;
+            }
+            wire_type => return Err(format!("invalid protobuf wire type {}", wire_type))
+        }
+    }
+    Ok(())
+}
+
+fn fields(data: &[u8]) -> Result<Vec<(u64, Value<'_>)>, String> {
+    let mut result = Vec::new();
+    let mut pos = 0;
+    while pos < data.len() {
+        let key = read_varint(data, &mut pos)?;
+        let value = match key & 7 {
+            0 => Value::Varint(read_varint(data, &mut pos)?),
+            1 => {
+                read_bytes(data, &mut pos, 8)?;
+                Value::Other
+            }
+            2 => {
+                let len = read_varint(data, &mut pos)?;
+                Value::Bytes(read_bytes(data, &mut pos, len)?)
+            }
+            3 => {
+                skip_group(data, &mut pos)?;
+                Value::Other
+            }
+            5 => {
+                read_bytes(data, &mut pos, 4)?;
+                Value::Other
+            }
+            wire_type => return Err(format!("invalid protobuf wire type {}", wire_type))
+        };
+        result.push((key >> 3, value));
+    }
+    Ok(result)
+}
+
+fn packed_varints(value: &Value) -> Result<Vec<u64>, String> {
+    match value {
+        Value::Bytes(data) => {
+            let mut result = Vec::new();
+            let mut pos = 0;
+            while pos < data.len() {
+                result.push(read_varint(data, &mut pos)?);
+            }
+            Ok(result)
+        }
+        Value::Varint(n) => Ok(vec![*n]),
+        Value::Other => Ok(Vec::new())
+    }
+}
+
+// Delta coded sint64 values as used for ids, coordinates and node refs
+fn packed_deltas(value: &Value) -> Result<Vec<i64>, String> {
+    let mut last: i64 = 0;
+    Ok(packed_varints(value)?
+        .into_iter()
+        .map(|n| {
+            last = last.wrapping_add(zigzag(n));
+            last
+        })
+        .collect())
+}
+
+// Reads the next fileblock, returns its type and the decompressed data
+fn read_blob(reader: &mut impl Read) -> Result<Option<(String, Vec<u8>)>, String> {
+    let mut len = [0; 4];
+    match reader.read_exact(&mut len) {
+        Ok(()) => {}
+        Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e.to_string())
+    }
+
+    let header_size = u32::from_be_bytes(len) as usize;
+    if header_size > MAX_HEADER_SIZE {
+        return Err(format!("blob header of {} bytes is too large", header_size));
+    }
+    let mut header = vec![0; header_size];
+    reader
+        .read_exact(&mut header)
+        .map_err(|e| format!("error reading blob header: {}", e))?;
+    let mut blob_type = String::new();
+    let mut data_size = 0;
+    for (field, value) in fields(&header)? {
+        match (field, value) {
+            (1, Value::Bytes(b)) => blob_type = String::from_utf8_lossy(b).to_string(),
+            (3, Value::Varint(n)) => data_size = n,
+            _ => {}
+        }
+    }
+    if data_size > MAX_BLOB_SIZE as u64 {
+        return Err(format!("blob of {} bytes is too large", data_size));
+    }
+
+    let mut blob = vec![0; data_size as usize];
+    reader
+        .read_exact(&mut blob)
+        .map_err(|e| format!("error reading blob: {}", e))?;
+    let mut data = None;
+    for (field, value) in fields(&blob)? {
+        match (field, value) {
+            (1, Value::Bytes(raw)) => data = Some(raw.to_vec()),
+            (3, Value::Bytes(zlib_data)) => {
+                let mut decompressed = Vec::new();
+                ZlibDecoder::new(zlib_data)
+                    .take(MAX_BLOB_SIZE as u64 + 1)
+                    .read_to_end(&mut decompressed)
+                    .map_err(|e| format!("error decompressing blob: {}", e))?;
+                if decompressed.len() > MAX_BLOB_SIZE {
+                    return Err("decompressed blob is too large".to_string());
+                }
+                data = Some(decompressed);
+            }
+            (4..=7, _) => return Err("unsupported compression, only zlib is supported".to_string()),
+            _ => {}
+        }
+    }
+
+    Ok(Some((blob_type, data.unwrap_or_default())))
+}
+
+struct Block<'a> {
+    strings: Vec<String>,
+    groups: Vec<&'a [u8]>,
+    granularity: i64,
+    lat_offset: i64,
+    lon_offset: i64
+}
+
+impl Block<'_> {
+    fn parse(data: &[u8]) -> Result<Block<'_>, String> {
+        let mut block = Block {
+            strings: Vec::new(),
+            groups: Vec::new(),
+            granularity: 100,
+            lat_offset: 0,
+            lon_offset: 0
+        };
+        for (field, value) in fields(data)? {
+            match (field, value) {
+                (1, Value::Bytes(table)) => {
+                    block.strings = fields(table)?
+                        .into_iter()
+                        .filter_map(|(f, v)| match (f, v) {
+                            (1, Value::Bytes(s)) => Some(String::from_utf8_lossy(s).to_string()),
+                            _ => None
+                        })
+                        .collect()
+                }
+                (2, Value::Bytes(group)) => block.groups.push(group),
+                (17, Value::Varint(n)) => block.granularity = n as i64,
+                (19, Value::Varint(n)) => block.lat_offset = n as i64,
+                (20, Value::Varint(n)) => block.lon_offset = n as i64,
+                _ => {}
+            }
+        }
+        Ok(block)
+    }
+
+    fn coordinate(&self, offset: i64, value: i64) -> f64 {
+        1e-9 * (offset as f64 + self.granularity as f64 * value as f64)
+    }
+
+    fn string(&self, index: u64) -> String {
+        self.strings
+            .get(index as usize)
+            .cloned()
+            .unwrap_or_default()
+    }
+}
+
+fn for_each_block(
+    path: &Path,
+    mut f: impl FnMut(&Block) -> Result<(), String>
+) -> Result<(), String> {
+    let file = File::open(path).map_err(|e| e.to_string())?;
+    let mut reader = BufReader::new(file);
+    while let Some((blob_type, data)) = read_blob(&mut reader)? {
+        if blob_type == "OSMData" {
+            f(&Block::parse(&data)?)?;
+        }
+    }
+    Ok(())
+}
+
+// Reads all ways accepted by the filter
+pub fn read_ways(
+    path: &Path,
+    filter: impl Fn(&HashMap<String, String>) -> bool
+) -> Result<Vec<Way>, String> {
+    let mut ways = Vec::new();
+    for_each_block(path, |block| {
+        for group in &block.groups {
+            for (field, value) in fields(group)? {
+                let Value::Bytes(way) = value else {
+                    continue;
+                };
+                if field != 3 {
+                    continue;
+                }
+
+                let mut keys = Vec::new();
+                let mut vals = Vec::new();
+                let mut refs = Vec::new();
+                for (f, v) in fields(way)? {
+                    match f {
+                        2 => keys = packed_varints(&v)?,
+                        3 => vals = packed_varints(&v)?,
+                        8 => refs = packed_deltas(&v)?,
+                        _ => {}
+                    }
+                }
+
+                let tags = keys
+                    .iter()
+                    .zip(vals.iter())
+                    .map(|(k, v)| (block.string(*k), block.string(*v)))
+                    .collect();
+                if filter(&tags) {
+                    ways.push(Way { tags, refs });
+                }
+            }
+        }
+        Ok(())
+    })?;
+    Ok(ways)
+}
+
+// Reads the coordinates (lat, lon) of the given nodes
+pub fn read_nodes(path: &Path, ids: &HashSet<i64>) -> Result<HashMap<i64, (f64, f64)>, String> {
+    let mut nodes = HashMap::new();
+    for_each_block(path, |block| {
+        for group in &block.groups {
+            for (field, value) in fields(group)? {
+                let Value::Bytes(data) = value else {
+                    continue;
+                };
+                match field {
+                    // Plain nodes
+                    1 => {
+                        let (mut id, mut lat, mut lon) = (0, 0, 0);
+                        for (f, v) in fields(data)? {
+                            if let Value::Varint(n) = v {
+                                match f {
+                                    1 => id = zigzag(n),
+                                    8 => lat = zigzag(n),
+                                    9 => lon = zigzag(n),
+                                    _ => {}
+                                }
+                            }
+                        }
+                        if ids.contains(&id) {
+                            nodes.insert(
+                                id,
+                                (
+                                    block.coordinate(block.lat_offset, lat),
+                                    block.coordinate(block.lon_offset, lon)
+                                )
+                            );
+                        }
+                    }
+                    // Dense nodes
+                    2 => {
+                        let (mut id, mut lat, mut lon) = (Vec::new(), Vec::new(), Vec::new());
+                        for (f, v) in fields(data)? {
+                            match f {
+                                1 => id = packed_deltas(&v)?,
+                                8 => lat = packed_deltas(&v)?,
+                                9 => lon = packed_deltas(&v)?,
+                                _ => {}
+                            }
+                        }
+                        for ((id, lat), lon) in id.iter().zip(lat.iter()).zip(lon.iter()) {
+                            if ids.contains(id) {
+                                nodes.insert(
+                                    *id,
+                                    (
+                                        block.coordinate(block.lat_offset, *lat),
+                                        block.coordinate(block.lon_offset, *lon)
+                                    )
+                                );
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+        Ok(())
+    })?;
+    Ok(nodes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXTRACT: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/testdata/small.osm.pbf");
+
+    #[test]
+    fn reads_ways_and_nodes() {
+        let ways = read_ways(Path::new(EXTRACT), |tags| tags.contains_key("highway")).unwrap();
+        assert_eq!(ways.len(), 2);
+        assert_eq!(ways[0].refs, vec![1, 2, 3, 4, 5, 6, 7]);
+        assert_eq!(ways[1].refs, vec![1, 8, 9, 4]);
+        assert_eq!(ways[1].tags.get("oneway").map(String::as_str), Some("yes"));
+
+        // Node 3 is a dense node, node 8 a plain one
+        let nodes = read_nodes(Path::new(EXTRACT), &HashSet::from([3, 8])).unwrap();
+        assert_eq!(nodes.len(), 2);
+        assert!((nodes[&3].0 - 48.14).abs() < 1e-7 && (nodes[&3].1 - 11.57).abs() < 1e-7);
+        assert!((nodes[&8].0 - 48.1425).abs() < 1e-7 && (nodes[&8].1 - 11.56).abs() < 1e-7);
+    }
+
+    #[test]
+    fn missing_file() {
+        assert!(read_ways(Path::new("does_not_exist.osm.pbf"), |_| true).is_err());
+    }
+
+    #[test]
+    fn malformed_varints() {
+        assert!(read_varint(&[0xff; 11], &mut 0).is_err());
+        assert!(read_varint(&[0x80, 0x80], &mut 0).is_err());
+        assert_eq!(read_varint(&[0xac, 0x02], &mut 0), Ok(300));
+    }
+
+    #[test]
+    fn malformed_messages() {
+        // Length beyond the end of the message
+        assert!(fields(&[0x0a, 0x05, 0x01]).is_err());
+        assert!(fields(&[0x0a, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x7f]).is_err());
+        // Truncated fixed64 and unknown wire type
+        assert!(fields(&[0x09, 0x01, 0x02]).is_err());
+        assert!(fields(&[0x0e]).is_err());
+        // Group without end
+        assert!(fields(&[0x0b, 0x10, 0x01]).is_err());
+    }
+
+    #[test]
+    fn groups_are_skipped() {
+        // Group 1 containing a nested group 2 and varint 3, then varint 4
+        let data = [0x0b, 0x13, 0x18, 0x01, 0x14, 0x18, 0x02, 0x0c, 0x20, 0x05];
+        let fields = fields(&data).unwrap();
+        assert_eq!(fields.len(), 2);
+        assert!(matches!(fields[0], (1, Value::Other)));
+        assert!(matches!(fields[1], (4, Value::Varint(5))));
+    }
+
+    fn blob(header: &[u8], blob: &[u8]) -> Vec<u8> {
+        let mut data = (header.len() as u32).to_be_bytes().to_vec();
+        data.extend_from_slice(header);
+        data.extend_from_slice(blob);
+        data
+    }
+
+    #[test]
+    fn unsupported_blobs() {
+        assert!(matches!(read_blob(&mut &[][..]), Ok(None)));
+
+        // Header larger than allowed
+        assert!(read_blob(&mut &[0x7f, 0xff, 0xff, 0xff][..]).is_err());
+
+        // Blob larger than allowed: type "OSMData", datasize 1 GiB
+        let mut header = vec![0x0a, 0x07];
+        header.extend_from_slice(b"OSMData");
+        header.extend_from_slice(&[0x18, 0x80, 0x80, 0x80, 0x80, 0x04]);
+        assert!(read_blob(&mut &blob(&header, &[])[..]).is_err());
+
+        // lzma compressed blob
+        let mut header = vec![0x0a, 0x07];
+        header.extend_from_slice(b"OSMData");
+        header.extend_from_slice(&[0x18, 0x03]);
+        assert!(read_blob(&mut &blob(&header, &[0x22, 0x01, 0x00])[..]).is_err());
+
+        // Blob shorter than announced
+        let mut header = vec![0x0a, 0x07];
+        header.extend_from_slice(b"OSMData");
+        header.extend_from_slice(&[0x18, 0x10]);
+        assert!(read_blob(&mut &blob(&header, &[0x0a, 0x00])[..]).is_err());
+    }
+}
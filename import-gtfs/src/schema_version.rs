@@ -0,0 +1,4 @@
+// Version of the schema created by an import. Increase it with every change of
+// the feed tables or views and add a migration. The server includes this file
+// to check the version of the feeds it serves.
+pub const SCHEMA_VERSION: i32 = 5;
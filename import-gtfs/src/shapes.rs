@@ -0,0 +1,385 @@
+// Distances along shapes for feeds without shape_dist_traveled
+use std::collections::HashMap;
+
+use gtfs_structures::{RawGtfs, RawStopTime, RawTrip, Shape, Stop};
+
+use crate::validation::distance;
+
+// Coordinates of a shape point and its distance along the shape
+type ShapePoint = ((f64, f64), f64);
+
+// Projects p onto the segment a-b in a local equirectangular projection.
+// Returns the distance in meters and the position on the segment (0 - 1).
+pub fn project_onto_segment(p: (f64, f64), a: (f64, f64), b: (f64, f64)) -> (f64, f64) {
+    let scale = p.0.to_radians().cos();
+    let project = |c: (f64, f64)| ((c.1 - p.1) * scale, c.0 - p.0);
+    let (ax, ay) = project(a);
+    let (bx, by) = project(b);
+    let (dx, dy) = (bx - ax, by - ay);
+    let len = dx * dx + dy * dy;
+    let t = if len > 0.0 {
+        (-(ax * dx + ay * dy) / len).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    let (x, y) = (ax + t * dx, ay + t * dy);
+    ((x * x + y * y).sqrt().to_radians() * 6371000.0, t)
+}
+
+// Indices of the points of each shape, ordered by sequence
+fn shape_points(shapes: &[Shape]) -> HashMap<&str, Vec<usize>> {
+    let mut points: HashMap<&str, Vec<usize>> = HashMap::new();
+    for (i, p) in shapes.iter().enumerate() {
+        points.entry(p.id.as_str()).or_default().push(i);
+    }
+    for indices in points.values_mut() {
+        indices.sort_by_key(|i| shapes[*i].sequence);
+    }
+    points
+}
+
+// Stop coordinates, stops without coordinates are placed at their parent station
+pub fn stop_coordinates(stops: &[Stop]) -> HashMap<&str, (f64, f64)> {
+    let stops_by_id: HashMap<&str, &Stop> = stops.iter().map(|s| (s.id.as_str(), s)).collect();
+    stops
+        .iter()
+        .filter_map(|stop| {
+            let mut current = Some(stop);
+            while let Some(s) = current {
+                if let (Some(lat), Some(lon)) = (s.latitude, s.longitude) {
+                    return Some((stop.id.as_str(), (lat, lon)));
+                }
+                current = s
+                    .parent_station
+                    .as_ref()
+                    .and_then(|parent_id| stops_by_id.get(parent_id.as_str()).copied());
+            }
+            None
+        })
+        .collect()
+}
+
+// Projects the stops in order onto the shape. The segments of the stops have
+// to be in the order of the stops, among those the sum of the distances of the
+// stops to their segments is minimized so that stops on loops and shapes which
+// return on themselves are placed on the right leg.
+fn project_stops(stops: &[(f64, f64)], shape: &[ShapePoint]) -> Vec<f64> {
+    let start = shape.first().map(|p| p.1).unwrap_or(0.0);
+    if shape.len() < 2 {
+        return vec![start; stops.len()];
+    }
+
+    // Distances along the shape in meters, the shape distances of the feed
+    // may be in other units
+    let mut meters = vec![0.0; shape.len()];
+    for i in 1..shape.len() {
+        meters[i] = meters[i - 1] + distance(shape[i - 1].0, shape[i].0);
+    }
+
+    // Distance to each segment and position on it in shape units and in
+    // meters for each stop
+    let projections: Vec<Vec<(f64, f64, f64)>> = stops
+        .iter()
+        .map(|stop| {
+            shape
+                .windows(2)
+                .zip(meters.windows(2))
+                .map(|(w, m)| {
+                    let (d, t) = project_onto_segment(*stop, w[0].0, w[1].0);
+                    (d, w[0].1 + t * (w[1].1 - w[0].1), m[0] + t * (m[1] - m[0]))
+                })
+                .collect()
+        })
+        .collect();
+
+    // costs[i][j]: smallest sum of distances of the stops up to i with stop i
+    // on segment j, choices[i][j]: the segment of stop i - 1 for it. A stop
+    // behind the previous one on the same segment is charged the difference
+    // in meters.
+    let segments = shape.len() - 1;
+    let mut costs: Vec<Vec<f64>> = Vec::with_capacity(stops.len());
+    let mut choices: Vec<Vec<usize>> = Vec::with_capacity(stops.len());
+    for (i, projection) in projections.iter().enumerate() {
+        let mut row = Vec::with_capacity(segments);
+        let mut choice = Vec::with_capacity(segments);
+        // Best previous stop on one of the segments before j
+        let mut best_before: Option<(f64, usize)> = None;
+        for (j, (d, _, m)) in projection.iter().enumerate() {
+            let (cost, previous) = if i == 0 {
+                (0.0, 0)
+            } else {
+                let same = (costs[i - 1][j] + (projections[i - 1][j].2 - m).max(0.0), j);
+                match best_before {
+                    Some(before) if before.0 < same.0 => before,
+                    _ => same
+                }
+            };
+            row.push(cost + d);
+            choice.push(previous);
+            if i > 0 && best_before.is_none_or(|(c, _)| costs[i - 1][j] < c) {
+                best_before = Some((costs[i - 1][j], j));
+            }
+        }
+        costs.push(row);
+        choices.push(choice);
+    }
+
+    let mut segment = costs.last().map_or(0, |row| {
+        (0..segments)
+            .min_by(|a, b| row[*a].total_cmp(&row[*b]))
+            .unwrap()
+    });
+    let mut assigned = vec![0; stops.len()];
+    for i in (0..stops.len()).rev() {
+        assigned[i] = segment;
+        segment = choices[i][segment];
+    }
+
+    // Stops on the same segment keep their order
+    let mut position = start;
+    assigned
+        .iter()
+        .zip(&projections)
+        .map(|(j, projection)| {
+            position = projection[*j].1.max(position);
+            position
+        })
+        .collect()
+}
+
+// Fills in shape_dist_traveled of shapes and stop_times where it is missing.
+// Computed shape distances are in meters, so the stop distances of the trips
+// on those shapes are all replaced as the ones of the feed may be in other
+// units. Otherwise only the missing stop distances are filled in.
+pub fn fill_shape_distances(gtfs: &mut RawGtfs) {
+    let (Some(Ok(shapes)), Ok(stop_times), Ok(trips), Ok(stops)) = (
+        gtfs.shapes.as_mut(),
+        gtfs.stop_times.as_mut(),
+        gtfs.trips.as_ref(),
+        gtfs.stops.as_ref()
+    ) else {
+        return;
+    };
+
+    let points = shape_points(shapes);
+
+    // Shapes without distances get the distance along the shape
+    let mut computed_shapes: Vec<String> = Vec::new();
+    let mut distances: Vec<Option<f32>> = shapes.iter().map(|p| p.dist_traveled).collect();
+    for (shape_id, indices) in &points {
+        if indices.iter().all(|i| shapes[*i].dist_traveled.is_some()) {
+            continue;
+        }
+
+        let mut total = 0.0;
+        for (n, i) in indices.iter().enumerate() {
+            if n > 0 {
+                let previous = &shapes[indices[n - 1]];
+                total += distance(
+                    (previous.latitude, previous.longitude),
+                    (shapes[*i].latitude, shapes[*i].longitude)
+                );
+            }
+            distances[*i] = Some(total as f32);
+        }
+        computed_shapes.push(shape_id.to_string());
+    }
+
+    if !computed_shapes.is_empty() {
+        println!(
+            "Compute distances along {} shapes...",
+            computed_shapes.len()
+        );
+    }
+
+    let geometries: HashMap<&str, Vec<ShapePoint>> = points
+        .iter()
+        .map(|(shape_id, indices)| {
+            (
+                *shape_id,
+                indices
+                    .iter()
+                    .map(|i| {
+                        (
+                            (shapes[*i].latitude, shapes[*i].longitude),
+                            distances[*i].unwrap_or(0.0) as f64
+                        )
+                    })
+                    .collect()
+            )
+        })
+        .collect();
+
+    let coordinates = stop_coordinates(stops);
+    let trips_by_id: HashMap<&str, &RawTrip> = trips.iter().map(|t| (t.id.as_str(), t)).collect();
+
+    let mut trip_stop_times: HashMap<&str, Vec<usize>> = HashMap::new();
+    for (i, st) in stop_times.iter().enumerate() {
+        trip_stop_times
+            .entry(st.trip_id.as_str())
+            .or_default()
+            .push(i);
+    }
+
+    // Trips with the same shape and stops share the projection
+    let mut projections: HashMap<(&str, Vec<&str>), Option<Vec<f64>>> = HashMap::new();
+    let mut updates: Vec<(usize, f32)> = Vec::new();
+    let mut projected_trips = 0;
+
+    for (trip_id, mut indices) in trip_stop_times {
+        let Some(shape_id) = trips_by_id.get(trip_id).and_then(|t| t.shape_id.as_deref()) else {
+            continue;
+        };
+        let Some(geometry) = geometries.get(shape_id) else {
+            continue;
+        };
+
+        let trip_stops: Vec<&RawStopTime> = {
+            indices.sort_by_key(|i| stop_times[*i].stop_sequence);
+            indices.iter().map(|i| &stop_times[*i]).collect()
+        };
+        let complete = trip_stops.iter().all(|st| st.shape_dist_traveled.is_some());
+        if complete && !computed_shapes.iter().any(|s| s == shape_id) {
+            continue;
+        }
+
+        let key = (
+            shape_id,
+            trip_stops.iter().map(|st| st.stop_id.as_str()).collect()
+        );
+        let projection = projections.entry(key).or_insert_with(|| {
+            let positions: Option<Vec<(f64, f64)>> = trip_stops
+                .iter()
+                .map(|st| coordinates.get(st.stop_id.as_str()).copied())
+                .collect();
+            positions.map(|p| project_stops(&p, geometry))
+        });
+
+        if let Some(projection) = projection {
+            // Filled in distances stay between the ones of the feed
+            let computed = computed_shapes.iter().any(|s| s == shape_id);
+            let provided: Vec<Option<f32>> = trip_stops
+                .iter()
+                .map(|st| st.shape_dist_traveled.filter(|_| !computed))
+                .collect();
+            let mut position = f64::MIN;
+            for (n, i) in indices.iter().enumerate() {
+                if let Some(d) = provided[n] {
+                    position = d as f64;
+                    continue;
+                }
+                let next = provided[n + 1..]
+                    .iter()
+                    .flatten()
+                    .next()
+                    .map_or(f64::MAX, |d| *d as f64);
+                position = projection[n].max(position).min(next);
+                updates.push((*i, position as f32));
+            }
+            projected_trips += 1;
+        }
+    }
+
+    if projected_trips > 0 {
+        println!(
+            "Project stops of {} trips onto their shapes...",
+            projected_trips
+        );
+    }
+
+    for (shape, d) in shapes.iter_mut().zip(distances) {
+        shape.dist_traveled = d;
+    }
+    for (i, d) in updates {
+        stop_times[i].shape_dist_traveled = Some(d);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_positions(actual: &[f64], expected: &[f64]) {
+        assert_eq!(actual.len(), expected.len());
+        for (a, e) in actual.iter().zip(expected) {
+            assert!((a - e).abs() < 50.0, "{:?} != {:?}", actual, expected);
+        }
+    }
+
+    #[test]
+    fn stops_on_straight_shape() {
+        let shape = vec![
+            ((48.0, 11.0), 0.0),
+            ((48.0, 11.01), 750.0),
+            ((48.0, 11.02), 1500.0),
+        ];
+        let stops = [(48.0, 11.0), (48.0001, 11.01), (48.0, 11.02)];
+        assert_positions(&project_stops(&stops, &shape), &[0.0, 750.0, 1500.0]);
+    }
+
+    #[test]
+    fn stops_on_shape_returning_on_itself() {
+        // Out and back on almost the same street, the first stop is closer to
+        // the way back
+        let shape = vec![
+            ((48.0, 11.0), 0.0),
+            ((48.0, 11.02), 1500.0),
+            ((48.0001, 11.0), 3000.0),
+        ];
+        let stops = [
+            (48.00006, 11.005),
+            (48.0, 11.015),
+            (48.0, 11.02),
+            (48.00009, 11.015),
+            (48.0001, 11.005)
+        ];
+        assert_positions(
+            &project_stops(&stops, &shape),
+            &[375.0, 1125.0, 1500.0, 1875.0, 2625.0]
+        );
+    }
+
+    #[test]
+    fn stops_on_shape_with_distances_in_kilometers() {
+        let shape = vec![
+            ((48.0, 11.0), 0.0),
+            ((48.0, 11.02), 1.5),
+            ((48.0001, 11.0), 3.0),
+        ];
+        let stops = [
+            (48.00006, 11.005),
+            (48.0, 11.015),
+            (48.0, 11.02),
+            (48.00009, 11.015),
+            (48.0001, 11.005)
+        ];
+        let positions = project_stops(&stops, &shape)
+            .iter()
+            .map(|p| p * 1000.0)
+            .collect::<Vec<_>>();
+        assert_positions(&positions, &[375.0, 1125.0, 1500.0, 1875.0, 2625.0]);
+    }
+
+    #[test]
+    fn stops_on_loop() {
+        // Square loop which ends where it starts
+        let shape = vec![
+            ((48.0, 11.0), 0.0),
+            ((48.0, 11.01), 1000.0),
+            ((48.01, 11.01), 2000.0),
+            ((48.01, 11.0), 3000.0),
+            ((48.0, 11.0), 4000.0),
+        ];
+        let stops = [(48.0, 11.0), (48.01, 11.01), (48.0, 11.0)];
+        assert_positions(&project_stops(&stops, &shape), &[0.0, 2000.0, 4000.0]);
+    }
+
+    #[test]
+    fn shape_without_segments() {
+        let shape = vec![((48.0, 11.0), 0.0)];
+        assert_eq!(
+            project_stops(&[(48.0, 11.0), (48.1, 11.0)], &shape),
+            vec![0.0, 0.0]
+        );
+    }
+}
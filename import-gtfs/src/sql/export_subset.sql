@@ -0,0 +1,87 @@
+-- Selects the exported part of a feed into temporary tables. The filter is
+-- given in export_filter, NULL or empty filters select everything.
+
+CREATE TEMP TABLE export_routes AS
+SELECT r.route_id
+FROM routes r, export_filter f
+WHERE (cardinality(f.agencies) = 0
+        OR r.agency_id IN (SELECT id FROM ids WHERE gtfs_id = ANY(f.agencies)))
+    AND (cardinality(f.route_types) = 0 OR r.route_type = ANY(f.route_types));
+
+-- Services running on at least one day of the date range
+CREATE TEMP TABLE export_services AS
+SELECT c.service_id
+FROM calendar c, export_filter f,
+    generate_series(GREATEST(c.start_date, f.start_date), LEAST(c.end_date, f.end_date), '1 day') AS d(day)
+WHERE CASE EXTRACT(ISODOW FROM d.day)
+        WHEN 1 THEN c.monday
+        WHEN 2 THEN c.tuesday
+        WHEN 3 THEN c.wednesday
+        WHEN 4 THEN c.thursday
+        WHEN 5 THEN c.friday
+        WHEN 6 THEN c.saturday
+        ELSE c.sunday
+    END
+    AND NOT EXISTS (
+        SELECT 1 FROM calendar_dates cd
+        WHERE cd.service_id = c.service_id AND cd.date = d.day AND cd.exception_type = 2
+    )
+UNION
+SELECT cd.service_id
+FROM calendar_dates cd, export_filter f
+WHERE cd.exception_type = 1
+    AND (f.start_date IS NULL OR cd.date >= f.start_date)
+    AND (f.end_date IS NULL OR cd.date <= f.end_date);
+
+CREATE TEMP TABLE export_trips AS
+SELECT t.trip_id
+FROM trips t
+WHERE t.route_id IN (SELECT route_id FROM export_routes)
+    AND t.service_id IN (SELECT service_id FROM export_services);
+
+-- Trips are cut to their stops within the bounding box (min_lon, min_lat, max_lon, max_lat)
+CREATE TEMP TABLE export_stop_times AS
+SELECT st.trip_id, st.stop_sequence, st.stop_id
+FROM stop_times st
+JOIN stops s ON s.stop_id = st.stop_id, export_filter f
+WHERE st.trip_id IN (SELECT trip_id FROM export_trips)
+    AND (f.bbox IS NULL
+        OR (s.stop_lon BETWEEN f.bbox[1] AND f.bbox[3] AND s.stop_lat BETWEEN f.bbox[2] AND f.bbox[4]));
+
+-- Trips need at least two stops, routes and services at least one trip
+DELETE FROM export_trips WHERE trip_id NOT IN (
+    SELECT trip_id FROM export_stop_times GROUP BY trip_id HAVING COUNT(*) >= 2
+);
+DELETE FROM export_stop_times WHERE trip_id NOT IN (SELECT trip_id FROM export_trips);
+DELETE FROM export_routes WHERE route_id NOT IN (
+    SELECT route_id FROM trips WHERE trip_id IN (SELECT trip_id FROM export_trips)
+);
+DELETE FROM export_services WHERE service_id NOT IN (
+    SELECT service_id FROM trips WHERE trip_id IN (SELECT trip_id FROM export_trips)
+);
+
+-- Served stops with their platforms and stations, and the entrances, nodes
+-- and boarding areas within them
+CREATE TEMP TABLE export_stops AS
+SELECT DISTINCT stop_id FROM export_stop_times;
+
+INSERT INTO export_stops
+SELECT DISTINCT s.parent_station
+FROM stops s
+WHERE s.stop_id IN (SELECT stop_id FROM export_stops)
+    AND s.parent_station IS NOT NULL
+    AND s.parent_station NOT IN (SELECT stop_id FROM export_stops);
+
+INSERT INTO export_stops
+SELECT DISTINCT s.parent_station
+FROM stops s
+WHERE s.stop_id IN (SELECT stop_id FROM export_stops)
+    AND s.parent_station IS NOT NULL
+    AND s.parent_station NOT IN (SELECT stop_id FROM export_stops);
+
+INSERT INTO export_stops
+SELECT s.stop_id
+FROM stops s
+WHERE s.location_type IN (2, 3, 4)
+    AND s.parent_station IN (SELECT stop_id FROM export_stops)
+    AND s.stop_id NOT IN (SELECT stop_id FROM export_stops);
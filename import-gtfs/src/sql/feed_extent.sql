@@ -0,0 +1,15 @@
+UPDATE public.feeds SET
+    calendar_start_date = calendar_range.first_date,
+    calendar_end_date = calendar_range.last_date,
+    min_lat = extent.min_lat,
+    min_lon = extent.min_lon,
+    max_lat = extent.max_lat,
+    max_lon = extent.max_lon
+FROM
+    (SELECT MIN(d) AS first_date, MAX(d) AS last_date FROM
+        (SELECT start_date AS d FROM calendar
+         UNION ALL SELECT end_date FROM calendar
+         UNION ALL SELECT date FROM calendar_dates WHERE exception_type = 1) AS t) AS calendar_range,
+    (SELECT MIN(stop_lat) AS min_lat, MIN(stop_lon) AS min_lon, MAX(stop_lat) AS max_lat, MAX(stop_lon) AS max_lon
+     FROM stops) AS extent
+WHERE feed_uid = $1;
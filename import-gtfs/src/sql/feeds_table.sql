@@ -0,0 +1,20 @@
+CREATE TABLE IF NOT EXISTS public.feeds (
+    feed_uid TEXT PRIMARY KEY,
+    feed_title TEXT NOT NULL,
+    feed_publisher_name TEXT NOT NULL,
+    feed_publisher_url TEXT NOT NULL
+);
+
+ALTER TABLE public.feeds
+    ADD COLUMN IF NOT EXISTS feed_lang TEXT,
+    ADD COLUMN IF NOT EXISTS feed_version TEXT,
+    ADD COLUMN IF NOT EXISTS feed_start_date DATE,
+    ADD COLUMN IF NOT EXISTS feed_end_date DATE,
+    ADD COLUMN IF NOT EXISTS imported_at TIMESTAMPTZ,
+    ADD COLUMN IF NOT EXISTS calendar_start_date DATE,
+    ADD COLUMN IF NOT EXISTS calendar_end_date DATE,
+    ADD COLUMN IF NOT EXISTS min_lat FLOAT,
+    ADD COLUMN IF NOT EXISTS min_lon FLOAT,
+    ADD COLUMN IF NOT EXISTS max_lat FLOAT,
+    ADD COLUMN IF NOT EXISTS max_lon FLOAT,
+    ADD COLUMN IF NOT EXISTS schema_version INT;
@@ -1,4 +1,7 @@
+CREATE INDEX agency_agency_id_index ON agency (agency_id);
+
 CREATE INDEX routes_route_id_index ON routes (route_id);
+CREATE INDEX routes_agency_id_index ON routes (agency_id);
 
 CREATE INDEX trips_trip_id_index ON trips (trip_id);
 CREATE INDEX trips_route_id_index ON trips (route_id);
@@ -31,5 +34,24 @@ CREATE INDEX shapes_shape_id_index ON shapes (shape_id);
 CREATE INDEX shapes_shape_dist_traveled_index ON shapes (shape_id, shape_dist_traveled);
 CREATE INDEX shapes_shape_id_shape_pt_sequence_index ON shapes (shape_id, shape_pt_sequence);
 
+CREATE INDEX frequencies_trip_id_index ON frequencies (trip_id);
+
+CREATE INDEX transfers_from_stop_id_index ON transfers (from_stop_id);
+
+CREATE INDEX levels_level_id_index ON levels (level_id);
+
+CREATE INDEX pathways_from_stop_id_index ON pathways (from_stop_id);
+CREATE INDEX pathways_to_stop_id_index ON pathways (to_stop_id);
+
+CREATE INDEX fare_rules_fare_id_index ON fare_rules (fare_id);
+CREATE INDEX fare_products_fare_product_id_index ON fare_products (fare_product_id);
+CREATE INDEX stop_areas_stop_id_index ON stop_areas (stop_id);
+CREATE INDEX route_networks_route_id_index ON route_networks (route_id);
+
+CREATE INDEX translations_record_id_index ON translations (table_name, field_name, record_id);
+CREATE INDEX translations_field_value_index ON translations (table_name, field_name, field_value);
+
+CREATE INDEX ids_id_index ON ids (id);
+
 CREATE INDEX cache_tile_index ON frequency_tile_cache (date, z, x, y);
 CREATE INDEX cache_hit_index ON frequency_tile_cache (last_hit);
\ No newline at end of file
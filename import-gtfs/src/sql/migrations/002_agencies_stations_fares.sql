@@ -0,0 +1,131 @@
+-- Feeds imported before schema versions were introduced.
+-- New tables stay empty and new columns NULL until the feed is imported again.
+CREATE TABLE IF NOT EXISTS agency (
+    agency_id OID NOT NULL,
+    agency_name TEXT NOT NULL,
+    agency_url TEXT NOT NULL,
+    agency_timezone TEXT NOT NULL,
+    agency_lang TEXT,
+    agency_phone TEXT,
+    agency_fare_url TEXT
+);
+
+ALTER TABLE routes
+    ADD COLUMN IF NOT EXISTS agency_id OID;
+
+ALTER TABLE trips
+    ADD COLUMN IF NOT EXISTS wheelchair_accessible INT,
+    ADD COLUMN IF NOT EXISTS bikes_allowed INT;
+
+ALTER TABLE stops
+    ADD COLUMN IF NOT EXISTS level_id OID,
+    ADD COLUMN IF NOT EXISTS zone_id OID,
+    ADD COLUMN IF NOT EXISTS wheelchair_boarding INT;
+
+CREATE TABLE IF NOT EXISTS frequencies (
+    trip_id OID NOT NULL,
+    start_time INT NOT NULL,
+    end_time INT NOT NULL,
+    headway_secs INT NOT NULL,
+    exact_times INT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS transfers (
+    from_stop_id OID,
+    to_stop_id OID,
+    from_route_id OID,
+    to_route_id OID,
+    from_trip_id OID,
+    to_trip_id OID,
+    transfer_type INT NOT NULL,
+    min_transfer_time INT
+);
+
+CREATE TABLE IF NOT EXISTS levels (
+    level_id OID NOT NULL,
+    level_index FLOAT NOT NULL,
+    level_name TEXT
+);
+
+CREATE TABLE IF NOT EXISTS pathways (
+    pathway_id OID NOT NULL,
+    from_stop_id OID NOT NULL,
+    to_stop_id OID NOT NULL,
+    pathway_mode INT NOT NULL,
+    is_bidirectional BOOL NOT NULL,
+    length REAL,
+    traversal_time INT,
+    stair_count INT,
+    max_slope REAL,
+    min_width REAL,
+    signposted_as TEXT,
+    reversed_signposted_as TEXT
+);
+
+CREATE TABLE IF NOT EXISTS fare_attributes (
+    fare_id OID NOT NULL,
+    price FLOAT NOT NULL,
+    currency_type TEXT NOT NULL,
+    payment_method INT NOT NULL,
+    transfers INT,
+    agency_id OID,
+    transfer_duration INT
+);
+
+CREATE TABLE IF NOT EXISTS fare_rules (
+    fare_id OID NOT NULL,
+    route_id OID,
+    origin_id OID,
+    destination_id OID,
+    contains_id OID
+);
+
+CREATE TABLE IF NOT EXISTS fare_media (
+    fare_media_id OID NOT NULL,
+    fare_media_name TEXT,
+    fare_media_type INT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS fare_products (
+    fare_product_id OID NOT NULL,
+    fare_product_name TEXT,
+    fare_media_id OID,
+    amount FLOAT NOT NULL,
+    currency TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS fare_leg_rules (
+    leg_group_id OID,
+    network_id OID,
+    from_area_id OID,
+    to_area_id OID,
+    fare_product_id OID NOT NULL,
+    rule_priority INT
+);
+
+CREATE TABLE IF NOT EXISTS areas (
+    area_id OID NOT NULL,
+    area_name TEXT
+);
+
+CREATE TABLE IF NOT EXISTS stop_areas (
+    area_id OID NOT NULL,
+    stop_id OID NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS route_networks (
+    network_id OID NOT NULL,
+    route_id OID NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS agency_agency_id_index ON agency (agency_id);
+CREATE INDEX IF NOT EXISTS routes_agency_id_index ON routes (agency_id);
+CREATE INDEX IF NOT EXISTS frequencies_trip_id_index ON frequencies (trip_id);
+CREATE INDEX IF NOT EXISTS transfers_from_stop_id_index ON transfers (from_stop_id);
+CREATE INDEX IF NOT EXISTS levels_level_id_index ON levels (level_id);
+CREATE INDEX IF NOT EXISTS pathways_from_stop_id_index ON pathways (from_stop_id);
+CREATE INDEX IF NOT EXISTS pathways_to_stop_id_index ON pathways (to_stop_id);
+CREATE INDEX IF NOT EXISTS fare_rules_fare_id_index ON fare_rules (fare_id);
+CREATE INDEX IF NOT EXISTS fare_products_fare_product_id_index ON fare_products (fare_product_id);
+CREATE INDEX IF NOT EXISTS stop_areas_stop_id_index ON stop_areas (stop_id);
+CREATE INDEX IF NOT EXISTS route_networks_route_id_index ON route_networks (route_id);
@@ -0,0 +1,8 @@
+-- Original ids are only known for feeds imported with version 3 or later,
+-- older feeds are exported with their OIDs as ids.
+CREATE TABLE IF NOT EXISTS ids (
+    id OID NOT NULL,
+    gtfs_id TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS ids_id_index ON ids (id);
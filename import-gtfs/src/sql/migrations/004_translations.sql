@@ -0,0 +1,38 @@
+-- Feeds imported before version 4 have no translations until they are imported again.
+CREATE TABLE IF NOT EXISTS translations (
+    table_name TEXT NOT NULL,
+    field_name TEXT NOT NULL,
+    language TEXT NOT NULL,
+    translation TEXT NOT NULL,
+    record_id OID,
+    record_sub_id TEXT,
+    field_value TEXT
+);
+
+-- Translation of the field $2 of the record $3 in table $1 with the value $4
+-- into the first of the languages $5 which is available. Untranslated values
+-- are in the language of the feed. Translations of the record take precedence
+-- over translations of the value, both over the untranslated value.
+CREATE OR REPLACE FUNCTION translated(TEXT, TEXT, OID, TEXT, TEXT[]) RETURNS TEXT AS $$
+    SELECT COALESCE((
+        SELECT candidates.translation
+        FROM (
+            SELECT translation, lower(language) AS language, CASE WHEN record_id IS NULL THEN 1 ELSE 0 END AS priority
+            FROM translations
+            WHERE table_name = $1 AND field_name = $2
+                AND (record_id = $3 OR (record_id IS NULL AND field_value = $4))
+            UNION ALL
+            SELECT $4, lower(feed_lang), 2
+            FROM public.feeds
+            WHERE feed_uid = current_schema()
+        ) AS candidates
+        JOIN unnest($5) WITH ORDINALITY AS requested(language, preference)
+            ON candidates.language = requested.language
+                OR split_part(candidates.language, '-', 1) = requested.language
+        ORDER BY requested.preference, candidates.priority, candidates.language = requested.language DESC
+        LIMIT 1
+    ), $4)
+$$ LANGUAGE SQL STABLE;
+
+CREATE INDEX IF NOT EXISTS translations_record_id_index ON translations (table_name, field_name, record_id);
+CREATE INDEX IF NOT EXISTS translations_field_value_index ON translations (table_name, field_name, field_value);
@@ -0,0 +1,2 @@
+-- Stop headsigns are only known for feeds imported with version 5 or later
+ALTER TABLE stop_times ADD COLUMN IF NOT EXISTS stop_headsign TEXT;
@@ -0,0 +1,13 @@
+-- Drops the materialized views before they are created again by views.sql
+DROP MATERIALIZED VIEW IF EXISTS segment_frequencies CASCADE;
+DROP MATERIALIZED VIEW IF EXISTS segment_paths CASCADE;
+DROP MATERIALIZED VIEW IF EXISTS stop_hierarchy CASCADE;
+DROP MATERIALIZED VIEW IF EXISTS trip_offsets CASCADE;
+DROP MATERIALIZED VIEW IF EXISTS trip_terminals CASCADE;
+
+-- Dropped by views.sql after the views are computed
+CREATE INDEX IF NOT EXISTS stop_times_trip_id_stop_sequence_index ON stop_times (trip_id, stop_sequence);
+CREATE INDEX IF NOT EXISTS stop_times_trip_id_shape_dist_traveled_index ON stop_times (trip_id, shape_dist_traveled);
+
+-- Cached tiles were rendered from the old views
+DELETE FROM frequency_tile_cache;
@@ -1,12 +1,16 @@
-CREATE TABLE IF NOT EXISTS public.feeds (
-    feed_uid TEXT PRIMARY KEY,
-    feed_title TEXT NOT NULL,
-    feed_publisher_name TEXT NOT NULL,
-    feed_publisher_url TEXT NOT NULL
+CREATE TABLE agency (
+    agency_id OID NOT NULL,
+    agency_name TEXT NOT NULL,
+    agency_url TEXT NOT NULL,
+    agency_timezone TEXT NOT NULL,
+    agency_lang TEXT,
+    agency_phone TEXT,
+    agency_fare_url TEXT
 );
 
 CREATE TABLE routes (
     route_id OID NOT NULL,
+    agency_id OID,
     route_short_name TEXT,
     route_long_name TEXT,
     route_desc TEXT,
@@ -22,7 +26,9 @@ CREATE TABLE trips (
     service_id OID NOT NULL,
     shape_id OID,
     trip_headsign TEXT,
-    direction_id INT
+    direction_id INT,
+    wheelchair_accessible INT,
+    bikes_allowed INT
 );
 
 CREATE TABLE stops (
@@ -32,7 +38,10 @@ CREATE TABLE stops (
     stop_lon FLOAT NOT NULL,
     location_type INT,
     parent_station OID,
-    platform_code TEXT
+    platform_code TEXT,
+    level_id OID,
+    zone_id OID,
+    wheelchair_boarding INT
 );
 
 CREATE TABLE stop_times (
@@ -41,7 +50,8 @@ CREATE TABLE stop_times (
     departure_time INT,
     stop_id OID NOT NULL,
     stop_sequence INT,
-    shape_dist_traveled REAL
+    shape_dist_traveled REAL,
+    stop_headsign TEXT
 );
 
 CREATE TABLE calendar (
@@ -71,6 +81,143 @@ CREATE TABLE shapes (
     shape_dist_traveled REAL
 );
 
+CREATE TABLE frequencies (
+    trip_id OID NOT NULL,
+    start_time INT NOT NULL,
+    end_time INT NOT NULL,
+    headway_secs INT NOT NULL,
+    exact_times INT NOT NULL
+);
+
+CREATE TABLE transfers (
+    from_stop_id OID,
+    to_stop_id OID,
+    from_route_id OID,
+    to_route_id OID,
+    from_trip_id OID,
+    to_trip_id OID,
+    transfer_type INT NOT NULL,
+    min_transfer_time INT
+);
+
+CREATE TABLE levels (
+    level_id OID NOT NULL,
+    level_index FLOAT NOT NULL,
+    level_name TEXT
+);
+
+CREATE TABLE pathways (
+    pathway_id OID NOT NULL,
+    from_stop_id OID NOT NULL,
+    to_stop_id OID NOT NULL,
+    pathway_mode INT NOT NULL,
+    is_bidirectional BOOL NOT NULL,
+    length REAL,
+    traversal_time INT,
+    stair_count INT,
+    max_slope REAL,
+    min_width REAL,
+    signposted_as TEXT,
+    reversed_signposted_as TEXT
+);
+
+CREATE TABLE fare_attributes (
+    fare_id OID NOT NULL,
+    price FLOAT NOT NULL,
+    currency_type TEXT NOT NULL,
+    payment_method INT NOT NULL,
+    transfers INT,
+    agency_id OID,
+    transfer_duration INT
+);
+
+CREATE TABLE fare_rules (
+    fare_id OID NOT NULL,
+    route_id OID,
+    origin_id OID,
+    destination_id OID,
+    contains_id OID
+);
+
+CREATE TABLE fare_media (
+    fare_media_id OID NOT NULL,
+    fare_media_name TEXT,
+    fare_media_type INT NOT NULL
+);
+
+CREATE TABLE fare_products (
+    fare_product_id OID NOT NULL,
+    fare_product_name TEXT,
+    fare_media_id OID,
+    amount FLOAT NOT NULL,
+    currency TEXT NOT NULL
+);
+
+CREATE TABLE fare_leg_rules (
+    leg_group_id OID,
+    network_id OID,
+    from_area_id OID,
+    to_area_id OID,
+    fare_product_id OID NOT NULL,
+    rule_priority INT
+);
+
+CREATE TABLE areas (
+    area_id OID NOT NULL,
+    area_name TEXT
+);
+
+CREATE TABLE stop_areas (
+    area_id OID NOT NULL,
+    stop_id OID NOT NULL
+);
+
+CREATE TABLE route_networks (
+    network_id OID NOT NULL,
+    route_id OID NOT NULL
+);
+
+CREATE TABLE translations (
+    table_name TEXT NOT NULL,
+    field_name TEXT NOT NULL,
+    language TEXT NOT NULL,
+    translation TEXT NOT NULL,
+    record_id OID,
+    record_sub_id TEXT,
+    field_value TEXT
+);
+
+-- Translation of the field $2 of the record $3 in table $1 with the value $4
+-- into the first of the languages $5 which is available. Untranslated values
+-- are in the language of the feed. Translations of the record take precedence
+-- over translations of the value, both over the untranslated value.
+CREATE FUNCTION translated(TEXT, TEXT, OID, TEXT, TEXT[]) RETURNS TEXT AS $$
+    SELECT COALESCE((
+        SELECT candidates.translation
+        FROM (
+            SELECT translation, lower(language) AS language, CASE WHEN record_id IS NULL THEN 1 ELSE 0 END AS priority
+            FROM translations
+            WHERE table_name = $1 AND field_name = $2
+                AND (record_id = $3 OR (record_id IS NULL AND field_value = $4))
+            UNION ALL
+            SELECT $4, lower(feed_lang), 2
+            FROM public.feeds
+            WHERE feed_uid = current_schema()
+        ) AS candidates
+        JOIN unnest($5) WITH ORDINALITY AS requested(language, preference)
+            ON candidates.language = requested.language
+                OR split_part(candidates.language, '-', 1) = requested.language
+        ORDER BY requested.preference, candidates.priority, candidates.language = requested.language DESC
+        LIMIT 1
+    ), $4)
+$$ LANGUAGE SQL STABLE;
+
+-- Original ids of the OIDs of all tables, used to export the feed again
+CREATE TABLE ids (
+    id OID NOT NULL,
+    gtfs_id TEXT NOT NULL
+);
+
 CREATE TABLE frequency_tile_cache (
     date DATE NOT NULL,
     z INT NOT NULL,
@@ -14,6 +14,19 @@ FROM trips;
 CREATE INDEX trip_terminals_trip_id_index
   ON trip_terminals (trip_id);
 
+-- Each run of a trip, given as offset to the times in stop_times.
+-- Trips in frequencies.txt are repeated within their headway windows, all other trips run once.
+CREATE MATERIALIZED VIEW trip_offsets AS
+SELECT trip_id,
+    generate_series(start_time, end_time - 1, headway_secs) - first_departure AS time_offset,
+    headway_secs, exact_times
+FROM frequencies NATURAL JOIN trip_terminals
+    UNION ALL
+SELECT trip_id, 0, NULL, NULL
+FROM trips WHERE NOT EXISTS (SELECT * FROM frequencies WHERE frequencies.trip_id = trips.trip_id);
+
+CREATE INDEX trip_offsets_trip_id_index ON trip_offsets (trip_id);
+
 -- Fast queries for root station <-> substations (i.e. platforms)
 CREATE MATERIALIZED VIEW stop_hierarchy AS
 WITH RECURSIVE stop_hierarchy(root_id, stop_id) AS
@@ -77,15 +90,18 @@ CREATE INDEX segment_paths_spatial_index ON segment_paths USING GIST (path);
 CREATE INDEX segment_paths_zoom_index ON segment_paths (zoom_threshold, astop, bstop);
 
 -- Helper table for quickly computing the number of trips for this segment on a day
--- We save a trip_count for a triple (astop, bstop, service_id).
+-- We save a trip_count for a quadruple (astop, bstop, service_id, agency_id).
+-- Trips with frequencies are counted once per run.
 CREATE MATERIALIZED VIEW segment_frequencies AS (
-WITH segment_frequencies_helper(astop, bstop, trip_id, service_id) AS (
+WITH segment_frequencies_helper(astop, bstop, trip_id, service_id, agency_id, wheelchair_accessible) AS (
     SELECT stop_id, LEAD(stop_id) OVER (PARTITION BY trip_id ORDER BY stop_sequence),
-        trip_id, service_id
-    FROM stop_times NATURAL JOIN trips)
-SELECT astop, bstop, COUNT(trip_id) AS trip_count, service_id
-FROM segment_frequencies_helper WHERE bstop IS NOT NULL
-GROUP BY astop, bstop, service_id
+        trip_id, service_id, agency_id, COALESCE(wheelchair_accessible, 0)
+    FROM stop_times NATURAL JOIN trips NATURAL JOIN routes),
+trip_runs(trip_id, runs) AS (
+    SELECT trip_id, COUNT(*) FROM trip_offsets GROUP BY trip_id)
+SELECT astop, bstop, SUM(runs)::BIGINT AS trip_count, service_id, agency_id, wheelchair_accessible
+FROM segment_frequencies_helper NATURAL JOIN trip_runs WHERE bstop IS NOT NULL
+GROUP BY astop, bstop, service_id, agency_id, wheelchair_accessible
 );
 
 CREATE INDEX segment_frequencies_stops_index ON segment_frequencies (astop, bstop, service_id);
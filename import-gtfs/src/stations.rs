@@ -0,0 +1,249 @@
+// Unification of nearby stops with similar names under a common parent station
+use std::collections::HashMap;
+
+use gtfs_structures::{LocationType, Stop};
+use postgres::Transaction;
+use string_interner::Symbol;
+
+use crate::validation::distance;
+use crate::Interner;
+
+pub struct StopCandidate {
+    pub stop_id: u32,
+    // Id in the GTFS feed
+    pub gtfs_id: String,
+    pub name: String,
+    pub lat: f64,
+    pub lon: f64,
+    pub is_station: bool,
+    // Index of the feed the stop belongs to
+    pub source: usize
+}
+
+// Stops and stations which are not part of a station yet
+pub fn top_level_stops(
+    interner: &mut Interner,
+    stops: &[Stop],
+    source: usize
+) -> Vec<StopCandidate> {
+    stops
+        .iter()
+        .filter(|s| s.parent_station.is_none())
+        .filter(|s| {
+            matches!(
+                s.location_type,
+                LocationType::StopPoint | LocationType::StopArea
+            )
+        })
+        .filter_map(|s| {
+            Some(StopCandidate {
+                stop_id: interner.get_or_intern(&s.id).to_usize() as u32,
+                gtfs_id: s.id.clone(),
+                name: s.name.clone(),
+                lat: s.latitude?,
+                lon: s.longitude?,
+                is_station: s.location_type == LocationType::StopArea,
+                source
+            })
+        })
+        .collect()
+}
+
+fn normalize_name(name: &str) -> String {
+    name.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(|c| c.to_lowercase())
+        .collect()
+}
+
+fn similar_names(a: &str, b: &str, threshold: f64) -> bool {
+    let (a, b) = (normalize_name(a), normalize_name(b));
+    if a.is_empty() || b.is_empty() {
+        return false;
+    }
+    a.contains(&b) || b.contains(&a) || strsim::jaro_winkler(&a, &b) >= threshold
+}
+
+fn find(parents: &mut [usize], i: usize) -> usize {
+    let mut root = i;
+    while parents[root] != root {
+        root = parents[root];
+    }
+    parents[i] = root;
+    root
+}
+
+// Groups stops within max_distance meters with similar names. Returns the
+// groups with more than one stop as indices into `stops`.
+pub fn cluster_stops(
+    stops: &[StopCandidate],
+    max_distance: f64,
+    name_similarity: f64,
+    across_sources_only: bool
+) -> Vec<Vec<usize>> {
+    // Grid cells of about max_distance, neighbours are in the surrounding cells
+    let cell_size = (max_distance / 111_000.0).max(1e-6);
+    let cell = |s: &StopCandidate| {
+        (
+            (s.lat / cell_size).floor() as i64,
+            (s.lon * s.lat.to_radians().cos() / cell_size).floor() as i64
+        )
+    };
+
+    let mut grid: HashMap<(i64, i64), Vec<usize>> = HashMap::new();
+    for (i, s) in stops.iter().enumerate() {
+        grid.entry(cell(s)).or_default().push(i);
+    }
+
+    let mut parents: Vec<usize> = (0..stops.len()).collect();
+    for (i, a) in stops.iter().enumerate() {
+        let (y, x) = cell(a);
+        for dy in -1..=1 {
+            for dx in -1..=1 {
+                for &j in grid.get(&(y + dy, x + dx)).into_iter().flatten() {
+                    let b = &stops[j];
+                    if j <= i || (across_sources_only && a.source == b.source) {
+                        continue;
+                    }
+                    if distance((a.lat, a.lon), (b.lat, b.lon)) <= max_distance
+                        && similar_names(&a.name, &b.name, name_similarity)
+                    {
+                        let (root_a, root_b) = (find(&mut parents, i), find(&mut parents, j));
+                        parents[root_b] = root_a;
+                    }
+                }
+            }
+        }
+    }
+
+    let mut groups: HashMap<usize, Vec<usize>> = HashMap::new();
+    for i in 0..stops.len() {
+        let root = find(&mut parents, i);
+        groups.entry(root).or_default().push(i);
+    }
+
+    let mut groups: Vec<Vec<usize>> = groups.into_values().filter(|g| g.len() > 1).collect();
+    groups.sort();
+    groups
+}
+
+// The most common name of the group, the shortest one on a tie
+fn station_name(stops: &[&StopCandidate]) -> String {
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for s in stops {
+        *counts.entry(s.name.as_str()).or_default() += 1;
+    }
+    counts
+        .into_iter()
+        .max_by(|a, b| {
+            a.1.cmp(&b.1)
+                .then(b.0.len().cmp(&a.0.len()))
+                .then(b.0.cmp(a.0))
+        })
+        .map(|(name, _)| name.to_string())
+        .unwrap_or_default()
+}
+
+// Makes a station the parent of the grouped stops. If the group contains
+// stations, the first one is used and the stops of the others are moved to it
+// before they are deleted, otherwise a new station is inserted.
+pub fn insert_parent_stations(
+    tx: &mut Transaction,
+    interner: &mut Interner,
+    stops: &[StopCandidate],
+    groups: &[Vec<usize>]
+) {
+    println!(
+        "Unify {} stops into {} stations...",
+        groups.iter().map(|g| g.len()).sum::<usize>(),
+        groups.len()
+    );
+
+    let insert_stmt = tx
+        .prepare(
+            "INSERT INTO stops (stop_id, stop_name, stop_lat, stop_lon, location_type) VALUES ($1, $2, $3, $4, 1)"
+        )
+        .unwrap();
+    let update_stmt = tx
+        .prepare("UPDATE stops SET parent_station = $1 WHERE stop_id = ANY($2)")
+        .unwrap();
+    let move_stmt = tx
+        .prepare("UPDATE stops SET parent_station = $1 WHERE parent_station = ANY($2)")
+        .unwrap();
+    // Everything else referring to the absorbed stations
+    let repoint_stmts: Vec<_> = [
+        "UPDATE pathways SET from_stop_id = $1 WHERE from_stop_id = ANY($2)",
+        "UPDATE pathways SET to_stop_id = $1 WHERE to_stop_id = ANY($2)",
+        "UPDATE transfers SET from_stop_id = $1 WHERE from_stop_id = ANY($2)",
+        "UPDATE transfers SET to_stop_id = $1 WHERE to_stop_id = ANY($2)",
+        "UPDATE stop_areas SET stop_id = $1 WHERE stop_id = ANY($2)"
+    ]
+    .iter()
+    .map(|query| tx.prepare(query).unwrap())
+    .collect();
+    let delete_translations_stmt = tx
+        .prepare("DELETE FROM translations WHERE table_name = 'stops' AND record_id = ANY($1)")
+        .unwrap();
+    let delete_stmt = tx
+        .prepare("DELETE FROM stops WHERE stop_id = ANY($1)")
+        .unwrap();
+
+    for (n, group) in groups.iter().enumerate() {
+        let members: Vec<&StopCandidate> = group.iter().map(|i| &stops[*i]).collect();
+        let station_id = match members.iter().find(|s| s.is_station) {
+            Some(station) => station.stop_id,
+            None => {
+                let name = station_name(&members);
+                let lat = members.iter().map(|s| s.lat).sum::<f64>() / members.len() as f64;
+                let lon = members.iter().map(|s| s.lon).sum::<f64>() / members.len() as f64;
+                let station_id = interner
+                    .get_or_intern(format!("station_{}", n + 1))
+                    .to_usize() as u32;
+                tx.execute(&insert_stmt, &[&station_id, &name, &lat, &lon])
+                    .unwrap();
+                station_id
+            }
+        };
+
+        println!(
+            "  {}",
+            members
+                .iter()
+                .map(|s| format!("{} ({})", s.name, s.gtfs_id))
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+
+        let (stations, stops): (Vec<&&StopCandidate>, Vec<_>) = members
+            .iter()
+            .filter(|s| s.stop_id != station_id)
+            .partition(|s| s.is_station);
+        let stop_ids: Vec<u32> = stops.iter().map(|s| s.stop_id).collect();
+        tx.execute(&update_stmt, &[&station_id, &stop_ids]).unwrap();
+        let station_ids: Vec<u32> = stations.iter().map(|s| s.stop_id).collect();
+        if !stations.is_empty() {
+            // Existing stations are rarely meant to be merged, make it visible
+            println!(
+                "    Warning: merged the stations {} into {}",
+                stations
+                    .iter()
+                    .map(|s| format!("{} ({})", s.name, s.gtfs_id))
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                members
+                    .iter()
+                    .find(|s| s.stop_id == station_id)
+                    .map(|s| format!("{} ({})", s.name, s.gtfs_id))
+                    .unwrap()
+            );
+        }
+        tx.execute(&move_stmt, &[&station_id, &station_ids])
+            .unwrap();
+        for stmt in &repoint_stmts {
+            tx.execute(stmt, &[&station_id, &station_ids]).unwrap();
+        }
+        tx.execute(&delete_translations_stmt, &[&station_ids])
+            .unwrap();
+        tx.execute(&delete_stmt, &[&station_ids]).unwrap();
+    }
+}
@@ -0,0 +1,475 @@
+// Validation of GTFS data before the import
+use gtfs_structures::{Exception, LocationType, RawGtfs, RawStopTime, Shape};
+use itertools::Itertools;
+use serde::Serialize;
+use serde_plain::to_string;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+use crate::shapes::project_onto_segment;
+
+// Stops farther away from the shape of their trip are reported
+const MAX_SHAPE_DISTANCE: f64 = 150.0; // m
+
+// Number of examples per issue code in the summary
+const SUMMARY_EXAMPLES: usize = 3;
+
+#[derive(Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning
+}
+
+#[derive(Serialize)]
+pub struct Issue {
+    pub severity: Severity,
+    pub code: &'static str,
+    pub file: &'static str,
+    pub id: String,
+    pub message: String
+}
+
+#[derive(Serialize, Default)]
+pub struct Report {
+    pub errors: usize,
+    pub warnings: usize,
+    pub issues: Vec<Issue>
+}
+
+impl Report {
+    fn error(&mut self, code: &'static str, file: &'static str, id: &str, message: String) {
+        self.errors += 1;
+        self.issues.push(Issue {
+            severity: Severity::Error,
+            code,
+            file,
+            id: id.to_string(),
+            message
+        });
+    }
+
+    fn warning(&mut self, code: &'static str, file: &'static str, id: &str, message: String) {
+        self.warnings += 1;
+        self.issues.push(Issue {
+            severity: Severity::Warning,
+            code,
+            file,
+            id: id.to_string(),
+            message
+        });
+    }
+
+    // Files which could not be parsed
+    pub fn unreadable_files(&self) -> Vec<&'static str> {
+        self.issues
+            .iter()
+            .filter(|issue| issue.code == "parse_error")
+            .map(|issue| issue.file)
+            .unique()
+            .collect()
+    }
+
+    pub fn write_json(&self, path: &Path) {
+        let file = std::fs::File::create(path).expect("Error creating validation report");
+        serde_json::to_writer_pretty(file, self).expect("Error writing validation report");
+    }
+
+    pub fn print_summary(&self) {
+        println!(
+            "Validation: {} errors, {} warnings",
+            self.errors, self.warnings
+        );
+
+        let issues_by_code = self
+            .issues
+            .iter()
+            .into_group_map_by(|issue| (issue.severity == Severity::Warning, issue.code));
+
+        for ((_, code), issues) in issues_by_code.iter().sorted_by_key(|(key, _)| *key) {
+            let severity = match issues[0].severity {
+                Severity::Error => "error",
+                Severity::Warning => "warning"
+            };
+            println!("  {} {} ({}x)", severity, code, issues.len());
+            for issue in issues.iter().take(SUMMARY_EXAMPLES) {
+                println!("    {}: {}", issue.file, issue.message);
+            }
+        }
+    }
+}
+
+pub fn distance(a: (f64, f64), b: (f64, f64)) -> f64 {
+    let (lat1, lon1) = (a.0.to_radians(), a.1.to_radians());
+    let (lat2, lon2) = (b.0.to_radians(), b.1.to_radians());
+    let h = ((lat2 - lat1) / 2.0).sin().powi(2)
+        + lat1.cos() * lat2.cos() * ((lon2 - lon1) / 2.0).sin().powi(2);
+    2.0 * 6371000.0 * h.sqrt().asin()
+}
+
+// Distance of point p to the segment a-b in meters
+fn segment_distance(p: (f64, f64), a: (f64, f64), b: (f64, f64)) -> f64 {
+    project_onto_segment(p, a, b).0
+}
+
+fn shape_distance(p: (f64, f64), shape: &[&Shape]) -> f64 {
+    match shape {
+        [] => 0.0,
+        [point] => distance(p, (point.latitude, point.longitude)),
+        _ => shape
+            .windows(2)
+            .map(|w| {
+                segment_distance(
+                    p,
+                    (w[0].latitude, w[0].longitude),
+                    (w[1].latitude, w[1].longitude)
+                )
+            })
+            .fold(f64::INFINITY, f64::min)
+    }
+}
+
+// Maximum plausible speed in km/h for a route type
+fn max_speed(route_type: i32) -> f64 {
+    match route_type {
+        0 | 3 | 5 | 6 | 7 | 11 | 700..=899 | 900..=999 => 150.0,
+        1 | 400..=499 => 200.0,
+        2 | 100..=199 => 400.0,
+        _ => 500.0
+    }
+}
+
+fn check_parse_errors(report: &mut Report, gtfs: &RawGtfs) {
+    let mut check = |file: &'static str, error: Option<String>| {
+        if let Some(e) = error {
+            report.error("parse_error", file, "", e);
+        }
+    };
+
+    check(
+        "stops.txt",
+        gtfs.stops.as_ref().err().map(|e| e.to_string())
+    );
+    check(
+        "routes.txt",
+        gtfs.routes.as_ref().err().map(|e| e.to_string())
+    );
+    check(
+        "trips.txt",
+        gtfs.trips.as_ref().err().map(|e| e.to_string())
+    );
+    check(
+        "agency.txt",
+        gtfs.agencies.as_ref().err().map(|e| e.to_string())
+    );
+    check(
+        "stop_times.txt",
+        gtfs.stop_times.as_ref().err().map(|e| e.to_string())
+    );
+    check(
+        "calendar.txt",
+        gtfs.calendar
+            .as_ref()
+            .and_then(|c| c.as_ref().err())
+            .map(|e| e.to_string())
+    );
+    check(
+        "calendar_dates.txt",
+        gtfs.calendar_dates
+            .as_ref()
+            .and_then(|c| c.as_ref().err())
+            .map(|e| e.to_string())
+    );
+    check(
+        "shapes.txt",
+        gtfs.shapes
+            .as_ref()
+            .and_then(|s| s.as_ref().err())
+            .map(|e| e.to_string())
+    );
+    check(
+        "fare_attributes.txt",
+        gtfs.fare_attributes
+            .as_ref()
+            .and_then(|f| f.as_ref().err())
+            .map(|e| e.to_string())
+    );
+    check(
+        "frequencies.txt",
+        gtfs.frequencies
+            .as_ref()
+            .and_then(|f| f.as_ref().err())
+            .map(|e| e.to_string())
+    );
+    check(
+        "feed_info.txt",
+        gtfs.feed_info
+            .as_ref()
+            .and_then(|f| f.as_ref().err())
+            .map(|e| e.to_string())
+    );
+}
+
+pub fn validate(gtfs: &RawGtfs) -> Report {
+    println!("Validate GTFS data...");
+
+    let mut report = Report::default();
+    check_parse_errors(&mut report, gtfs);
+
+    let stops = gtfs.stops.as_deref().unwrap_or_default();
+    let routes = gtfs.routes.as_deref().unwrap_or_default();
+    let trips = gtfs.trips.as_deref().unwrap_or_default();
+    let stop_times = gtfs.stop_times.as_deref().unwrap_or_default();
+    let calendar = gtfs
+        .calendar
+        .as_ref()
+        .and_then(|c| c.as_deref().ok())
+        .unwrap_or_default();
+    let calendar_dates = gtfs
+        .calendar_dates
+        .as_ref()
+        .and_then(|c| c.as_deref().ok())
+        .unwrap_or_default();
+    let shapes = gtfs
+        .shapes
+        .as_ref()
+        .and_then(|s| s.as_deref().ok())
+        .unwrap_or_default();
+
+    // Stops
+    let stop_ids: HashSet<&str> = stops.iter().map(|s| s.id.as_str()).collect();
+    let stop_coords: HashMap<&str, (f64, f64)> = stops
+        .iter()
+        .filter_map(|s| Some((s.id.as_str(), (s.latitude?, s.longitude?))))
+        .collect();
+    for s in stops {
+        // Only generic nodes and boarding areas may omit their coordinates
+        let requires_coordinates = matches!(
+            s.location_type,
+            LocationType::StopPoint | LocationType::StopArea | LocationType::StationEntrance
+        );
+        if requires_coordinates && !stop_coords.contains_key(s.id.as_str()) {
+            report.error(
+                "missing_coordinates",
+                "stops.txt",
+                &s.id,
+                format!("Stop {} has no coordinates", s.id)
+            );
+        }
+        if let (Some(lat), Some(lon)) = (s.latitude, s.longitude) {
+            if lat.abs() < 1e-6 && lon.abs() < 1e-6 {
+                report.error(
+                    "zero_coordinates",
+                    "stops.txt",
+                    &s.id,
+                    format!("Stop {} is located at (0, 0)", s.id)
+                );
+            }
+        }
+    }
+
+    if let Some(Ok(feed_info)) = &gtfs.feed_info {
+        if feed_info.is_empty() {
+            report.warning(
+                "empty_feed_info",
+                "feed_info.txt",
+                "",
+                "feed_info.txt has no record".to_string()
+            );
+        }
+    }
+
+    // Calendars
+    let added_services: HashSet<&str> = calendar_dates
+        .iter()
+        .filter(|cd| cd.exception_type == Exception::Added)
+        .map(|cd| cd.service_id.as_str())
+        .collect();
+    for c in calendar {
+        let weekdays = [
+            c.monday,
+            c.tuesday,
+            c.wednesday,
+            c.thursday,
+            c.friday,
+            c.saturday,
+            c.sunday
+        ];
+        let active = weekdays.iter().any(|d| *d) && c.start_date <= c.end_date;
+        if !active && !added_services.contains(c.id.as_str()) {
+            report.warning(
+                "no_active_days",
+                "calendar.txt",
+                &c.id,
+                format!("Service {} is not active on any day", c.id)
+            );
+        }
+    }
+
+    // Trips
+    let route_types: HashMap<&str, i32> = routes
+        .iter()
+        .map(|r| {
+            (
+                r.id.as_str(),
+                to_string(&r.route_type)
+                    .ok()
+                    .and_then(|x| x.parse::<i32>().ok())
+                    .unwrap_or(3)
+            )
+        })
+        .collect();
+    let services: HashSet<&str> = calendar
+        .iter()
+        .map(|c| c.id.as_str())
+        .chain(calendar_dates.iter().map(|cd| cd.service_id.as_str()))
+        .collect();
+    let shape_points: HashMap<&str, Vec<&Shape>> = shapes
+        .iter()
+        .sorted_by_key(|s| s.sequence)
+        .into_group_map_by(|s| s.id.as_str());
+
+    for t in trips {
+        if !route_types.contains_key(t.route_id.as_str()) {
+            report.error(
+                "unknown_route",
+                "trips.txt",
+                &t.id,
+                format!("Trip {} references unknown route {}", t.id, t.route_id)
+            );
+        }
+        if !services.contains(t.service_id.as_str()) {
+            report.error(
+                "unknown_service",
+                "trips.txt",
+                &t.id,
+                format!("Trip {} references unknown service {}", t.id, t.service_id)
+            );
+        }
+        if let Some(shape_id) = &t.shape_id {
+            if !shape_points.contains_key(shape_id.as_str()) {
+                report.error(
+                    "unknown_shape",
+                    "trips.txt",
+                    &t.id,
+                    format!("Trip {} references unknown shape {}", t.id, shape_id)
+                );
+            }
+        }
+    }
+
+    // Stop times
+    let trips_by_id: HashMap<&str, _> = trips.iter().map(|t| (t.id.as_str(), t)).collect();
+    let stop_times_by_trip: HashMap<&str, Vec<&RawStopTime>> = stop_times
+        .iter()
+        .into_group_map_by(|st| st.trip_id.as_str());
+    let mut checked_shape_stops: HashSet<(&str, &str)> = HashSet::new();
+
+    for (trip_id, mut trip_stop_times) in
+        stop_times_by_trip.into_iter().sorted_by_key(|(id, _)| *id)
+    {
+        let trip = match trips_by_id.get(trip_id) {
+            Some(trip) => trip,
+            None => {
+                report.error(
+                    "unknown_trip",
+                    "stop_times.txt",
+                    trip_id,
+                    format!("Stop times reference unknown trip {}", trip_id)
+                );
+                continue;
+            }
+        };
+
+        trip_stop_times.sort_by_key(|st| st.stop_sequence);
+
+        for st in &trip_stop_times {
+            if !stop_ids.contains(st.stop_id.as_str()) {
+                report.error(
+                    "unknown_stop",
+                    "stop_times.txt",
+                    trip_id,
+                    format!("Trip {} references unknown stop {}", trip_id, st.stop_id)
+                );
+            }
+        }
+
+        for (a, b) in trip_stop_times.iter().tuple_windows() {
+            if a.stop_sequence == b.stop_sequence {
+                report.error(
+                    "duplicate_stop_sequence",
+                    "stop_times.txt",
+                    trip_id,
+                    format!(
+                        "Trip {} has stop_sequence {} twice",
+                        trip_id, a.stop_sequence
+                    )
+                );
+            }
+
+            let departure = a.departure_time.or(a.arrival_time);
+            let arrival = b.arrival_time.or(b.departure_time);
+            if let (Some(departure), Some(arrival)) = (departure, arrival) {
+                if arrival < departure {
+                    report.error(
+                        "decreasing_time",
+                        "stop_times.txt",
+                        trip_id,
+                        format!(
+                            "Trip {} travels back in time at stop_sequence {}",
+                            trip_id, b.stop_sequence
+                        )
+                    );
+                } else if let (Some(pa), Some(pb)) = (
+                    stop_coords.get(a.stop_id.as_str()),
+                    stop_coords.get(b.stop_id.as_str())
+                ) {
+                    // Times are rounded to minutes in many feeds
+                    let km = distance(*pa, *pb) / 1000.0;
+                    let hours = (arrival - departure).max(60) as f64 / 3600.0;
+                    let limit = max_speed(*route_types.get(trip.route_id.as_str()).unwrap_or(&3));
+                    if km / hours > limit {
+                        report.warning(
+                            "unrealistic_speed",
+                            "stop_times.txt",
+                            trip_id,
+                            format!(
+                                "Trip {} travels with {:.0} km/h from stop {} to {}",
+                                trip_id,
+                                km / hours,
+                                a.stop_id,
+                                b.stop_id
+                            )
+                        );
+                    }
+                }
+            }
+        }
+
+        if let Some(shape_id) = &trip.shape_id {
+            if let Some(points) = shape_points.get(shape_id.as_str()) {
+                for st in &trip_stop_times {
+                    if !checked_shape_stops.insert((shape_id.as_str(), st.stop_id.as_str())) {
+                        continue;
+                    }
+                    if let Some(p) = stop_coords.get(st.stop_id.as_str()) {
+                        let d = shape_distance(*p, points);
+                        if d > MAX_SHAPE_DISTANCE {
+                            report.warning(
+                                "stop_far_from_shape",
+                                "shapes.txt",
+                                shape_id,
+                                format!(
+                                    "Stop {} is {:.0} m away from shape {}",
+                                    st.stop_id, d, shape_id
+                                )
+                            );
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    report
+}
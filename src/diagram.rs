@@ -0,0 +1,122 @@
+// Layout of a schematic line diagram from the stop patterns of a route
+use itertools::Itertools;
+use std::collections::HashMap;
+
+pub struct DiagramStation {
+    pub stop_id: u32,
+    // Row of the station, each station has its own row
+    pub row: usize,
+    // 0 for the main line, branches get further lanes
+    pub lane: usize,
+    // First or last station of a pattern
+    pub terminus: bool
+}
+
+pub struct RouteDiagram {
+    pub stations: Vec<DiagramStation>,
+    // Consecutive stations of any pattern, as indices into stations
+    pub edges: Vec<(usize, usize)>,
+    pub lanes: usize
+}
+
+// Builds the diagram from the station sequences of the trips, most frequent
+// first. The first pattern is the main line, stations of the other patterns
+// are inserted after their predecessor and runs of them form branches.
+pub fn route_diagram(patterns: &[Vec<u32>]) -> RouteDiagram {
+    let patterns = patterns
+        .iter()
+        .map(|p| p.iter().cloned().dedup().collect_vec())
+        .filter(|p| !p.is_empty())
+        .collect_vec();
+    let Some(main) = patterns.first() else {
+        return RouteDiagram {
+            stations: Vec::new(),
+            edges: Vec::new(),
+            lanes: 0
+        };
+    };
+
+    let mut order: Vec<u32> = main.iter().cloned().unique().collect();
+    // Stations of a branch with the stations it leaves and rejoins from
+    let mut branches: Vec<(Option<u32>, Vec<u32>, Option<u32>)> = Vec::new();
+    for pattern in &patterns[1..] {
+        let mut last: Option<usize> = None;
+        let mut branch: Vec<u32> = Vec::new();
+        let mut leaves: Option<u32> = None;
+        for stop_id in pattern {
+            if let Some(position) = order.iter().position(|s| s == stop_id) {
+                last = Some(position);
+                if !branch.is_empty() {
+                    branches.push((leaves, std::mem::take(&mut branch), Some(*stop_id)));
+                }
+                leaves = Some(*stop_id);
+            } else {
+                let position = last.map_or(0, |l| l + 1);
+                order.insert(position, *stop_id);
+                last = Some(position);
+                branch.push(*stop_id);
+            }
+        }
+        if !branch.is_empty() {
+            branches.push((leaves, branch, None));
+        }
+    }
+
+    let rows: HashMap<u32, usize> = order.iter().enumerate().map(|(row, s)| (*s, row)).collect();
+
+    // Branches take the first lane where they keep a row of distance to the
+    // other branches of that lane, including the rows their connections to
+    // the main line run through
+    let mut lanes: HashMap<u32, usize> = main.iter().map(|s| (*s, 0)).collect();
+    let mut occupied: Vec<Vec<(usize, usize)>> = vec![Vec::new()];
+    for (leaves, branch, rejoins) in &branches {
+        let (first, last) = branch
+            .iter()
+            .map(|s| rows[s])
+            .chain(leaves.map(|s| rows[&s] + 1))
+            .chain(rejoins.map(|s| rows[&s].saturating_sub(1)))
+            .minmax()
+            .into_option()
+            .unwrap();
+        let lane = (1..)
+            .find(|lane| {
+                occupied.get(*lane).is_none_or(|ranges| {
+                    ranges.iter().all(|(f, l)| last + 1 < *f || *l + 1 < first)
+                })
+            })
+            .unwrap();
+        if occupied.len() <= lane {
+            occupied.resize(lane + 1, Vec::new());
+        }
+        occupied[lane].push((first, last));
+        for stop_id in branch {
+            lanes.insert(*stop_id, lane);
+        }
+    }
+
+    let termini = patterns
+        .iter()
+        .flat_map(|p| [p[0], p[p.len() - 1]])
+        .collect_vec();
+    let stations = order
+        .iter()
+        .enumerate()
+        .map(|(row, stop_id)| DiagramStation {
+            stop_id: *stop_id,
+            row,
+            lane: lanes[stop_id],
+            terminus: termini.contains(stop_id)
+        })
+        .collect();
+    let edges = patterns
+        .iter()
+        .flat_map(|p| p.iter().tuple_windows().map(|(a, b)| (rows[a], rows[b])))
+        .unique()
+        .collect();
+
+    RouteDiagram {
+        stations,
+        edges,
+        lanes: occupied.len()
+    }
+}
@@ -0,0 +1,222 @@
+// Fare calculation for a single ride with fares v1 (fare_attributes.txt, fare_rules.txt)
+use std::collections::HashSet;
+
+use crate::model::{FareAttribute, FareRule, FareTripStop};
+
+// The part of a trip between the boarding and the alighting stop
+pub fn ride_stops(
+    trip_stops: &[FareTripStop],
+    from_stop_id: u32,
+    to_stop_id: u32
+) -> Option<&[FareTripStop]> {
+    let from = trip_stops.iter().position(|s| s.stop_id == from_stop_id)?;
+    let (to, _) = trip_stops
+        .iter()
+        .enumerate()
+        .skip(from + 1)
+        .find(|(_, s)| s.stop_id == to_stop_id)?;
+    Some(&trip_stops[from..=to])
+}
+
+fn fare_matches(rules: &[&FareRule], ride: &[FareTripStop]) -> bool {
+    let route_id = ride[0].route_id;
+    let origin_id = ride[0].zone_id;
+    let destination_id = ride[ride.len() - 1].zone_id;
+
+    let route_rules = rules
+        .iter()
+        .filter(|r| r.route_id.is_none() || r.route_id == Some(route_id))
+        .collect::<Vec<_>>();
+
+    let simple_match = route_rules.iter().any(|r| {
+        r.contains_id.is_none()
+            && (r.origin_id.is_none() || r.origin_id == origin_id)
+            && (r.destination_id.is_none() || r.destination_id == destination_id)
+    });
+
+    // With contains_id, the zones passed by the ride must be exactly the listed zones
+    let contains_zones: HashSet<u32> = route_rules.iter().filter_map(|r| r.contains_id).collect();
+    let ride_zones: HashSet<u32> = ride.iter().filter_map(|s| s.zone_id).collect();
+    let contains_match = !contains_zones.is_empty() && contains_zones == ride_zones;
+
+    simple_match || contains_match
+}
+
+// Returns the cheapest fare that is valid for the ride
+pub fn cheapest_fare<'a>(
+    fare_attributes: &'a [FareAttribute],
+    fare_rules: &[FareRule],
+    ride: &[FareTripStop]
+) -> Option<&'a FareAttribute> {
+    let agency_id = ride[0].agency_id;
+
+    fare_attributes
+        .iter()
+        .filter(|f| f.agency_id.is_none() || agency_id.is_none() || f.agency_id == agency_id)
+        .filter(|f| {
+            // Without fare_rules.txt, all fares apply to every ride
+            fare_rules.is_empty()
+                || fare_matches(
+                    &fare_rules
+                        .iter()
+                        .filter(|r| r.fare_id == f.fare_id)
+                        .collect::<Vec<_>>(),
+                    ride
+                )
+        })
+        .min_by(|a, b| a.price.total_cmp(&b.price))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Stops 1 to 4 of route 10 in the zones 1, 1, 2 and 3
+    fn trip_stops() -> Vec<FareTripStop> {
+        [(1, 1), (2, 1), (3, 2), (4, 3)]
+            .into_iter()
+            .map(|(stop_id, zone_id)| FareTripStop {
+                stop_id,
+                stop_name: format!("Stop {}", stop_id),
+                zone_id: Some(zone_id),
+                route_id: 10,
+                agency_id: None
+            })
+            .collect()
+    }
+
+    fn fare(fare_id: u32, price: f64) -> FareAttribute {
+        FareAttribute {
+            fare_id,
+            price,
+            currency_type: "EUR".to_string(),
+            transfers: None,
+            transfer_duration: None,
+            agency_id: None
+        }
+    }
+
+    fn rule(fare_id: u32) -> FareRule {
+        FareRule {
+            fare_id,
+            route_id: None,
+            origin_id: None,
+            destination_id: None,
+            contains_id: None
+        }
+    }
+
+    fn stop_ids(ride: &[FareTripStop]) -> Vec<u32> {
+        ride.iter().map(|s| s.stop_id).collect()
+    }
+
+    #[test]
+    fn ride_between_stops() {
+        let stops = trip_stops();
+        assert_eq!(stop_ids(ride_stops(&stops, 2, 4).unwrap()), [2, 3, 4]);
+        assert!(ride_stops(&stops, 4, 2).is_none());
+        assert!(ride_stops(&stops, 2, 2).is_none());
+        assert!(ride_stops(&stops, 2, 5).is_none());
+    }
+
+    #[test]
+    fn ride_on_loop_alights_at_next_visit() {
+        let mut stops = trip_stops();
+        stops.push(FareTripStop {
+            stop_id: 1,
+            stop_name: "Stop 1".to_string(),
+            zone_id: Some(1),
+            route_id: 10,
+            agency_id: None
+        });
+        assert_eq!(stop_ids(ride_stops(&stops, 3, 1).unwrap()), [3, 4, 1]);
+    }
+
+    #[test]
+    fn fares_without_rules_apply_to_every_ride() {
+        let stops = trip_stops();
+        let fares = [fare(1, 3.0), fare(2, 2.5)];
+        let cheapest = cheapest_fare(&fares, &[], &stops).unwrap();
+        assert_eq!(cheapest.fare_id, 2);
+    }
+
+    #[test]
+    fn route_rules() {
+        let stops = trip_stops();
+        let fares = [fare(1, 3.0), fare(2, 2.5)];
+        let rules = [
+            FareRule {
+                route_id: Some(10),
+                ..rule(1)
+            },
+            FareRule {
+                route_id: Some(11),
+                ..rule(2)
+            }
+        ];
+        let cheapest = cheapest_fare(&fares, &rules, &stops).unwrap();
+        assert_eq!(cheapest.fare_id, 1);
+    }
+
+    #[test]
+    fn origin_and_destination_rules() {
+        let stops = trip_stops();
+        let fares = [fare(1, 2.0), fare(2, 4.0)];
+        let rules = [
+            FareRule {
+                origin_id: Some(1),
+                destination_id: Some(2),
+                ..rule(1)
+            },
+            FareRule {
+                origin_id: Some(1),
+                ..rule(2)
+            }
+        ];
+        let within = ride_stops(&stops, 1, 3).unwrap();
+        assert_eq!(cheapest_fare(&fares, &rules, within).unwrap().fare_id, 1);
+        let beyond = ride_stops(&stops, 1, 4).unwrap();
+        assert_eq!(cheapest_fare(&fares, &rules, beyond).unwrap().fare_id, 2);
+        let from_zone_2 = ride_stops(&stops, 3, 4).unwrap();
+        assert!(cheapest_fare(&fares, &rules, from_zone_2).is_none());
+    }
+
+    #[test]
+    fn contains_rules_need_exactly_the_zones_of_the_ride() {
+        let stops = trip_stops();
+        let fares = [fare(1, 2.0)];
+        let rules = [
+            FareRule {
+                contains_id: Some(1),
+                ..rule(1)
+            },
+            FareRule {
+                contains_id: Some(2),
+                ..rule(1)
+            }
+        ];
+        let zones_1_2 = ride_stops(&stops, 1, 3).unwrap();
+        assert_eq!(cheapest_fare(&fares, &rules, zones_1_2).unwrap().fare_id, 1);
+        let zones_1_2_3 = ride_stops(&stops, 1, 4).unwrap();
+        assert!(cheapest_fare(&fares, &rules, zones_1_2_3).is_none());
+        let zone_1 = ride_stops(&stops, 1, 2).unwrap();
+        assert!(cheapest_fare(&fares, &rules, zone_1).is_none());
+    }
+
+    #[test]
+    fn fares_of_other_agencies_do_not_apply() {
+        let mut stops = trip_stops();
+        stops.iter_mut().for_each(|s| s.agency_id = Some(7));
+        let fares = [
+            FareAttribute {
+                agency_id: Some(8),
+                ..fare(1, 1.0)
+            },
+            FareAttribute {
+                agency_id: Some(7),
+                ..fare(2, 2.0)
+            }
+        ];
+        assert_eq!(cheapest_fare(&fares, &[], &stops).unwrap().fare_id, 2);
+    }
+}
@@ -1,11 +1,14 @@
-use chrono::{NaiveDate, Timelike};
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDate, Timelike, Weekday};
 use itertools::Itertools;
-use maud::{html, Markup};
+use maud::{html, Markup, DOCTYPE};
 use std::collections::HashMap;
 
+use crate::i18n::Messages;
 use crate::model::*;
+use crate::service_days::service_pattern;
+use crate::walking::WalkingTime;
 
-fn route_type_css(route_type: i32) -> &'static str {
+pub fn route_type_css(route_type: i32) -> &'static str {
     match route_type {
         0 | 900..=906 => "tram",
         1 | 400..=404 => "subway",
@@ -31,8 +34,21 @@ fn route_sorting_key(route: &Route) -> (i32, String) {
     )
 }
 
+fn wheelchair_symbol(m: &Messages, availability: i32) -> Markup {
+    html! {
+        @if availability == 1 {
+            " " span class="accessibility" title=(m.wheelchair_accessible) {"♿"}
+        }
+    }
+}
+
 fn format_route(route: &Route) -> Markup {
-    html! {span class={"route" " " (route_type_css(route.route_type))} {(route.route_short_name)}}
+    let title = [&route.route_long_name, &route.agency_name]
+        .into_iter()
+        .flatten()
+        .filter(|name| !name.is_empty())
+        .join(" – ");
+    html! {span class={"route" " " (route_type_css(route.route_type))} title=(title) {(route.route_short_name)}}
 }
 /*
 fn departures_list(stop_times: &Vec<StopTimesExtra>) -> Markup {
@@ -56,30 +72,41 @@ fn departures_list(stop_times: &Vec<StopTimesExtra>) -> Markup {
 }
 */
 
-fn departure_minutes(stop_times: &Vec<&StopTimesExtra>) -> Markup {
+// Departures with another headsign than the direction, e.g. short turns, are marked
+fn departure_minutes(m: &Messages, stop_times: &Vec<&StopTimesExtra>, headsign: &str) -> Markup {
     let stop_times_by_hour: HashMap<(NaiveDate, u32), Vec<&StopTimesExtra>> =
-        stop_times.into_iter().cloned().into_group_map_by(|st| {
+        stop_times.iter().cloned().into_group_map_by(|st| {
             let d = st.stop_time.departure_time;
             (d.date_naive(), d.time().hour())
         });
     let mut ordered_by_hour: Vec<((NaiveDate, u32), Vec<&StopTimesExtra>)> =
         stop_times_by_hour.into_iter().collect();
-    ordered_by_hour.sort_by_key(|(day_and_hour, _)| day_and_hour.clone());
+    ordered_by_hour.sort_by_key(|(day_and_hour, _)| *day_and_hour);
 
     html! {
     ul class="departure_minutes" {
     @for ((_, hour), list) in ordered_by_hour {
-        li {b class="trip_hour" {(hour)} {
+        li {b class="trip_hour" {(m.format_hour(hour))} {
             span {
-                @for st in list {
+                @for st in list.iter().filter(|st| st.headway_secs.is_none()) {
+                    " "
+                    span.trip_minute.link.accessible[st.trip.wheelchair_accessible == 1].other_headsign[st.headsign() != headsign] title=(st.headsign()) data-trip-id=(st.trip.trip_id) data-time-offset=(st.time_offset) onclick="onStopDepartureClicked(this)" {
+                        (format!("{:0>2?}", st.stop_time.departure_time.time().minute()))}}
+                // Frequency-based trips are summarized by their headway
+                @for (headway_secs, st) in list.iter().filter_map(|st| st.headway_secs.map(|h| (h, st))).unique_by(|(h, _)| *h) {
                     " "
-                    span class="trip_minute link" data-trip-id=(st.trip.trip_id) onclick="onStopDepartureClicked(this)" {
-                        (format!("{:0>2?}", st.stop_time.departure_time.time().minute()))}}}}}}}}
+                    span.trip_minute.link.accessible[st.trip.wheelchair_accessible == 1].other_headsign[st.headsign() != headsign] title=(st.headsign()) data-trip-id=(st.trip.trip_id) data-time-offset=(st.time_offset) onclick="onStopDepartureClicked(this)" {
+                        ((m.every_minutes)((headway_secs + 59) / 60))}}}}}}}}
 }
 
-fn departure_direction_tabs(stop_times: &Vec<&StopTimesExtra>, route_tab_id: usize) -> Markup {
+fn departure_direction_tabs(
+    m: &Messages,
+    stop_id: u32,
+    stop_times: &Vec<&StopTimesExtra>,
+    route_tab_id: usize
+) -> Markup {
     let stop_times_by_direction: HashMap<i32, Vec<&StopTimesExtra>> = stop_times
-        .into_iter()
+        .iter()
         .cloned()
         .into_group_map_by(|st| st.trip.direction_id);
     let mut ordered_routes: Vec<(i32, Vec<&StopTimesExtra>)> =
@@ -88,27 +115,29 @@ fn departure_direction_tabs(stop_times: &Vec<&StopTimesExtra>, route_tab_id: usi
 
     html! {
         div class="tabs" {
-            @for (tab, (_, route_stop_times)) in ordered_routes.into_iter().enumerate() {
+            @for (tab, (direction_id, route_stop_times)) in ordered_routes.into_iter().enumerate() {
                 input
                     type="radio"
                     checked=[if tab == 0 {Some("checked")} else {None}]
                     name=(format!("direction-tabs-{}", route_tab_id))
                     id=(format!("direction-tabs-{}-{}", route_tab_id, tab));
+                @let headsign = route_stop_times.iter().map(|st| st.headsign()).counts().into_iter().max_by_key(|(_, v)| *v).unwrap().0;
                 label
-                    for=(format!("direction-tabs-{}-{}", route_tab_id, tab)) {
-                    (route_stop_times.iter().map(|st| &st.trip.last_stop_name).counts().into_iter().max_by_key(|(_, v)| *v).unwrap().0)
-                }
+                    for=(format!("direction-tabs-{}-{}", route_tab_id, tab)) {(headsign)}
                 div class="tab" style="padding: 0em" {
-                    (departure_minutes(&route_stop_times))
+                    (departure_minutes(m, &route_stop_times, headsign))
+                    p class="timetable_link" {
+                        span class="link" data-stop-id=(stop_id) data-route-id=(route_stop_times[0].route.route_id) data-direction-id=(direction_id) onclick="onTimetableClicked(this)" {(m.timetable)}
+                    }
                 }
             }
         }
     }
 }
 
-fn departure_route_tabs(stop_times: &Vec<StopTimesExtra>) -> Markup {
+fn departure_route_tabs(m: &Messages, stop_id: u32, stop_times: &[StopTimesExtra]) -> Markup {
     let stop_times_by_route_id: HashMap<&Route, Vec<&StopTimesExtra>> =
-        stop_times.into_iter().into_group_map_by(|st| &st.route);
+        stop_times.iter().into_group_map_by(|st| &st.route);
     let mut ordered_routes: Vec<(&Route, Vec<&StopTimesExtra>)> =
         stop_times_by_route_id.into_iter().collect();
     ordered_routes.sort_by_key(|(route, _)| route_sorting_key(route));
@@ -124,32 +153,224 @@ fn departure_route_tabs(stop_times: &Vec<StopTimesExtra>) -> Markup {
                     label
                         for=(format!("route-tabs-{}", tab)) {(format_route(route))}
                     div class="tab" style="padding: 0em; border: none" {
-                        (departure_direction_tabs(&route_stop_times, tab))}}}}
-        @else {"There are no departures on this day"}
+                        (departure_direction_tabs(m, stop_id, &route_stop_times, tab))}}}}
+        @else {(m.no_departures)}
+    }
+}
+
+fn format_transfer_route(
+    m: &Messages,
+    route: &Option<Route>,
+    platform_code: &Option<String>
+) -> Markup {
+    html! {
+        @if let Some(route) = route {(format_route(route))}
+        @else {(m.all_lines)}
+        @if let Some(platform_code) = platform_code {" (" (platform_code) ")"}
+    }
+}
+
+fn transfers_table(m: &Messages, transfers: &[Transfer]) -> Markup {
+    html! {
+        @if !transfers.is_empty() {
+            details class="transfers" {
+                summary {(m.transfers)}
+                table class="departures_table" {
+                    tr {
+                        th {(m.from)}
+                        th {(m.to)}
+                        th {(m.transfer_type)}
+                        th {(m.min_time)}
+                    }
+                    @for t in transfers {
+                        tr {
+                            td {(format_transfer_route(m, &t.from_route, &t.from_platform_code))}
+                            td {
+                                @if let (Some(to_root_id), Some(to_stop_name)) = (t.to_root_id, &t.to_stop_name) {
+                                    span class="link" data-stop-id=(to_root_id) onclick="onTripStopClicked(this)" {(to_stop_name)}
+                                } @else {(m.same_vehicle)}
+                                " " (format_transfer_route(m, &t.to_route, &t.to_platform_code))
+                            }
+                            td {(m.transfer_type_name(t.transfer_type))}
+                            td {
+                                @if let Some(min_transfer_time) = t.min_transfer_time {
+                                    ((m.minutes)((min_transfer_time + 59) / 60))
+                                } @else {"-"}
+                            }
+                        }
+                    }
+                }
+            }
+        }
     }
 }
 
-pub fn stop_html(stop: &Stop, stop_times: &Vec<StopTimesExtra>) -> Markup {
+pub fn stop_html(
+    m: &Messages,
+    stop: &Stop,
+    stop_times: &[StopTimesExtra],
+    transfers: &[Transfer],
+    has_station_details: bool
+) -> Markup {
     html! {
         article class="stop_info" {
-            h1 {(stop.stop_name)}
-            (departure_route_tabs(stop_times))
+            h1 {(stop.stop_name) (wheelchair_symbol(m, stop.wheelchair_boarding))}
+            p {
+                @if has_station_details {
+                    span class="link" data-stop-id=(stop.stop_id) onclick="onStationDetailsClicked(this)" {(m.station_details)}
+                    " · "
+                }
+                span class="link" data-stop-id=(stop.stop_id) onclick="onDepartureBoardClicked(this)" {(m.departure_board)}
+            }
+            (departure_route_tabs(m, stop.stop_id, stop_times))
+            (transfers_table(m, transfers))
+        }
+    }
+}
+
+// Weekdays with consecutive runs as ranges, e.g. "Mon–Fri, Sun"
+fn format_weekdays(m: &Messages, weekdays: &[Weekday]) -> String {
+    if weekdays.len() == 7 {
+        return m.daily.to_string();
+    }
+    let name = |d: &Weekday| m.weekdays[d.num_days_from_monday() as usize];
+    weekdays
+        .chunk_by(|a, b| b.num_days_from_monday() == a.num_days_from_monday() + 1)
+        .map(|run| {
+            if run.len() >= 3 {
+                format!("{}–{}", name(&run[0]), name(&run[run.len() - 1]))
+            } else {
+                run.iter().map(name).join(", ")
+            }
+        })
+        .join(", ")
+}
+
+fn format_dates(m: &Messages, dates: &[NaiveDate]) -> String {
+    const LISTED_DATES: usize = 5;
+    let listed = dates
+        .iter()
+        .take(LISTED_DATES)
+        .map(|d| m.format_day_month(*d))
+        .join(", ");
+    if dates.len() > LISTED_DATES {
+        (m.and_more)(&listed, dates.len() - LISTED_DATES)
+    } else {
+        listed
+    }
+}
+
+// Summary of the operating days, e.g. "Mon–Fri except 24 Dec, 31 Dec"
+fn service_summary(m: &Messages, dates: &[NaiveDate]) -> String {
+    if dates.is_empty() {
+        return m.no_operating_days.to_string();
+    }
+    let pattern = service_pattern(dates);
+    if pattern.weekdays.is_empty() {
+        return (m.only_on)(&format_dates(m, &pattern.also));
+    }
+
+    let mut summary = format_weekdays(m, &pattern.weekdays);
+    if !pattern.except.is_empty() {
+        summary += &(m.except)(&format_dates(m, &pattern.except));
+    }
+    if !pattern.also.is_empty() {
+        summary += &(m.also_on)(&format_dates(m, &pattern.also));
+    }
+    summary
+}
+
+fn month_calendar(
+    m: &Messages,
+    first_of_month: NaiveDate,
+    dates: &[NaiveDate],
+    selected: NaiveDate
+) -> Markup {
+    let offset = first_of_month.weekday().num_days_from_monday() as i64;
+    let days_in_month = first_of_month
+        .iter_days()
+        .take_while(|d| d.month() == first_of_month.month())
+        .count() as i64;
+    let weeks = (offset + days_in_month + 6) / 7;
+
+    html! {
+        table class="month_calendar" {
+            caption {(m.months[first_of_month.month0() as usize]) " " (first_of_month.year())}
+            tr {
+                @for weekday in m.weekdays {th {(weekday)}}
+            }
+            @for week in 0..weeks {
+                tr {
+                    @for weekday in 0..7 {
+                        @let day = week * 7 + weekday - offset;
+                        @if day < 0 || day >= days_in_month {
+                            td {}
+                        } @else {
+                            @let date = first_of_month + Duration::days(day);
+                            td.active[dates.binary_search(&date).is_ok()].selected[date == selected] {(date.day())}
+                        }
+                    }
+                }
+            }
         }
     }
 }
 
-pub fn trip_html(trip: &Trip, route: &Route, trip_stops: &Vec<(StopTime, Stop, u32)>) -> Markup {
+// Month calendars of the operating days of a service with a summary
+fn operating_days(m: &Messages, dates: &[NaiveDate], selected: NaiveDate) -> Markup {
+    let months = match (dates.first(), dates.last()) {
+        (Some(first), Some(last)) => std::iter::successors(first.with_day(1), |d| {
+            d.checked_add_months(chrono::Months::new(1))
+        })
+        .take_while(|d| d <= last)
+        .collect_vec(),
+        _ => Vec::new()
+    };
+
+    html! {
+        details class="operating_days" {
+            summary {(m.operating_days) ": " (service_summary(m, dates))}
+            @if let (Some(first), Some(last)) = (dates.first(), dates.last()) {
+                p {i {((m.date_range)(&m.format_date(*first), &m.format_date(*last)))}}
+            }
+            div class="month_calendars" {
+                @for month in months {(month_calendar(m, month, dates, selected))}
+            }
+        }
+    }
+}
+
+pub fn trip_html(
+    m: &Messages,
+    trip: &Trip,
+    route: &Route,
+    trip_stops: &[(StopTime, Stop, u32)],
+    has_fares: bool,
+    service_dates: &[NaiveDate],
+    date: NaiveDate
+) -> Markup {
     html! {
         p {
             {(format_route(route))} " "
-            (trip.first_stop_name) " ➜ " (trip.last_stop_name)
+            (trip.first_stop_name) " ➜ " (trip.headsign())
+            (wheelchair_symbol(m, trip.wheelchair_accessible))
+            @if trip.bikes_allowed == 1 {
+                " " span class="accessibility" title=(m.bicycles_allowed) {"🚲"}
+            }
+        }
+        @if let Some(route_long_name) = route.route_long_name.as_ref().filter(|n| !n.is_empty()) {
+            p class="route_long_name" {(route_long_name)}
         }
         table style="font-size: 90%" {
             tr {
-                th {"Station"}
-                th {"Arrival"}
-                th {"Departure"}
-                th {"Platform"}
+                th {(m.station)}
+                th {(m.arrival)}
+                th {(m.departure)}
+                th {(m.platform)}
+                @if has_fares {
+                    th {(m.from)}
+                    th {(m.to)}
+                }
             }
             @for (st, stop, root_id) in trip_stops {
                 tr {
@@ -157,15 +378,229 @@ pub fn trip_html(trip: &Trip, route: &Route, trip_stops: &Vec<(StopTime, Stop, u
                         span style="color: #666" class="link" data-stop-id=(root_id)  onclick="onTripStopClicked(this)" {
                             (stop.stop_name)
                         }
+                        (wheelchair_symbol(m, stop.wheelchair_boarding))
                     }
-                    td {(st.arrival_time.time().format("%H:%M"))}
-                    td {(st.departure_time.time().format("%H:%M"))}
+                    td {(m.format_time(st.arrival_time.time()))}
+                    td {(m.format_time(st.departure_time.time()))}
                     td {(stop.platform_code.as_ref().unwrap_or(&"-".to_string()))}
+                    @if has_fares {
+                        td {input type="radio" name="fare-from" value=(stop.stop_id) onchange="onFareStopChanged()";}
+                        td {input type="radio" name="fare-to" value=(stop.stop_id) onchange="onFareStopChanged()";}
+                    }
                 }
             }
         }
+        @if has_fares {
+            div id="trip-fare" data-trip-id=(trip.trip_id) {
+                i {(m.select_fare_stops)}
+            }
+        }
         p {
-            i {"Number of stations: " (trip_stops.len())}
+            i {(m.number_of_stations) (trip_stops.len())}
+        }
+        (operating_days(m, service_dates, date))
+        // The diagram is loaded by the page, which knows the feed
+        details class="route_diagram" data-route-id=(route.route_id) data-direction-id=(trip.direction_id) {
+            summary {(m.route_diagram)}
+            img alt=(m.route_diagram);
+            a class="route_diagram_download" {(m.download)}
+        }
+    }
+}
+
+fn format_platform(node: &StationNode) -> Markup {
+    html! {
+        @if let Some(platform_code) = &node.stop.platform_code {(platform_code)}
+        @else {(node.stop.stop_name)}
+    }
+}
+
+fn format_walking_time(m: &Messages, time: &WalkingTime) -> String {
+    let minutes = (m.minutes)(time.seconds.div_ceil(60) as i32);
+    if time.estimated {
+        format!("~{}", minutes)
+    } else {
+        minutes
+    }
+}
+
+pub fn station_html(
+    m: &Messages,
+    station: &Stop,
+    nodes: &[StationNode],
+    platforms: &[&StationNode],
+    walking_times: &[Vec<WalkingTime>]
+) -> Markup {
+    let entrances = nodes.iter().filter(|n| n.location_type == 2).collect_vec();
+
+    html! {
+        article class="station_info" {
+            h1 {(station.stop_name)}
+            @if !entrances.is_empty() {
+                h2 {(m.entrances)}
+                ul {
+                    @for e in &entrances {
+                        li {
+                            (e.stop.stop_name)
+                            @if let Some(level_name) = &e.level_name {" (" (level_name) ")"}
+                        }
+                    }
+                }
+            }
+            @if platforms.len() > 1 {
+                h2 {(m.walking_times)}
+                table class="walking_times" {
+                    tr {
+                        th {}
+                        @for p in platforms {th {(format_platform(p))}}
+                    }
+                    @for (from, times) in platforms.iter().zip(walking_times) {
+                        tr {
+                            th {(format_platform(from))}
+                            @for (to, time) in platforms.iter().zip(times) {
+                                td {
+                                    @if from.stop.stop_id == to.stop.stop_id {"-"}
+                                    @else {(format_walking_time(m, time))}
+                                }
+                            }
+                        }
+                    }
+                }
+                @if walking_times.iter().flatten().any(|t| t.estimated) {
+                    p {
+                        i {(m.estimated_walking_times)}
+                    }
+                }
+            }
+            h2 {(m.nodes)}
+            table style="font-size: 90%" {
+                tr {
+                    th {(m.name)}
+                    th {(m.node_type)}
+                    th {(m.level)}
+                }
+                @for n in nodes {
+                    tr {
+                        td {
+                            (n.stop.stop_name)
+                            @if let Some(platform_code) = &n.stop.platform_code {" (" (platform_code) ")"}
+                        }
+                        td {(m.location_type_name(n.location_type))}
+                        td {(n.level_name.as_deref().unwrap_or("-"))}
+                    }
+                }
+            }
+        }
+    }
+}
+
+pub fn fare_html(
+    m: &Messages,
+    ride: &[FareTripStop],
+    fare_products: &[FareProduct],
+    fare: Option<&FareAttribute>
+) -> Markup {
+    html! {
+        p {
+            b {(ride[0].stop_name) " ➜ " (ride[ride.len() - 1].stop_name)}
+        }
+        @if fare_products.is_empty() && fare.is_none() {
+            i {(m.no_fare)}
+        }
+        @if !fare_products.is_empty() {
+            ul class="fare_products" {
+                @for p in fare_products {
+                    li {
+                        (p.fare_product_name.as_deref().unwrap_or(m.ticket)) ": "
+                        b {(m.format_price(p.amount, &p.currency))}
+                        @if let Some(fare_media_name) = &p.fare_media_name {" (" (fare_media_name) ")"}
+                    }
+                }
+            }
+        }
+        @if let Some(fare) = fare {
+            p {
+                (m.fare) b {(m.format_price(fare.price, &fare.currency_type))}
+                ((m.fare_transfers)(fare.transfers))
+                @if let Some(transfer_duration) = fare.transfer_duration {
+                    ((m.within_minutes)((transfer_duration + 59) / 60))
+                }
+            }
+        }
+    }
+}
+
+// Minutes until the departure, the time for departures in more than an hour
+fn format_countdown(m: &Messages, departure: DateTime<Local>, now: DateTime<Local>) -> String {
+    let minutes = (departure - now).num_minutes();
+    if minutes < 1 {
+        m.now.to_string()
+    } else if minutes <= 60 {
+        (m.minutes)(minutes as i32)
+    } else {
+        m.format_time(departure.time())
+    }
+}
+
+// The list of a departure board, reloaded by the page
+pub fn board_departures_html(
+    m: &Messages,
+    departures: &[StopTimesExtra],
+    now: DateTime<Local>
+) -> Markup {
+    html! {
+        @if departures.is_empty() {
+            p class="no_departures" {(m.no_upcoming_departures)}
+        } @else {
+            table class="board_departures" {
+                tr {
+                    th {(m.line)}
+                    th {(m.destination)}
+                    th {(m.platform)}
+                    th class="countdown" {(m.departure)}
+                }
+                @for st in departures {
+                    tr {
+                        td {(format_route(&st.route))}
+                        td {(st.headsign())}
+                        td {(st.platform_code.as_deref().unwrap_or("-"))}
+                        td class="countdown" {(format_countdown(m, st.stop_time.departure_time, now))}
+                    }
+                }
+            }
+        }
+    }
+}
+
+// Full-screen departure board of a stop for station screens, without the map
+pub fn board_html(
+    m: &Messages,
+    stop: &Stop,
+    platforms: &[String],
+    departures: &[StopTimesExtra],
+    now: DateTime<Local>
+) -> Markup {
+    html! {
+        (DOCTYPE)
+        html lang=(m.language) {
+            head {
+                meta charset="utf-8";
+                meta name="viewport" content="width=device-width, initial-scale=1.0";
+                title {(stop.stop_name) " – " (m.departure_board)}
+                link rel="stylesheet" href="/public/transit.css";
+                link rel="stylesheet" href="/public/board.css";
+            }
+            body class="board" {
+                header {
+                    h1 {
+                        (stop.stop_name)
+                        @if !platforms.is_empty() {" " span class="board_platforms" {(m.platform) " " (platforms.join(", "))}}
+                    }
+                    span id="board-clock" {(m.format_time(now.time()))}
+                }
+                main id="board-departures" {(board_departures_html(m, departures, now))}
+                script src="/public/board.js" {}
+            }
         }
     }
 }
@@ -0,0 +1,462 @@
+use chrono::{Datelike, NaiveDate, NaiveTime};
+
+// Texts of the server-rendered HTML in one language. Texts with values are
+// functions so that each language can place the value where it belongs.
+pub struct Messages {
+    pub language: &'static str,
+    time_format: &'static str,
+    hour_format: &'static str,
+    date_format: &'static str,
+    decimal_separator: char,
+
+    // Stops
+    pub no_departures: &'static str,
+    pub every_minutes: fn(i32) -> String,
+    pub station_details: &'static str,
+    pub transfers: &'static str,
+    pub from: &'static str,
+    pub to: &'static str,
+    pub transfer_type: &'static str,
+    pub min_time: &'static str,
+    pub minutes: fn(i32) -> String,
+    pub all_lines: &'static str,
+    pub same_vehicle: &'static str,
+    // Indexed by transfer_type
+    transfer_types: [&'static str; 6],
+
+    // Trips
+    pub wheelchair_accessible: &'static str,
+    pub bicycles_allowed: &'static str,
+    pub station: &'static str,
+    pub arrival: &'static str,
+    pub departure: &'static str,
+    pub platform: &'static str,
+    pub select_fare_stops: &'static str,
+    pub number_of_stations: &'static str,
+
+    // Stations
+    pub entrances: &'static str,
+    pub walking_times: &'static str,
+    pub estimated_walking_times: &'static str,
+    pub nodes: &'static str,
+    pub name: &'static str,
+    pub node_type: &'static str,
+    pub level: &'static str,
+    // Indexed by location_type
+    location_types: [&'static str; 5],
+
+    // Fares
+    pub no_fare: &'static str,
+    pub ticket: &'static str,
+    pub fare: &'static str,
+    pub fare_transfers: fn(Option<i32>) -> String,
+    pub within_minutes: fn(i32) -> String,
+    pub invalid_ride: &'static str,
+
+    // Timetables
+    pub timetable: &'static str,
+    pub hour: &'static str,
+    // Monday to Friday, Saturday, Sunday
+    pub timetable_columns: [&'static str; 3],
+    // Monday to Sunday
+    pub weekdays: [&'static str; 7],
+    pub only_on: fn(&str) -> String,
+    pub except_on: fn(&str) -> String,
+    pub timetable_week: fn(&str, &str) -> String,
+    pub holiday_service: fn(&str) -> String,
+
+    // Operating days
+    pub operating_days: &'static str,
+    pub no_operating_days: &'static str,
+    pub daily: &'static str,
+    pub except: fn(&str) -> String,
+    pub also_on: fn(&str) -> String,
+    pub and_more: fn(&str, usize) -> String,
+    pub date_range: fn(&str, &str) -> String,
+    pub months: [&'static str; 12],
+    pub short_months: [&'static str; 12],
+    day_month: fn(u32, &str) -> String,
+
+    // Departure boards
+    pub departure_board: &'static str,
+    pub line: &'static str,
+    pub destination: &'static str,
+    pub now: &'static str,
+    pub no_upcoming_departures: &'static str,
+
+    // Route diagrams
+    pub route_diagram: &'static str,
+    pub download: &'static str
+}
+
+static EN: Messages = Messages {
+    language: "en",
+    time_format: "%H:%M",
+    hour_format: "%-H",
+    date_format: "%Y-%m-%d",
+    decimal_separator: '.',
+
+    no_departures: "There are no departures on this day",
+    every_minutes: |n| format!("every {} min", n),
+    station_details: "Station details",
+    transfers: "Transfers",
+    from: "From",
+    to: "To",
+    transfer_type: "Type",
+    min_time: "Min. time",
+    minutes: |n| format!("{} min", n),
+    all_lines: "All lines",
+    same_vehicle: "Same vehicle",
+    transfer_types: [
+        "Recommended",
+        "Guaranteed",
+        "Minimum time",
+        "Not possible",
+        "Stay on board",
+        "Change vehicle"
+    ],
+
+    wheelchair_accessible: "Wheelchair accessible",
+    bicycles_allowed: "Bicycles allowed",
+    station: "Station",
+    arrival: "Arrival",
+    departure: "Departure",
+    platform: "Platform",
+    select_fare_stops: "Select a boarding and an alighting stop to see the fare",
+    number_of_stations: "Number of stations: ",
+
+    entrances: "Entrances",
+    walking_times: "Walking times between platforms",
+    estimated_walking_times: "~ Estimated from the distance, no pathways available",
+    nodes: "Nodes",
+    name: "Name",
+    node_type: "Type",
+    level: "Level",
+    location_types: ["Platform", "Station", "Entrance", "Node", "Boarding area"],
+
+    no_fare: "No fare information available for this ride",
+    ticket: "Ticket",
+    fare: "Fare: ",
+    fare_transfers: |transfers| match transfers {
+        Some(0) => ", no transfers".to_string(),
+        Some(n) => format!(", {} transfer(s)", n),
+        None => ", unlimited transfers".to_string()
+    },
+    within_minutes: |n| format!(" within {} min", n),
+    invalid_ride: "The alighting stop must follow the boarding stop",
+
+    timetable: "Timetable",
+    hour: "Hour",
+    timetable_columns: ["Monday – Friday", "Saturday", "Sunday and holidays"],
+    weekdays: ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"],
+    only_on: |days| format!("only on {}", days),
+    except_on: |days| format!("not on {}", days),
+    timetable_week: |first, last| format!("Departures of the week from {} to {}", first, last),
+    holiday_service: |dates| format!("{}: service as on Sundays and holidays", dates),
+
+    operating_days: "Operating days",
+    no_operating_days: "This trip has no operating days",
+    daily: "daily",
+    except: |dates| format!(" except {}", dates),
+    also_on: |dates| format!(", also on {}", dates),
+    and_more: |dates, n| format!("{} and {} more", dates, n),
+    date_range: |first, last| format!("from {} to {}", first, last),
+    months: [
+        "January",
+        "February",
+        "March",
+        "April",
+        "May",
+        "June",
+        "July",
+        "August",
+        "September",
+        "October",
+        "November",
+        "December"
+    ],
+    short_months: [
+        "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"
+    ],
+    day_month: |day, month| format!("{} {}", day, month),
+
+    departure_board: "Departure board",
+    line: "Line",
+    destination: "Destination",
+    now: "now",
+    no_upcoming_departures: "No departures in the next hours",
+
+    route_diagram: "Line diagram",
+    download: "Download"
+};
+
+static DE: Messages = Messages {
+    language: "de",
+    time_format: "%H:%M",
+    hour_format: "%-H",
+    date_format: "%d.%m.%Y",
+    decimal_separator: ',',
+
+    no_departures: "An diesem Tag gibt es keine Abfahrten",
+    every_minutes: |n| format!("alle {} Min.", n),
+    station_details: "Stationsdetails",
+    transfers: "Umstiege",
+    from: "Von",
+    to: "Nach",
+    transfer_type: "Art",
+    min_time: "Mindestzeit",
+    minutes: |n| format!("{} Min.", n),
+    all_lines: "Alle Linien",
+    same_vehicle: "Im selben Fahrzeug",
+    transfer_types: [
+        "Empfohlen",
+        "Garantiert",
+        "Mindestzeit",
+        "Nicht möglich",
+        "Im Fahrzeug bleiben",
+        "Fahrzeugwechsel"
+    ],
+
+    wheelchair_accessible: "Rollstuhlgerecht",
+    bicycles_allowed: "Fahrradmitnahme möglich",
+    station: "Haltestelle",
+    arrival: "Ankunft",
+    departure: "Abfahrt",
+    platform: "Gleis",
+    select_fare_stops: "Ein- und Ausstiegshaltestelle wählen, um den Fahrpreis zu sehen",
+    number_of_stations: "Anzahl der Haltestellen: ",
+
+    entrances: "Eingänge",
+    walking_times: "Gehzeiten zwischen den Bahnsteigen",
+    estimated_walking_times: "~ Aus der Entfernung geschätzt, keine Wege vorhanden",
+    nodes: "Knoten",
+    name: "Name",
+    node_type: "Art",
+    level: "Ebene",
+    location_types: [
+        "Bahnsteig",
+        "Station",
+        "Eingang",
+        "Knoten",
+        "Einstiegsbereich"
+    ],
+
+    no_fare: "Für diese Fahrt sind keine Tarifinformationen verfügbar",
+    ticket: "Fahrkarte",
+    fare: "Fahrpreis: ",
+    fare_transfers: |transfers| match transfers {
+        Some(0) => ", ohne Umstieg".to_string(),
+        Some(1) => ", 1 Umstieg".to_string(),
+        Some(n) => format!(", {} Umstiege", n),
+        None => ", beliebig viele Umstiege".to_string()
+    },
+    within_minutes: |n| format!(" innerhalb von {} Min.", n),
+    invalid_ride: "Die Ausstiegshaltestelle muss nach der Einstiegshaltestelle liegen",
+
+    timetable: "Fahrplan",
+    hour: "Stunde",
+    timetable_columns: ["Montag – Freitag", "Samstag", "Sonn- und Feiertag"],
+    weekdays: ["Mo", "Di", "Mi", "Do", "Fr", "Sa", "So"],
+    only_on: |days| format!("nur {}", days),
+    except_on: |days| format!("nicht {}", days),
+    timetable_week: |first, last| format!("Abfahrten der Woche vom {} bis {}", first, last),
+    holiday_service: |dates| format!("{}: Verkehr wie an Sonn- und Feiertagen", dates),
+
+    operating_days: "Verkehrstage",
+    no_operating_days: "Diese Fahrt hat keine Verkehrstage",
+    daily: "täglich",
+    except: |dates| format!(" außer {}", dates),
+    also_on: |dates| format!(", auch {}", dates),
+    and_more: |dates, n| format!("{} und {} weitere", dates, n),
+    date_range: |first, last| format!("vom {} bis {}", first, last),
+    months: [
+        "Januar",
+        "Februar",
+        "März",
+        "April",
+        "Mai",
+        "Juni",
+        "Juli",
+        "August",
+        "September",
+        "Oktober",
+        "November",
+        "Dezember"
+    ],
+    short_months: [
+        "Jan.", "Feb.", "März", "Apr.", "Mai", "Juni", "Juli", "Aug.", "Sep.", "Okt.", "Nov.",
+        "Dez."
+    ],
+    day_month: |day, month| format!("{}. {}", day, month),
+
+    departure_board: "Abfahrtstafel",
+    line: "Linie",
+    destination: "Ziel",
+    now: "sofort",
+    no_upcoming_departures: "Keine Abfahrten in den nächsten Stunden",
+
+    route_diagram: "Linienplan",
+    download: "Herunterladen"
+};
+
+static NL: Messages = Messages {
+    language: "nl",
+    time_format: "%H:%M",
+    hour_format: "%-H",
+    date_format: "%d-%m-%Y",
+    decimal_separator: ',',
+
+    no_departures: "Er zijn geen vertrekken op deze dag",
+    every_minutes: |n| format!("elke {} min", n),
+    station_details: "Stationsinformatie",
+    transfers: "Overstappen",
+    from: "Van",
+    to: "Naar",
+    transfer_type: "Soort",
+    min_time: "Min. tijd",
+    minutes: |n| format!("{} min", n),
+    all_lines: "Alle lijnen",
+    same_vehicle: "Zelfde voertuig",
+    transfer_types: [
+        "Aanbevolen",
+        "Gegarandeerd",
+        "Minimale tijd",
+        "Niet mogelijk",
+        "Blijven zitten",
+        "Overstappen"
+    ],
+
+    wheelchair_accessible: "Rolstoeltoegankelijk",
+    bicycles_allowed: "Fietsen toegestaan",
+    station: "Halte",
+    arrival: "Aankomst",
+    departure: "Vertrek",
+    platform: "Spoor",
+    select_fare_stops: "Kies een opstap- en een uitstaphalte om de prijs te zien",
+    number_of_stations: "Aantal haltes: ",
+
+    entrances: "Ingangen",
+    walking_times: "Looptijden tussen perrons",
+    estimated_walking_times: "~ Geschat op basis van de afstand, geen looppaden beschikbaar",
+    nodes: "Knooppunten",
+    name: "Naam",
+    node_type: "Soort",
+    level: "Verdieping",
+    location_types: ["Perron", "Station", "Ingang", "Knooppunt", "Instapzone"],
+
+    no_fare: "Geen tariefinformatie beschikbaar voor deze rit",
+    ticket: "Kaartje",
+    fare: "Prijs: ",
+    fare_transfers: |transfers| match transfers {
+        Some(0) => ", zonder overstap".to_string(),
+        Some(1) => ", 1 overstap".to_string(),
+        Some(n) => format!(", {} overstappen", n),
+        None => ", onbeperkt overstappen".to_string()
+    },
+    within_minutes: |n| format!(" binnen {} min", n),
+    invalid_ride: "De uitstaphalte moet na de opstaphalte komen",
+
+    timetable: "Dienstregeling",
+    hour: "Uur",
+    timetable_columns: ["Maandag – vrijdag", "Zaterdag", "Zon- en feestdagen"],
+    weekdays: ["ma", "di", "wo", "do", "vr", "za", "zo"],
+    only_on: |days| format!("alleen op {}", days),
+    except_on: |days| format!("niet op {}", days),
+    timetable_week: |first, last| format!("Vertrektijden van de week van {} tot {}", first, last),
+    holiday_service: |dates| format!("{}: dienstregeling als op zon- en feestdagen", dates),
+
+    operating_days: "Rijdagen",
+    no_operating_days: "Deze rit heeft geen rijdagen",
+    daily: "dagelijks",
+    except: |dates| format!(" behalve {}", dates),
+    also_on: |dates| format!(", ook op {}", dates),
+    and_more: |dates, n| format!("{} en {} andere", dates, n),
+    date_range: |first, last| format!("van {} tot {}", first, last),
+    months: [
+        "januari",
+        "februari",
+        "maart",
+        "april",
+        "mei",
+        "juni",
+        "juli",
+        "augustus",
+        "september",
+        "oktober",
+        "november",
+        "december"
+    ],
+    short_months: [
+        "jan", "feb", "mrt", "apr", "mei", "jun", "jul", "aug", "sep", "okt", "nov", "dec"
+    ],
+    day_month: |day, month| format!("{} {}", day, month),
+
+    departure_board: "Vertrekstaat",
+    line: "Lijn",
+    destination: "Bestemming",
+    now: "nu",
+    no_upcoming_departures: "Geen vertrekken in de komende uren",
+
+    route_diagram: "Lijnschema",
+    download: "Downloaden"
+};
+
+// American English with 12-hour times
+static EN_US: Messages = Messages {
+    language: "en-US",
+    time_format: "%-I:%M %p",
+    hour_format: "%-I %p",
+    ..EN
+};
+
+static CATALOGUE: [&Messages; 4] = [&EN_US, &EN, &DE, &NL];
+
+// Messages in the first requested language we have texts for, English otherwise
+pub fn messages(languages: &[String]) -> &'static Messages {
+    languages
+        .iter()
+        .find_map(|language| {
+            CATALOGUE
+                .iter()
+                .find(|m| m.language.eq_ignore_ascii_case(language))
+        })
+        .unwrap_or(&&EN)
+}
+
+impl Messages {
+    pub fn format_time(&self, time: NaiveTime) -> String {
+        time.format(self.time_format).to_string()
+    }
+
+    pub fn format_date(&self, date: NaiveDate) -> String {
+        date.format(self.date_format).to_string()
+    }
+
+    // Day and abbreviated month, e.g. "24 Dec"
+    pub fn format_day_month(&self, date: NaiveDate) -> String {
+        (self.day_month)(date.day(), self.short_months[date.month0() as usize])
+    }
+
+    pub fn format_hour(&self, hour: u32) -> String {
+        NaiveTime::from_hms_opt(hour, 0, 0)
+            .map(|time| time.format(self.hour_format).to_string())
+            .unwrap_or_else(|| hour.to_string())
+    }
+
+    pub fn format_price(&self, amount: f64, currency: &str) -> String {
+        let amount = format!("{:.2}", amount).replace('.', &self.decimal_separator.to_string());
+        format!("{} {}", amount, currency)
+    }
+
+    pub fn transfer_type_name(&self, transfer_type: i32) -> &'static str {
+        self.transfer_types
+            .get(transfer_type as usize)
+            .unwrap_or(&self.transfer_types[0])
+    }
+
+    pub fn location_type_name(&self, location_type: i32) -> &'static str {
+        self.location_types
+            .get(location_type as usize)
+            .unwrap_or(&self.location_types[0])
+    }
+}
@@ -0,0 +1,57 @@
+use rocket::request::{FromRequest, Outcome, Request};
+use std::convert::Infallible;
+
+// Languages requested with the lang parameter or the Accept-Language header,
+// ordered by preference. Names are translated into the first language with
+// a translation and fall back to the names of the feed.
+pub struct Languages(pub Vec<String>);
+
+// Language tags ordered by their quality, each followed by its primary
+// language, e.g. "de-CH, en;q=0.5" gives ["de-ch", "de", "en"]
+fn parse_languages(header: &str) -> Vec<String> {
+    let mut tags: Vec<(String, f32)> = header
+        .split(',')
+        .filter_map(|part| {
+            let mut params = part.split(';');
+            let tag = params.next()?.trim().to_lowercase();
+            let quality = match params.find_map(|p| p.trim().strip_prefix("q=")) {
+                Some(q) => q.parse().ok()?,
+                None => 1.0
+            };
+            (!tag.is_empty() && tag != "*" && quality > 0.0).then_some((tag, quality))
+        })
+        .collect();
+    tags.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+    let mut languages: Vec<String> = Vec::new();
+    for (tag, _) in tags {
+        let primary = tag.split('-').next().unwrap_or_default().to_string();
+        for language in [tag, primary] {
+            if !languages.contains(&language) {
+                languages.push(language);
+            }
+        }
+    }
+    languages
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for Languages {
+    type Error = Infallible;
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let mut languages = request
+            .query_value::<&str>("lang")
+            .and_then(|lang| lang.ok())
+            .map(parse_languages)
+            .unwrap_or_default();
+        if let Some(header) = request.headers().get_one("Accept-Language") {
+            for language in parse_languages(header) {
+                if !languages.contains(&language) {
+                    languages.push(language);
+                }
+            }
+        }
+        Outcome::Success(Languages(languages))
+    }
+}
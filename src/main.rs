@@ -1,32 +1,70 @@
 #[macro_use]
 extern crate rocket;
-use chrono::NaiveDate;
+use chrono::{Local, NaiveDate};
 use rocket::fs::FileServer;
-use rocket::http::Header;
 use rocket::http::RawStr;
-use rocket::response::{content, Redirect, Responder};
+use rocket::http::{ContentType, Header, Status};
+use rocket::response::{content, status, Redirect, Responder};
 use rocket::serde::json::Json;
 use rocket_async_compression::Compression;
 use rocket_sync_db_pools::database;
 use serde_json::value::RawValue;
 use serde_json::{json, Value};
+use std::collections::HashMap;
 use std::time::Instant;
 
 mod model;
 use model::*;
 mod html;
-use html::{stop_html, trip_html};
+use html::{board_departures_html, board_html, fare_html, station_html, stop_html, trip_html};
+mod fares;
+use fares::{cheapest_fare, ride_stops};
+mod walking;
+use walking::platform_walking_times;
+mod language;
+use language::Languages;
+mod i18n;
+use i18n::messages;
+mod timetable;
+use timetable::{representative_week, weekly_timetable};
+mod svg;
+use svg::{route_diagram_svg, timetable_svg};
+mod diagram;
+use diagram::route_diagram;
+mod service_days;
 
 #[derive(Responder)]
 struct CachedResponder<T> {
     inner: T,
-    cached_header: Header<'static>
+    cached_header: Header<'static>,
+    // Names are translated according to the Accept-Language header
+    vary_header: Header<'static>
 }
 impl<'r, 'o: 'r, T: Responder<'r, 'o>> CachedResponder<T> {
     fn new(inner: T) -> Self {
         CachedResponder {
             inner,
-            cached_header: Header::new("Cache-Control", "public, max-age=2592000")
+            cached_header: Header::new("Cache-Control", "public, max-age=2592000"),
+            vary_header: Header::new("Vary", "Accept-Language")
+        }
+    }
+}
+
+// Drawings which can be shown in the browser or downloaded
+#[derive(Responder)]
+struct SvgFile {
+    inner: (ContentType, String),
+    disposition_header: Header<'static>
+}
+impl SvgFile {
+    fn new(svg: String, file_name: &str, download: bool) -> Self {
+        let disposition = if download { "attachment" } else { "inline" };
+        SvgFile {
+            inner: (ContentType::SVG, svg),
+            disposition_header: Header::new(
+                "Content-Disposition",
+                format!("{}; filename=\"{}\"", disposition, file_name)
+            )
         }
     }
 }
@@ -34,6 +72,17 @@ impl<'r, 'o: 'r, T: Responder<'r, 'o>> CachedResponder<T> {
 #[database("gtfs_data")]
 struct Database(postgres::Client);
 
+// Version of the feed schemas the queries are written for
+include!("../import-gtfs/src/schema_version.rs");
+
+// Feeds imported before schema versions were introduced have version 1
+fn schema_version(row: &postgres::Row) -> i32 {
+    row.try_get::<_, Option<i32>>("schema_version")
+        .ok()
+        .flatten()
+        .unwrap_or(1)
+}
+
 fn alphanumeric_string(input: &str) -> Result<String, &str> {
     if input
         .chars()
@@ -45,102 +94,302 @@ fn alphanumeric_string(input: &str) -> Result<String, &str> {
     }
 }
 
-fn set_db_schema(conn: &mut postgres::Client, feed: &String) {
-    let feed = alphanumeric_string(&feed).unwrap();
+// Names of routes may contain any characters, file names only some
+fn alphanumeric_file_name(name: &str) -> String {
+    let name: String = name
+        .chars()
+        .map(|ch| if ch.is_ascii_alphanumeric() { ch } else { '_' })
+        .collect();
+    format!("route_{}", name)
+}
+
+// Responses of the feed endpoints, unknown feeds and feeds with another
+// schema version are answered with an error message
+type FeedResult<T> = Result<T, status::Custom<String>>;
+
+fn set_db_schema(conn: &mut postgres::Client, feed: &str) -> FeedResult<()> {
+    let not_found = || status::Custom(Status::NotFound, format!("Feed {} not found", feed));
+    let feed = alphanumeric_string(feed).map_err(|_| not_found())?;
 
     let stmt = conn
-        .prepare("SELECT COUNT(*)::int AS cnt FROM feeds WHERE feed_uid = $1")
+        .prepare("SELECT * FROM feeds WHERE feed_uid = $1")
         .unwrap();
 
-    let row = conn.query_one(&stmt, &[&feed]).unwrap();
-    let cnt: i32 = row.get("cnt");
-    if cnt != 1 {
-        panic!("Feed uid not found in database");
+    let rows = conn.query(&stmt, &[&feed]).unwrap();
+    if rows.len() != 1 {
+        return Err(not_found());
+    }
+
+    let version = schema_version(&rows[0]);
+    if version != SCHEMA_VERSION {
+        return Err(status::Custom(
+            Status::Conflict,
+            format!(
+                "Feed {} has schema version {} instead of {}, run `import-gtfs migrate`",
+                feed, version, SCHEMA_VERSION
+            )
+        ));
     }
 
     conn.execute(&format!("SET search_path=\"{}\", public", feed), &[])
         .expect("Error setting default schema path");
+    Ok(())
 }
 
 #[get("/<feed>/stops")]
-async fn stops(db: Database, feed: String) -> CachedResponder<Json<Box<RawValue>>> {
+async fn stops(
+    db: Database,
+    feed: String,
+    languages: Languages
+) -> FeedResult<CachedResponder<Json<Box<RawValue>>>> {
     db.run(move |conn| {
-        set_db_schema(conn, &feed);
+        set_db_schema(conn, &feed)?;
 
         let timer = Instant::now();
 
         let stmt = conn.prepare(include_str!("sql/stops_geojson.sql")).unwrap();
 
-        let row = conn.query_one(&stmt, &[]).unwrap();
+        let row = conn.query_one(&stmt, &[&languages.0]).unwrap();
         let stops_geojson: Box<RawValue> = RawValue::from_string(row.get("geojson")).unwrap();
 
         println!("Time elapsed in stops is: {:?}", timer.elapsed());
 
-        CachedResponder::new(Json(stops_geojson))
+        Ok(CachedResponder::new(Json(stops_geojson)))
     })
     .await
 }
 
-#[get("/<feed>/<date>/stops/<stop_id>")]
+// Departures at a stop and its platforms on a service day
+fn query_departures(
+    conn: &mut postgres::Client,
+    date: NaiveDate,
+    stop_id: u32,
+    agency: Option<u32>,
+    accessible: Option<bool>,
+    languages: &[String]
+) -> Vec<StopTimesExtra> {
+    let stmt = conn.prepare(include_str!("sql/departures.sql")).unwrap();
+    conn.query(
+        &stmt,
+        &[
+            &date,
+            &"Europe/Berlin",
+            &stop_id,
+            &agency,
+            &accessible,
+            &languages
+        ]
+    )
+    .unwrap()
+    .iter()
+    .map(|row| {
+        Ok(StopTimesExtra {
+            stop_time: stop_time_from_row(row)?,
+            trip: trip_from_row(row)?,
+            route: route_from_row(row)?,
+            time_offset: row.try_get("time_offset")?,
+            headway_secs: row.try_get("headway_secs")?,
+            platform_code: row.try_get("platform_code")?,
+            terminates: row.try_get("terminates")?
+        })
+    })
+    .collect::<Result<Vec<StopTimesExtra>, postgres::Error>>()
+    .unwrap()
+}
+
+#[get("/<feed>/<date>/stops/<stop_id>?<agency>&<accessible>")]
 async fn stop_information(
     db: Database,
     feed: String,
     date: String,
-    stop_id: u32
-) -> content::RawHtml<String> {
+    stop_id: u32,
+    agency: Option<u32>,
+    accessible: Option<bool>,
+    languages: Languages
+) -> FeedResult<content::RawHtml<String>> {
     db.run(move |conn| {
-        set_db_schema(conn, &feed);
+        set_db_schema(conn, &feed)?;
 
         let stmt_stop = conn.prepare(include_str!("sql/stop.sql")).unwrap();
         let stop = conn
-            .query_one(&stmt_stop, &[&stop_id])
-            .and_then(|row| Ok(stop_from_row(&row)?))
+            .query_one(&stmt_stop, &[&stop_id, &languages.0])
+            .and_then(|row| stop_from_row(&row))
             .unwrap();
 
         let start = Instant::now();
         let ddate = NaiveDate::parse_from_str(&date, "%Y-%m-%d").unwrap();
-        let stmt = conn.prepare(include_str!("sql/departures.sql")).unwrap();
-        let stop_times = conn
-            .query(&stmt, &[&ddate, &"Europe/Berlin", &stop_id])
+        let stop_times = query_departures(conn, ddate, stop_id, agency, accessible, &languages.0);
+
+        let stmt_transfers = conn
+            .prepare(include_str!("sql/stop_transfers.sql"))
+            .unwrap();
+        let transfers = conn
+            .query(&stmt_transfers, &[&stop_id, &languages.0])
             .unwrap()
             .iter()
-            .map(|row| {
-                Ok(StopTimesExtra {
-                    stop_time: stop_time_from_row(row)?,
-                    trip: trip_from_row(row)?,
-                    route: route_from_row(row)?
-                })
-            })
-            .collect::<Result<Vec<StopTimesExtra>, postgres::Error>>()
+            .map(transfer_from_row)
+            .collect::<Result<Vec<Transfer>, postgres::Error>>()
             .unwrap();
 
+        let has_station_details: bool = conn
+            .query_one(include_str!("sql/station_has_details.sql"), &[&stop_id])
+            .unwrap()
+            .get("has_details");
+
         let duration = start.elapsed();
 
         println!("Time elapsed in stop_information() is: {:?}", duration);
 
-        content::RawHtml(stop_html(&stop, &stop_times).into_string())
+        Ok(content::RawHtml(
+            stop_html(
+                messages(&languages.0),
+                &stop,
+                &stop_times,
+                &transfers,
+                has_station_details
+            )
+            .into_string()
+        ))
+    })
+    .await
+}
+
+// Number of departures shown on a departure board
+const BOARD_DEPARTURES: usize = 12;
+
+#[get("/<feed>/boards/<stop_id>?<platform>&<partial>")]
+async fn departure_board(
+    db: Database,
+    feed: String,
+    stop_id: u32,
+    platform: Vec<String>,
+    partial: Option<bool>,
+    languages: Languages
+) -> FeedResult<content::RawHtml<String>> {
+    db.run(move |conn| {
+        set_db_schema(conn, &feed)?;
+
+        let now = Local::now();
+        let today = now.date_naive();
+        // Trips of the previous service day may still run after midnight
+        let mut departures: Vec<StopTimesExtra> = [today.pred_opt(), Some(today), today.succ_opt()]
+            .into_iter()
+            .flatten()
+            .flat_map(|day| query_departures(conn, day, stop_id, None, None, &languages.0))
+            .filter(|st| {
+                st.stop_time.departure_time >= now
+                    && !st.terminates
+                    && (platform.is_empty()
+                        || st
+                            .platform_code
+                            .as_ref()
+                            .is_some_and(|p| platform.contains(p)))
+            })
+            .collect();
+        departures.sort_by_key(|st| st.stop_time.departure_time);
+        departures.truncate(BOARD_DEPARTURES);
+
+        let m = messages(&languages.0);
+        if partial == Some(true) {
+            return Ok(content::RawHtml(
+                board_departures_html(m, &departures, now).into_string()
+            ));
+        }
+
+        let stop = conn
+            .query_one(include_str!("sql/stop.sql"), &[&stop_id, &languages.0])
+            .and_then(|row| stop_from_row(&row))
+            .unwrap();
+        Ok(content::RawHtml(
+            board_html(m, &stop, &platform, &departures, now).into_string()
+        ))
+    })
+    .await
+}
+
+#[get("/<feed>/<date>/timetables/<stop_id>/<route_id>/<direction_id>")]
+async fn stop_timetable(
+    db: Database,
+    feed: String,
+    date: String,
+    stop_id: u32,
+    route_id: u32,
+    direction_id: i32,
+    languages: Languages
+) -> FeedResult<Option<(ContentType, String)>> {
+    db.run(move |conn| {
+        set_db_schema(conn, &feed)?;
+
+        let stop = conn
+            .query_one(include_str!("sql/stop.sql"), &[&stop_id, &languages.0])
+            .and_then(|row| stop_from_row(&row))
+            .unwrap();
+
+        let ddate = NaiveDate::parse_from_str(&date, "%Y-%m-%d").unwrap();
+        let week: Vec<(NaiveDate, Vec<StopTimesExtra>)> = representative_week(ddate)
+            .into_iter()
+            .map(|day| {
+                let mut stop_times = query_departures(conn, day, stop_id, None, None, &languages.0);
+                stop_times.retain(|st| {
+                    st.route.route_id == route_id && st.trip.direction_id == direction_id
+                });
+                (day, stop_times)
+            })
+            .collect();
+
+        // Not found if the route does not depart at this stop in this week
+        let Some(route) = week
+            .iter()
+            .flat_map(|(_, stop_times)| stop_times.iter())
+            .map(|st| st.route.clone())
+            .next()
+        else {
+            return Ok(None);
+        };
+        let timetable = weekly_timetable(&week);
+
+        Ok(Some((
+            ContentType::SVG,
+            timetable_svg(messages(&languages.0), &stop, &route, &timetable).into_string()
+        )))
     })
     .await
 }
 
-#[get("/<feed>/<date>/trips/<trip_id>")]
-async fn trip_information(db: Database, feed: String, date: String, trip_id: u32) -> Json<Value> {
+#[get("/<feed>/<date>/trips/<trip_id>?<offset>")]
+async fn trip_information(
+    db: Database,
+    feed: String,
+    date: String,
+    trip_id: u32,
+    offset: Option<i32>,
+    languages: Languages
+) -> FeedResult<Json<Value>> {
     db.run(move |conn| {
-        set_db_schema(conn, &feed);
+        set_db_schema(conn, &feed)?;
         let stmt_trip = conn.prepare(include_str!("sql/trip.sql")).unwrap();
         let stmt_trip_stop_times = conn
             .prepare(include_str!("sql/trip_stop_times.sql"))
             .unwrap();
 
         let (trip, route) = conn
-            .query_one(&stmt_trip, &[&trip_id])
+            .query_one(&stmt_trip, &[&trip_id, &languages.0])
             .and_then(|row| Ok((trip_from_row(&row)?, route_from_row(&row)?)))
             .unwrap();
         let mut features = vec![];
 
         let ddate = NaiveDate::parse_from_str(&date, "%Y-%m-%d").unwrap();
         let trip_stops = conn
-            .query(&stmt_trip_stop_times, &[&ddate, &"Europe/Berlin", &trip_id])
+            .query(
+                &stmt_trip_stop_times,
+                &[
+                    &ddate,
+                    &"Europe/Berlin",
+                    &trip_id,
+                    &offset.unwrap_or(0),
+                    &languages.0
+                ]
+            )
             .unwrap()
             .iter()
             .map(|row| {
@@ -153,6 +402,18 @@ async fn trip_information(db: Database, feed: String, date: String, trip_id: u32
             .collect::<Result<Vec<(StopTime, Stop, u32)>, postgres::Error>>()
             .unwrap();
 
+        let has_fares: bool = conn
+            .query_one(include_str!("sql/has_fares.sql"), &[])
+            .unwrap()
+            .get("has_fares");
+
+        let service_dates: Vec<NaiveDate> = conn
+            .query(include_str!("sql/trip_service_dates.sql"), &[&trip_id])
+            .unwrap()
+            .iter()
+            .map(|row| row.get("date"))
+            .collect();
+
         // Query trip shape
         let stmt_trip_shape = conn.prepare(include_str!("sql/trip_shape.sql")).unwrap();
 
@@ -163,7 +424,7 @@ async fn trip_information(db: Database, feed: String, date: String, trip_id: u32
             json!({
                 "type": "Trip",
                 "route_short_name": route.route_short_name,
-                "trip_info": trip_html(&trip, &route, &trip_stops).into_string()})
+                "trip_info": trip_html(messages(&languages.0), &trip, &route, &trip_stops, has_fares, &service_dates, ddate).into_string()})
         );
 
         features.push(trip_shape_geojson);
@@ -185,32 +446,262 @@ async fn trip_information(db: Database, feed: String, date: String, trip_id: u32
         let feature_collection = json!({
         "type": "FeatureCollection",
         "features": features});
-        Json(feature_collection)
+        Ok(Json(feature_collection))
+    })
+    .await
+}
+
+#[get("/<feed>/routes/<route_id>/diagram/<direction_id>?<download>")]
+async fn route_diagram_file(
+    db: Database,
+    feed: String,
+    route_id: u32,
+    direction_id: i32,
+    download: Option<bool>,
+    languages: Languages
+) -> FeedResult<SvgFile> {
+    db.run(move |conn| {
+        set_db_schema(conn, &feed)?;
+
+        let route = conn
+            .query_one(include_str!("sql/route.sql"), &[&route_id, &languages.0])
+            .and_then(|row| colored_route_from_row(&row))
+            .unwrap();
+
+        let patterns: Vec<Vec<u32>> = conn
+            .query(
+                include_str!("sql/route_patterns.sql"),
+                &[&route_id, &direction_id]
+            )
+            .unwrap()
+            .iter()
+            .map(|row| row.get("stations"))
+            .collect();
+        let diagram = route_diagram(&patterns);
+        let stop_ids: Vec<u32> = diagram.stations.iter().map(|s| s.stop_id).collect();
+
+        let names: HashMap<u32, String> = conn
+            .query(
+                include_str!("sql/stop_names.sql"),
+                &[&stop_ids, &languages.0]
+            )
+            .unwrap()
+            .iter()
+            .map(|row| (row.get("stop_id"), row.get("stop_name")))
+            .collect();
+
+        let mut interchanges: HashMap<u32, Vec<ColoredRoute>> = HashMap::new();
+        for row in conn
+            .query(
+                include_str!("sql/route_interchanges.sql"),
+                &[&stop_ids, &route_id, &languages.0]
+            )
+            .unwrap()
+        {
+            interchanges
+                .entry(row.get("root_id"))
+                .or_default()
+                .push(colored_route_from_row(&row).unwrap());
+        }
+
+        let destination = patterns
+            .first()
+            .and_then(|p| p.last())
+            .and_then(|stop_id| names.get(stop_id))
+            .map(String::as_str)
+            .unwrap_or("");
+        let file_name = format!(
+            "{}-{}.svg",
+            alphanumeric_file_name(&route.route.route_short_name),
+            direction_id
+        );
+        Ok(SvgFile::new(
+            route_diagram_svg(&route, destination, &diagram, &names, &interchanges).into_string(),
+            &file_name,
+            download == Some(true)
+        ))
     })
     .await
 }
 
-#[get("/<feed>/frequency/<date>/<z>/<x>/<y>/tile.pbf")]
+#[get("/<feed>/fares/<trip_id>?<from>&<to>")]
+async fn trip_fare(
+    db: Database,
+    feed: String,
+    trip_id: u32,
+    from: u32,
+    to: u32,
+    languages: Languages
+) -> FeedResult<content::RawHtml<String>> {
+    db.run(move |conn| {
+        set_db_schema(conn, &feed)?;
+
+        let trip_stops = conn
+            .query(
+                include_str!("sql/fare_trip_stops.sql"),
+                &[&trip_id, &languages.0]
+            )
+            .unwrap()
+            .iter()
+            .map(fare_trip_stop_from_row)
+            .collect::<Result<Vec<FareTripStop>, postgres::Error>>()
+            .unwrap();
+
+        let ride = match ride_stops(&trip_stops, from, to) {
+            Some(ride) => ride,
+            None => {
+                return Ok(content::RawHtml(
+                    messages(&languages.0).invalid_ride.to_string()
+                ))
+            }
+        };
+
+        let fare_products = conn
+            .query(
+                include_str!("sql/fare_leg_products.sql"),
+                &[&ride[0].route_id, &from, &to]
+            )
+            .unwrap()
+            .iter()
+            .map(fare_product_from_row)
+            .collect::<Result<Vec<FareProduct>, postgres::Error>>()
+            .unwrap();
+
+        let fare_attributes = conn
+            .query(include_str!("sql/fare_attributes.sql"), &[])
+            .unwrap()
+            .iter()
+            .map(fare_attribute_from_row)
+            .collect::<Result<Vec<FareAttribute>, postgres::Error>>()
+            .unwrap();
+        let fare_rules = conn
+            .query(include_str!("sql/fare_rules.sql"), &[])
+            .unwrap()
+            .iter()
+            .map(fare_rule_from_row)
+            .collect::<Result<Vec<FareRule>, postgres::Error>>()
+            .unwrap();
+        let fare = cheapest_fare(&fare_attributes, &fare_rules, ride);
+
+        Ok(content::RawHtml(
+            fare_html(messages(&languages.0), ride, &fare_products, fare).into_string()
+        ))
+    })
+    .await
+}
+
+#[get("/<feed>/stations/<stop_id>")]
+async fn station_information(
+    db: Database,
+    feed: String,
+    stop_id: u32,
+    languages: Languages
+) -> FeedResult<Json<Value>> {
+    db.run(move |conn| {
+        set_db_schema(conn, &feed)?;
+
+        let station = conn
+            .query_one(include_str!("sql/stop.sql"), &[&stop_id, &languages.0])
+            .and_then(|row| stop_from_row(&row))
+            .unwrap();
+
+        let nodes = conn
+            .query(
+                include_str!("sql/station_nodes.sql"),
+                &[&stop_id, &languages.0]
+            )
+            .unwrap()
+            .iter()
+            .map(station_node_from_row)
+            .collect::<Result<Vec<StationNode>, postgres::Error>>()
+            .unwrap();
+
+        let pathways = conn
+            .query(include_str!("sql/station_pathways.sql"), &[&stop_id])
+            .unwrap()
+            .iter()
+            .map(pathway_from_row)
+            .collect::<Result<Vec<Pathway>, postgres::Error>>()
+            .unwrap();
+
+        let platforms: Vec<&StationNode> = nodes.iter().filter(|n| n.location_type == 0).collect();
+        let walking_times = platform_walking_times(&platforms, &nodes, &pathways);
+
+        let mut features: Vec<Value> = pathways
+            .iter()
+            .map(|p| {
+                json!({
+                    "type": "Feature",
+                    "geometry": {
+                        "type": "LineString",
+                        "coordinates": [[p.from_coords.0, p.from_coords.1], [p.to_coords.0, p.to_coords.1]]
+                    },
+                    "properties": {
+                        "pathway_mode": p.pathway_mode,
+                        "traversal_time": p.traversal_time
+                    }
+                })
+            })
+            .collect();
+        features.extend(nodes.iter().map(|n| {
+            json!({
+                "type": "Feature",
+                "geometry": {
+                    "type": "Point",
+                    "coordinates": [n.stop.stop_lon, n.stop.stop_lat]
+                },
+                "properties": {
+                    "stop_name": n.stop.stop_name,
+                    "stop_id": n.stop.stop_id,
+                    "platform_code": n.stop.platform_code,
+                    "location_type": n.location_type,
+                    "level_name": n.level_name,
+                    "level_index": n.level_index
+                }
+            })
+        }));
+
+        Ok(Json(json!({
+            "type": "FeatureCollection",
+            "features": features,
+            "properties": {
+                "station_info": station_html(messages(&languages.0), &station, &nodes, &platforms, &walking_times).into_string()
+            }
+        })))
+    })
+    .await
+}
+
+#[get("/<feed>/frequency/<date>/<z>/<x>/<y>/tile.pbf?<agency>&<accessible>")]
+#[allow(clippy::too_many_arguments)]
 async fn segment_frequencies(
     db: Database,
     feed: String,
     date: String,
     z: i32,
     x: i32,
-    y: i32
-) -> CachedResponder<Vec<u8>> {
+    y: i32,
+    agency: Option<u32>,
+    accessible: Option<bool>
+) -> FeedResult<CachedResponder<Vec<u8>>> {
     db.run(move |conn| {
-        set_db_schema(conn, &feed);
+        set_db_schema(conn, &feed)?;
+
+        // Only unfiltered tiles are cached
+        let use_cache = agency.is_none() && accessible != Some(true);
 
         let ddate = NaiveDate::parse_from_str(&date, "%Y-%m-%d").unwrap();
-        let opt = conn
-            .query_opt(
+        let opt = if use_cache {
+            conn.query_opt(
                 include_str!("sql/frequency_tile_query_cache.sql"),
                 &[&ddate, &z, &x, &y]
             )
-            .unwrap();
+            .unwrap()
+        } else {
+            None
+        };
 
-        match opt {
+        Ok(match opt {
             Some(row) => {
                 println!(
                     "Cache: hit: date = {}, z = {}, x = {}, y = {})",
@@ -237,10 +728,12 @@ async fn segment_frequencies(
                     .unwrap();
 
                 let start = Instant::now();
-                let row = conn.query_one(&stmt, &[&ddate, &z, &x, &y]).unwrap();
+                let row = conn
+                    .query_one(&stmt, &[&ddate, &z, &x, &y, &agency, &accessible])
+                    .unwrap();
                 let freqmvt: Vec<u8> = row.get("freqmvt");
 
-                if z <= 15 && !freqmvt.is_empty() {
+                if use_cache && z <= 15 && !freqmvt.is_empty() {
                     conn.execute(
                         include_str!("sql/frequency_tile_insert_into_cache.sql"),
                         &[&ddate, &z, &x, &y, &freqmvt]
@@ -256,7 +749,31 @@ async fn segment_frequencies(
                 );
                 CachedResponder::new(freqmvt)
             }
-        }
+        })
+    })
+    .await
+}
+
+#[get("/<feed>/agencies")]
+async fn agencies(
+    db: Database,
+    feed: String,
+    languages: Languages
+) -> FeedResult<Json<Vec<Agency>>> {
+    db.run(move |conn| {
+        set_db_schema(conn, &feed)?;
+
+        let stmt = conn.prepare(include_str!("sql/agencies.sql")).unwrap();
+
+        let agencies = conn
+            .query(&stmt, &[&languages.0])
+            .unwrap()
+            .iter()
+            .map(agency_from_row)
+            .collect::<Result<Vec<Agency>, _>>()
+            .unwrap();
+
+        Ok(Json(agencies))
     })
     .await
 }
@@ -268,11 +785,23 @@ async fn feeds(db: Database) -> Json<Vec<FeedInfo>> {
             .prepare("SELECT * FROM feeds ORDER BY feed_title;")
             .unwrap();
 
+        // Feeds with other schema versions are left out until they are migrated
         let feeds = conn
             .query(&stmt, &[])
             .unwrap()
             .iter()
-            .map(|x| feed_from_row(x))
+            .filter(|row| {
+                let version = schema_version(row);
+                if version != SCHEMA_VERSION {
+                    println!(
+                        "Skip feed {} with schema version {}",
+                        row.get::<_, String>("feed_uid"),
+                        version
+                    );
+                }
+                version == SCHEMA_VERSION
+            })
+            .map(feed_from_row)
             .collect::<Result<Vec<FeedInfo>, _>>()
             .unwrap();
 
@@ -282,19 +811,33 @@ async fn feeds(db: Database) -> Json<Vec<FeedInfo>> {
 }
 
 #[get("/")]
-async fn index(db: Database) -> Redirect {
+async fn index(db: Database) -> Result<Redirect, status::Custom<String>> {
     db.run(move |conn| {
         let stmt = conn
-            .prepare("SELECT feed_uid FROM feeds ORDER BY feed_title LIMIT 1;")
+            .prepare("SELECT * FROM feeds ORDER BY feed_title;")
             .unwrap();
 
-        let row = conn.query_one(&stmt, &[]).unwrap();
-        let feed_uid: String = row.get("feed_uid");
+        let feed_uid: String = conn
+            .query(&stmt, &[])
+            .unwrap()
+            .iter()
+            .find(|row| schema_version(row) == SCHEMA_VERSION)
+            .ok_or_else(|| {
+                status::Custom(
+                    Status::ServiceUnavailable,
+                    format!(
+                        "No feed with schema version {} found, import a feed with import-gtfs \
+                         or run `import-gtfs migrate`",
+                        SCHEMA_VERSION
+                    )
+                )
+            })?
+            .get("feed_uid");
 
-        Redirect::to(format!(
+        Ok(Redirect::to(format!(
             "/public/transit-viewer.html?feed={}",
             RawStr::new(&feed_uid).percent_encode().as_str()
-        ))
+        )))
     })
     .await
 }
@@ -308,8 +851,14 @@ fn rocket() -> _ {
             routes![
                 stops,
                 stop_information,
+                stop_timetable,
+                departure_board,
                 trip_information,
+                station_information,
+                trip_fare,
+                route_diagram_file,
                 segment_frequencies,
+                agencies,
                 feeds,
                 index
             ]
@@ -1,4 +1,4 @@
-use chrono::{DateTime, Local};
+use chrono::{DateTime, Local, NaiveDate};
 use postgres::Row;
 use serde::Serialize;
 
@@ -7,58 +7,184 @@ pub struct Stop {
     pub stop_name: String,
     pub stop_lon: f64,
     pub stop_lat: f64,
-    pub platform_code: Option<String>
+    pub platform_code: Option<String>,
+    // 0 = no information, 1 = accessible, 2 = not accessible
+    pub wheelchair_boarding: i32
 }
 
 #[derive(Clone, Hash, PartialEq, Eq)]
 pub struct Route {
     pub route_id: u32,
     pub route_short_name: String,
-    pub route_type: i32
+    pub route_long_name: Option<String>,
+    pub route_type: i32,
+    pub agency_name: Option<String>
+}
+
+// Route with the colors of routes.txt, used for drawings
+pub struct ColoredRoute {
+    pub route: Route,
+    pub route_color: Option<String>,
+    pub route_text_color: Option<String>
+}
+
+pub struct Transfer {
+    pub transfer_type: i32,
+    pub min_transfer_time: Option<i32>,
+    pub from_platform_code: Option<String>,
+    pub from_route: Option<Route>,
+    // None for in-seat transfers without target stop
+    pub to_root_id: Option<u32>,
+    pub to_stop_name: Option<String>,
+    pub to_platform_code: Option<String>,
+    pub to_route: Option<Route>
+}
+
+pub struct StationNode {
+    pub stop: Stop,
+    // 0 = platform, 2 = entrance, 3 = generic node, 4 = boarding area
+    pub location_type: i32,
+    pub parent_station: Option<u32>,
+    pub level_name: Option<String>,
+    pub level_index: Option<f64>
+}
+
+pub struct Pathway {
+    pub from_stop_id: u32,
+    pub to_stop_id: u32,
+    pub pathway_mode: i32,
+    pub is_bidirectional: bool,
+    pub length: Option<f32>,
+    pub traversal_time: Option<i32>,
+    pub from_coords: (f64, f64),
+    pub to_coords: (f64, f64)
+}
+
+pub struct FareAttribute {
+    pub fare_id: u32,
+    pub price: f64,
+    pub currency_type: String,
+    // None = unlimited transfers
+    pub transfers: Option<i32>,
+    pub transfer_duration: Option<i32>,
+    pub agency_id: Option<u32>
+}
+
+pub struct FareRule {
+    pub fare_id: u32,
+    pub route_id: Option<u32>,
+    pub origin_id: Option<u32>,
+    pub destination_id: Option<u32>,
+    pub contains_id: Option<u32>
+}
+
+pub struct FareProduct {
+    pub fare_product_name: Option<String>,
+    pub amount: f64,
+    pub currency: String,
+    pub fare_media_name: Option<String>
+}
+
+pub struct FareTripStop {
+    pub stop_id: u32,
+    pub stop_name: String,
+    pub zone_id: Option<u32>,
+    pub route_id: u32,
+    pub agency_id: Option<u32>
+}
+
+#[derive(Serialize)]
+pub struct Agency {
+    pub agency_id: u32,
+    pub agency_name: String,
+    pub agency_url: String,
+    pub agency_timezone: String,
+    pub agency_lang: Option<String>,
+    pub agency_phone: Option<String>,
+    pub agency_fare_url: Option<String>
 }
 
 #[derive(Clone, Hash, PartialEq, Eq)]
 pub struct StopTime {
     pub arrival_time: DateTime<Local>,
-    pub departure_time: DateTime<Local>
+    pub departure_time: DateTime<Local>,
+    pub stop_headsign: Option<String>
 }
 
 #[derive(Clone, Hash, PartialEq, Eq)]
 pub struct StopTimesExtra {
     pub stop_time: StopTime,
     pub trip: Trip,
-    pub route: Route
+    pub route: Route,
+    // Offset of this run to the times of the trip (non-zero for trips in frequencies.txt)
+    pub time_offset: i32,
+    // Headway of frequency-based trips without exact times
+    pub headway_secs: Option<i32>,
+    pub platform_code: Option<String>,
+    // The stop is the last one of the trip
+    pub terminates: bool
+}
+
+impl StopTimesExtra {
+    // Destination shown at this stop, which may differ along the trip
+    pub fn headsign(&self) -> &str {
+        self.stop_time
+            .stop_headsign
+            .as_deref()
+            .filter(|h| !h.is_empty())
+            .unwrap_or_else(|| self.trip.headsign())
+    }
 }
 
 #[derive(Clone, Hash, PartialEq, Eq)]
 pub struct Trip {
     pub trip_id: u32,
     pub direction_id: i32,
+    pub trip_headsign: Option<String>,
     pub first_stop_name: String,
-    pub last_stop_name: String
+    pub last_stop_name: String,
+    pub wheelchair_accessible: i32,
+    pub bikes_allowed: i32
 }
 
-
-#[derive(Debug)]
-pub struct ShapePoint {
-    pub latitude: f64,
-    pub longitude: f64
+impl Trip {
+    // The last stop is only a fallback, loop lines and short turns have
+    // headsigns which differ from it
+    pub fn headsign(&self) -> &str {
+        self.trip_headsign
+            .as_deref()
+            .filter(|h| !h.is_empty())
+            .unwrap_or(&self.last_stop_name)
+    }
 }
 
 #[derive(Serialize)]
 pub struct FeedInfo {
-    pub feed_uid : String,
-    pub feed_title : String,
-    pub feed_publisher_name : String,
-    pub feed_publisher_url : String
+    pub feed_uid: String,
+    pub feed_title: String,
+    pub feed_publisher_name: String,
+    pub feed_publisher_url: String,
+    pub feed_lang: Option<String>,
+    pub feed_version: Option<String>,
+    pub feed_start_date: Option<NaiveDate>,
+    pub feed_end_date: Option<NaiveDate>,
+    pub imported_at: Option<DateTime<Local>>,
+    // First and last day with service
+    pub calendar_start_date: Option<NaiveDate>,
+    pub calendar_end_date: Option<NaiveDate>,
+    // Extent of all stops as [min_lon, min_lat, max_lon, max_lat]
+    pub bbox: Option<[f64; 4]>
 }
 
 pub fn trip_from_row(row: &Row) -> Result<Trip, postgres::error::Error> {
     Ok(Trip {
         trip_id: row.try_get("trip_id")?,
         direction_id: row.try_get("direction_id")?,
+        trip_headsign: row.try_get("trip_headsign")?,
         first_stop_name: row.try_get("first_stop_name")?,
-        last_stop_name: row.try_get("last_stop_name")?
+        last_stop_name: row.try_get("last_stop_name")?,
+        wheelchair_accessible: row.try_get("wheelchair_accessible")?,
+        bikes_allowed: row.try_get("bikes_allowed")?
     })
 }
 
@@ -66,14 +192,69 @@ pub fn route_from_row(row: &Row) -> Result<Route, postgres::error::Error> {
     Ok(Route {
         route_id: row.try_get("route_id")?,
         route_short_name: row.try_get("route_short_name")?,
-        route_type: row.try_get("route_type")?
+        route_long_name: row.try_get("route_long_name")?,
+        route_type: row.try_get("route_type")?,
+        agency_name: row.try_get("agency_name")?
+    })
+}
+
+pub fn colored_route_from_row(row: &Row) -> Result<ColoredRoute, postgres::error::Error> {
+    Ok(ColoredRoute {
+        route: route_from_row(row)?,
+        route_color: row.try_get("route_color")?,
+        route_text_color: row.try_get("route_text_color")?
+    })
+}
+
+// Routes of a transfer are optional and their columns carry a prefix
+fn optional_route_from_row(
+    row: &Row,
+    prefix: &str
+) -> Result<Option<Route>, postgres::error::Error> {
+    let route_id: Option<u32> = row.try_get(format!("{}_route_id", prefix).as_str())?;
+    route_id
+        .map(|route_id| {
+            Ok(Route {
+                route_id,
+                route_short_name: row.try_get(format!("{}_route_short_name", prefix).as_str())?,
+                route_long_name: row.try_get(format!("{}_route_long_name", prefix).as_str())?,
+                route_type: row.try_get(format!("{}_route_type", prefix).as_str())?,
+                agency_name: row.try_get(format!("{}_agency_name", prefix).as_str())?
+            })
+        })
+        .transpose()
+}
+
+pub fn transfer_from_row(row: &Row) -> Result<Transfer, postgres::error::Error> {
+    Ok(Transfer {
+        transfer_type: row.try_get("transfer_type")?,
+        min_transfer_time: row.try_get("min_transfer_time")?,
+        from_platform_code: row.try_get("from_platform_code")?,
+        from_route: optional_route_from_row(row, "from")?,
+        to_root_id: row.try_get("to_root_id")?,
+        to_stop_name: row.try_get("to_stop_name")?,
+        to_platform_code: row.try_get("to_platform_code")?,
+        to_route: optional_route_from_row(row, "to")?
+    })
+}
+
+pub fn agency_from_row(row: &Row) -> Result<Agency, postgres::error::Error> {
+    Ok(Agency {
+        agency_id: row.try_get("agency_id")?,
+        agency_name: row.try_get("agency_name")?,
+        agency_url: row.try_get("agency_url")?,
+        agency_timezone: row.try_get("agency_timezone")?,
+        agency_lang: row.try_get("agency_lang")?,
+        agency_phone: row.try_get("agency_phone")?,
+        agency_fare_url: row.try_get("agency_fare_url")?
     })
 }
 
 pub fn stop_time_from_row(row: &Row) -> Result<StopTime, postgres::error::Error> {
     Ok(StopTime {
         arrival_time: row.try_get("arrival_time")?,
-        departure_time: row.try_get("departure_time")?
+        departure_time: row.try_get("departure_time")?,
+        stop_headsign: row.try_get("stop_headsign")?
     })
 }
 
@@ -83,7 +264,71 @@ pub fn stop_from_row(row: &Row) -> Result<Stop, postgres::error::Error> {
         stop_name: row.try_get("stop_name")?,
         stop_lon: row.try_get("stop_lon")?,
         stop_lat: row.try_get("stop_lat")?,
-        platform_code: row.try_get("platform_code")?
+        platform_code: row.try_get("platform_code")?,
+        wheelchair_boarding: row.try_get("wheelchair_boarding")?
+    })
+}
+
+pub fn station_node_from_row(row: &Row) -> Result<StationNode, postgres::error::Error> {
+    Ok(StationNode {
+        stop: stop_from_row(row)?,
+        location_type: row.try_get("location_type")?,
+        parent_station: row.try_get("parent_station")?,
+        level_name: row.try_get("level_name")?,
+        level_index: row.try_get("level_index")?
+    })
+}
+
+pub fn pathway_from_row(row: &Row) -> Result<Pathway, postgres::error::Error> {
+    Ok(Pathway {
+        from_stop_id: row.try_get("from_stop_id")?,
+        to_stop_id: row.try_get("to_stop_id")?,
+        pathway_mode: row.try_get("pathway_mode")?,
+        is_bidirectional: row.try_get("is_bidirectional")?,
+        length: row.try_get("length")?,
+        traversal_time: row.try_get("traversal_time")?,
+        from_coords: (row.try_get("from_stop_lon")?, row.try_get("from_stop_lat")?),
+        to_coords: (row.try_get("to_stop_lon")?, row.try_get("to_stop_lat")?)
+    })
+}
+
+pub fn fare_attribute_from_row(row: &Row) -> Result<FareAttribute, postgres::error::Error> {
+    Ok(FareAttribute {
+        fare_id: row.try_get("fare_id")?,
+        price: row.try_get("price")?,
+        currency_type: row.try_get("currency_type")?,
+        transfers: row.try_get("transfers")?,
+        transfer_duration: row.try_get("transfer_duration")?,
+        agency_id: row.try_get("agency_id")?
+    })
+}
+
+pub fn fare_rule_from_row(row: &Row) -> Result<FareRule, postgres::error::Error> {
+    Ok(FareRule {
+        fare_id: row.try_get("fare_id")?,
+        route_id: row.try_get("route_id")?,
+        origin_id: row.try_get("origin_id")?,
+        destination_id: row.try_get("destination_id")?,
+        contains_id: row.try_get("contains_id")?
+    })
+}
+
+pub fn fare_product_from_row(row: &Row) -> Result<FareProduct, postgres::error::Error> {
+    Ok(FareProduct {
+        fare_product_name: row.try_get("fare_product_name")?,
+        amount: row.try_get("amount")?,
+        currency: row.try_get("currency")?,
+        fare_media_name: row.try_get("fare_media_name")?
+    })
+}
+
+pub fn fare_trip_stop_from_row(row: &Row) -> Result<FareTripStop, postgres::error::Error> {
+    Ok(FareTripStop {
+        stop_id: row.try_get("stop_id")?,
+        stop_name: row.try_get("stop_name")?,
+        zone_id: row.try_get("zone_id")?,
+        route_id: row.try_get("route_id")?,
+        agency_id: row.try_get("agency_id")?
     })
 }
 
@@ -92,6 +337,24 @@ pub fn feed_from_row(row: &Row) -> Result<FeedInfo, postgres::error::Error> {
         feed_uid: row.try_get("feed_uid")?,
         feed_title: row.try_get("feed_title")?,
         feed_publisher_name: row.try_get("feed_publisher_name")?,
-        feed_publisher_url: row.try_get("feed_publisher_url")?
+        feed_publisher_url: row.try_get("feed_publisher_url")?,
+        feed_lang: row.try_get("feed_lang")?,
+        feed_version: row.try_get("feed_version")?,
+        feed_start_date: row.try_get("feed_start_date")?,
+        feed_end_date: row.try_get("feed_end_date")?,
+        imported_at: row.try_get("imported_at")?,
+        calendar_start_date: row.try_get("calendar_start_date")?,
+        calendar_end_date: row.try_get("calendar_end_date")?,
+        bbox: match (
+            row.try_get("min_lon")?,
+            row.try_get("min_lat")?,
+            row.try_get("max_lon")?,
+            row.try_get("max_lat")?
+        ) {
+            (Some(min_lon), Some(min_lat), Some(max_lon), Some(max_lat)) => {
+                Some([min_lon, min_lat, max_lon, max_lat])
+            }
+            _ => None
+        }
     })
-}
\ No newline at end of file
+}
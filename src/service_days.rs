@@ -0,0 +1,52 @@
+// Describes the dates a service runs on as weekdays with exceptions
+use chrono::{Datelike, NaiveDate, Weekday};
+
+pub struct ServicePattern {
+    // Weekdays on which the service usually runs, empty for irregular services
+    pub weekdays: Vec<Weekday>,
+    // Dates on these weekdays without service
+    pub except: Vec<NaiveDate>,
+    // Dates on other weekdays with service
+    pub also: Vec<NaiveDate>
+}
+
+// A weekday belongs to the pattern if the service runs on more than half of
+// its occurrences between the first and the last date of service, and at
+// least twice so that a single date is not taken as a weekly service
+pub fn service_pattern(dates: &[NaiveDate]) -> ServicePattern {
+    let (Some(first), Some(last)) = (dates.first(), dates.last()) else {
+        return ServicePattern {
+            weekdays: Vec::new(),
+            except: Vec::new(),
+            also: Vec::new()
+        };
+    };
+
+    let all_days = first
+        .iter_days()
+        .take_while(|d| d <= last)
+        .collect::<Vec<_>>();
+    let weekdays: Vec<Weekday> = std::iter::successors(Some(Weekday::Mon), |d| Some(d.succ()))
+        .take(7)
+        .filter(|weekday| {
+            let occurrences = all_days.iter().filter(|d| d.weekday() == *weekday).count();
+            let active = dates.iter().filter(|d| d.weekday() == *weekday).count();
+            active >= 2 && active * 2 > occurrences
+        })
+        .collect();
+
+    let except = all_days
+        .into_iter()
+        .filter(|d| weekdays.contains(&d.weekday()) && dates.binary_search(d).is_err())
+        .collect();
+    let also = dates
+        .iter()
+        .filter(|d| !weekdays.contains(&d.weekday()))
+        .cloned()
+        .collect();
+    ServicePattern {
+        weekdays,
+        except,
+        also
+    }
+}
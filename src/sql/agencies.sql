@@ -0,0 +1 @@
+SELECT agency_id, translated('agency', 'agency_name', agency_id, agency_name, $1) AS agency_name, agency_url, agency_timezone, agency_lang, agency_phone, agency_fare_url FROM agency ORDER BY agency_name
\ No newline at end of file
@@ -7,19 +7,33 @@ helper(start_of_day) AS (
     SELECT ((service_date + INTERVAL '12 hours') AT TIME ZONE input.timezone - INTERVAL '12 hours') FROM input
 )
 SELECT
-    start_of_day + arrival_time * INTERVAL '1 second' AS arrival_time,
-    start_of_day + departure_time * INTERVAL '1 second' AS departure_time,
-    trip_id, trip_headsign, direction_id,
-    route_id, route_short_name, route_type, first_stop_name, last_stop_name
+    start_of_day + (arrival_time + time_offset) * INTERVAL '1 second' AS arrival_time,
+    start_of_day + (departure_time + time_offset) * INTERVAL '1 second' AS departure_time,
+    time_offset, CASE WHEN exact_times = 0 THEN headway_secs END AS headway_secs,
+    (SELECT platform_code FROM stops WHERE stops.stop_id = stop_times.stop_id) AS platform_code,
+    -- Trips ending here only arrive
+    stop_times.stop_sequence = (SELECT MAX(st.stop_sequence) FROM stop_times AS st WHERE st.trip_id = stop_times.trip_id) AS terminates,
+    -- Record translations of stop_times need the stop_sequence, so only values are translated
+    translated('stop_times', 'stop_headsign', NULL, stop_headsign, $6) AS stop_headsign,
+    trip_id, translated('trips', 'trip_headsign', trip_id, trip_headsign, $6) AS trip_headsign, direction_id,
+    COALESCE(wheelchair_accessible, 0) AS wheelchair_accessible, COALESCE(bikes_allowed, 0) AS bikes_allowed,
+    route_id, translated('routes', 'route_short_name', route_id, route_short_name, $6) AS route_short_name,
+    translated('routes', 'route_long_name', route_id, route_long_name, $6) AS route_long_name, route_type,
+    translated('agency', 'agency_name', agency_id, agency_name, $6) AS agency_name,
+    translated('stops', 'stop_name', first_stop_id, first_stop_name, $6) AS first_stop_name,
+    translated('stops', 'stop_name', last_stop_id, last_stop_name, $6) AS last_stop_name
 FROM 
-    stop_hierarchy NATURAL JOIN stop_times NATURAL JOIN trips NATURAL JOIN routes NATURAL JOIN trip_terminals, input, helper
+    stop_hierarchy NATURAL JOIN stop_times NATURAL JOIN trips NATURAL JOIN routes NATURAL JOIN trip_terminals NATURAL JOIN trip_offsets
+    LEFT JOIN agency USING (agency_id), input, helper
 WHERE stop_hierarchy.root_id = $3 AND 
+    ($4::OID IS NULL OR routes.agency_id = $4) AND
+    ($5::BOOL IS NOT TRUE OR trips.wheelchair_accessible = 1) AND
     (EXISTS
        (SELECT *
         FROM calendar
         WHERE calendar.service_id = trips.service_id
           AND (service_date >= calendar.start_date
-               AND service_date < calendar.end_date
+               AND service_date <= calendar.end_date
                AND CASE EXTRACT(DOW FROM service_date)
                        WHEN '0' THEN sunday
                        WHEN '1' THEN monday
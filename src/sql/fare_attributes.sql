@@ -0,0 +1 @@
+SELECT fare_id, price, currency_type, transfers, transfer_duration, agency_id FROM fare_attributes
@@ -0,0 +1,30 @@
+-- Fare products (fares v2) for a leg on route $1 from stop $2 to stop $3
+-- Among all matching leg rules, only those with the highest priority
+-- and the most specific conditions are used
+WITH
+from_areas AS (
+    SELECT area_id FROM stop_areas JOIN stop_hierarchy
+        ON stop_areas.stop_id IN (stop_hierarchy.stop_id, stop_hierarchy.root_id)
+    WHERE stop_hierarchy.stop_id = $2
+),
+to_areas AS (
+    SELECT area_id FROM stop_areas JOIN stop_hierarchy
+        ON stop_areas.stop_id IN (stop_hierarchy.stop_id, stop_hierarchy.root_id)
+    WHERE stop_hierarchy.stop_id = $3
+),
+matching_rules AS (
+    SELECT fare_product_id,
+        RANK() OVER (ORDER BY
+            COALESCE(rule_priority, 0) DESC,
+            (network_id IS NOT NULL)::INT + (from_area_id IS NOT NULL)::INT + (to_area_id IS NOT NULL)::INT DESC) AS rank
+    FROM fare_leg_rules
+    WHERE (network_id IS NULL OR network_id IN (SELECT network_id FROM route_networks WHERE route_id = $1))
+        AND (from_area_id IS NULL OR from_area_id IN (SELECT area_id FROM from_areas))
+        AND (to_area_id IS NULL OR to_area_id IN (SELECT area_id FROM to_areas))
+)
+SELECT DISTINCT fare_product_name, amount, currency, fare_media_name
+FROM matching_rules
+    JOIN fare_products ON fare_products.fare_product_id = matching_rules.fare_product_id
+    LEFT JOIN fare_media ON fare_media.fare_media_id = fare_products.fare_media_id
+WHERE rank = 1
+ORDER BY amount, fare_product_name, fare_media_name
@@ -0,0 +1 @@
+SELECT fare_id, route_id, origin_id, destination_id, contains_id FROM fare_rules
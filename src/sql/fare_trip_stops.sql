@@ -0,0 +1,7 @@
+SELECT stop_times.stop_id, translated('stops', 'stop_name', stops.stop_id, stops.stop_name, $2) AS stop_name, stops.zone_id, trips.route_id, routes.agency_id
+FROM stop_times
+    JOIN stops ON stops.stop_id = stop_times.stop_id
+    JOIN trips ON trips.trip_id = stop_times.trip_id
+    JOIN routes ON routes.route_id = trips.route_id
+WHERE stop_times.trip_id = $1
+ORDER BY stop_sequence
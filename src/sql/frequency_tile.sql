@@ -10,12 +10,14 @@ segment_frequencies_at_day(astop, bstop, cnt, path) AS (
   SELECT astop, bstop, SUM(trip_count), path
   FROM paths_in_tile NATURAL JOIN segment_frequencies, input
     WHERE
+  ($5::OID IS NULL OR segment_frequencies.agency_id = $5) AND
+  ($6::BOOL IS NOT TRUE OR segment_frequencies.wheelchair_accessible = 1) AND
   (EXISTS
        (SELECT *
         FROM calendar AS c
         WHERE c.service_id = segment_frequencies.service_id
           AND (input.date >= c.start_date
-               AND input.date < c.end_date
+               AND input.date <= c.end_date
                AND CASE EXTRACT(DOW FROM input.date)
                        WHEN '0' THEN sunday
                        WHEN '1' THEN monday
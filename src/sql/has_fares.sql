@@ -0,0 +1 @@
+SELECT EXISTS (SELECT 1 FROM fare_attributes) OR EXISTS (SELECT 1 FROM fare_leg_rules) AS has_fares
@@ -0,0 +1,7 @@
+SELECT route_id,
+    translated('routes', 'route_short_name', route_id, route_short_name, $2) AS route_short_name,
+    translated('routes', 'route_long_name', route_id, route_long_name, $2) AS route_long_name,
+    route_type, route_color, route_text_color,
+    translated('agency', 'agency_name', agency_id, agency_name, $2) AS agency_name
+FROM routes LEFT JOIN agency USING (agency_id)
+WHERE route_id = $1
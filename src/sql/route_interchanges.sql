@@ -0,0 +1,17 @@
+-- Routes other than $2 serving the stations $1
+WITH station_routes AS (
+    SELECT DISTINCT stop_hierarchy.root_id, trips.route_id
+    FROM stop_hierarchy
+    JOIN stop_times USING (stop_id)
+    JOIN trips USING (trip_id)
+    WHERE stop_hierarchy.root_id = ANY($1) AND trips.route_id <> $2
+)
+SELECT station_routes.root_id, route_id,
+    translated('routes', 'route_short_name', route_id, route_short_name, $3) AS route_short_name,
+    translated('routes', 'route_long_name', route_id, route_long_name, $3) AS route_long_name,
+    route_type, route_color, route_text_color,
+    translated('agency', 'agency_name', agency_id, agency_name, $3) AS agency_name
+FROM station_routes
+JOIN routes USING (route_id)
+LEFT JOIN agency USING (agency_id)
+ORDER BY station_routes.root_id, route_type, route_short_name
@@ -0,0 +1,12 @@
+-- Distinct station sequences of the trips of route $1 in direction $2, most frequent first
+SELECT stations, COUNT(*) AS trips
+FROM (
+    SELECT trip_id, array_agg(stop_hierarchy.root_id ORDER BY stop_times.stop_sequence) AS stations
+    FROM trips
+    JOIN stop_times USING (trip_id)
+    JOIN stop_hierarchy USING (stop_id)
+    WHERE trips.route_id = $1 AND COALESCE(trips.direction_id, 0) = $2
+    GROUP BY trip_id
+) AS trip_stations
+GROUP BY stations
+ORDER BY trips DESC, cardinality(stations) DESC
@@ -0,0 +1,7 @@
+SELECT EXISTS (
+    SELECT 1 FROM stop_hierarchy JOIN stops USING (stop_id)
+    WHERE root_id = $1 AND location_type IN (2, 3, 4)
+) OR EXISTS (
+    SELECT 1 FROM stop_hierarchy JOIN pathways ON pathways.from_stop_id = stop_hierarchy.stop_id
+    WHERE root_id = $1
+) AS has_details
@@ -0,0 +1,12 @@
+-- Entrances, platforms, generic nodes and boarding areas of the station $1
+SELECT stops.stop_id, translated('stops', 'stop_name', stops.stop_id, stops.stop_name, $2) AS stop_name, stops.stop_lon, stops.stop_lat, stops.platform_code,
+    -- Nodes without accessibility information inherit it from their parent
+    COALESCE(NULLIF(stops.wheelchair_boarding, 0), parent.wheelchair_boarding, 0) AS wheelchair_boarding,
+    COALESCE(stops.location_type, 0) AS location_type, stops.parent_station,
+    translated('levels', 'level_name', levels.level_id, level_name, $2) AS level_name, level_index
+FROM stop_hierarchy
+    JOIN stops ON stops.stop_id = stop_hierarchy.stop_id
+    LEFT JOIN stops AS parent ON parent.stop_id = stops.parent_station
+    LEFT JOIN levels ON levels.level_id = stops.level_id
+WHERE root_id = $1 AND stops.stop_id <> $1
+ORDER BY location_type, platform_code, stop_name
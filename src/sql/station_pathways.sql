@@ -0,0 +1,9 @@
+-- Pathways between the nodes of the station $1
+SELECT from_stop_id, to_stop_id, pathway_mode, is_bidirectional, length, traversal_time,
+    from_stop.stop_lon AS from_stop_lon, from_stop.stop_lat AS from_stop_lat,
+    to_stop.stop_lon AS to_stop_lon, to_stop.stop_lat AS to_stop_lat
+FROM stop_hierarchy
+    JOIN pathways ON pathways.from_stop_id = stop_hierarchy.stop_id
+    JOIN stops AS from_stop ON from_stop.stop_id = from_stop_id
+    JOIN stops AS to_stop ON to_stop.stop_id = to_stop_id
+WHERE root_id = $1
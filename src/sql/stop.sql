@@ -1 +1 @@
-SELECT stop_id, stop_name, stop_lon, stop_lat, platform_code FROM stops WHERE stop_id = $1
\ No newline at end of file
+SELECT stop_id, translated('stops', 'stop_name', stop_id, stop_name, $2) AS stop_name, stop_lon, stop_lat, platform_code, COALESCE(wheelchair_boarding, 0) AS wheelchair_boarding FROM stops WHERE stop_id = $1
\ No newline at end of file
@@ -0,0 +1,3 @@
+SELECT stop_id, translated('stops', 'stop_name', stop_id, stop_name, $2) AS stop_name
+FROM stops
+WHERE stop_id = ANY($1)
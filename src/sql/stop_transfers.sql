@@ -0,0 +1,33 @@
+-- Transfers from any platform of the station $1
+WITH transfers_at_station AS (
+    SELECT transfers.*,
+        COALESCE(from_route_id, (SELECT route_id FROM trips WHERE trip_id = from_trip_id)) AS from_route,
+        COALESCE(to_route_id, (SELECT route_id FROM trips WHERE trip_id = to_trip_id)) AS to_route
+    FROM stop_hierarchy JOIN transfers ON transfers.from_stop_id = stop_hierarchy.stop_id
+    WHERE stop_hierarchy.root_id = $1
+)
+SELECT
+    transfer_type, min_transfer_time,
+    from_stop.platform_code AS from_platform_code,
+    to_hierarchy.root_id AS to_root_id, translated('stops', 'stop_name', to_root.stop_id, to_root.stop_name, $2) AS to_stop_name, to_stop.platform_code AS to_platform_code,
+    from_routes.route_id AS from_route_id,
+    translated('routes', 'route_short_name', from_routes.route_id, from_routes.route_short_name, $2) AS from_route_short_name,
+    translated('routes', 'route_long_name', from_routes.route_id, from_routes.route_long_name, $2) AS from_route_long_name,
+    from_routes.route_type AS from_route_type,
+    translated('agency', 'agency_name', from_agency.agency_id, from_agency.agency_name, $2) AS from_agency_name,
+    to_routes.route_id AS to_route_id,
+    translated('routes', 'route_short_name', to_routes.route_id, to_routes.route_short_name, $2) AS to_route_short_name,
+    translated('routes', 'route_long_name', to_routes.route_id, to_routes.route_long_name, $2) AS to_route_long_name,
+    to_routes.route_type AS to_route_type,
+    translated('agency', 'agency_name', to_agency.agency_id, to_agency.agency_name, $2) AS to_agency_name
+FROM transfers_at_station
+    JOIN stops AS from_stop ON from_stop.stop_id = from_stop_id
+    -- In-seat transfers may have no target stop
+    LEFT JOIN stops AS to_stop ON to_stop.stop_id = to_stop_id
+    LEFT JOIN stop_hierarchy AS to_hierarchy ON to_hierarchy.stop_id = to_stop_id
+    LEFT JOIN stops AS to_root ON to_root.stop_id = to_hierarchy.root_id
+    LEFT JOIN routes AS from_routes ON from_routes.route_id = from_route
+    LEFT JOIN agency AS from_agency ON from_agency.agency_id = from_routes.agency_id
+    LEFT JOIN routes AS to_routes ON to_routes.route_id = to_route
+    LEFT JOIN agency AS to_agency ON to_agency.agency_id = to_routes.agency_id
+ORDER BY transfer_type = 1 DESC, to_stop_name, from_route_short_name, to_route_short_name
\ No newline at end of file
@@ -1,4 +1,4 @@
 SELECT json_build_object(
     'type', 'FeatureCollection',
     'features', json_agg(ST_AsGeoJSON(t.*)::json))::text AS geojson
-FROM (SELECT ST_MakePoint(stop_lon, stop_lat), stop_id, stop_name FROM stops WHERE parent_station IS NULL) AS t;
\ No newline at end of file
+FROM (SELECT ST_MakePoint(stop_lon, stop_lat), stop_id, translated('stops', 'stop_name', stop_id, stop_name, $1) AS stop_name FROM stops WHERE parent_station IS NULL) AS t;
\ No newline at end of file
@@ -1 +1 @@
-SELECT trip_id, direction_id, route_id, route_short_name, route_type, shape_id, first_stop_id, first_stop_name, first_departure, last_stop_id, last_stop_name, last_arrival, number_of_stations, dist_traveled FROM trips NATURAL JOIN routes NATURAL JOIN trip_terminals WHERE trip_id = $1
\ No newline at end of file
+SELECT trip_id, translated('trips', 'trip_headsign', trip_id, trip_headsign, $2) AS trip_headsign, direction_id, COALESCE(wheelchair_accessible, 0) AS wheelchair_accessible, COALESCE(bikes_allowed, 0) AS bikes_allowed, route_id, translated('routes', 'route_short_name', route_id, route_short_name, $2) AS route_short_name, translated('routes', 'route_long_name', route_id, route_long_name, $2) AS route_long_name, route_type, translated('agency', 'agency_name', agency_id, agency_name, $2) AS agency_name, shape_id, first_stop_id, translated('stops', 'stop_name', first_stop_id, first_stop_name, $2) AS first_stop_name, first_departure, last_stop_id, translated('stops', 'stop_name', last_stop_id, last_stop_name, $2) AS last_stop_name, last_arrival, number_of_stations, dist_traveled FROM trips NATURAL JOIN routes NATURAL JOIN trip_terminals LEFT JOIN agency USING (agency_id) WHERE trip_id = $1
\ No newline at end of file
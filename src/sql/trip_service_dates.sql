@@ -0,0 +1,26 @@
+-- Dates on which the service of a trip runs, from calendar.txt and calendar_dates.txt
+WITH service AS (
+    SELECT service_id FROM trips WHERE trip_id = $1
+)
+SELECT d.day::DATE AS date
+FROM calendar c
+JOIN service USING (service_id), generate_series(c.start_date, c.end_date, '1 day') AS d(day)
+WHERE CASE EXTRACT(ISODOW FROM d.day)
+        WHEN 1 THEN c.monday
+        WHEN 2 THEN c.tuesday
+        WHEN 3 THEN c.wednesday
+        WHEN 4 THEN c.thursday
+        WHEN 5 THEN c.friday
+        WHEN 6 THEN c.saturday
+        ELSE c.sunday
+    END
+    AND NOT EXISTS (
+        SELECT 1 FROM calendar_dates cd
+        WHERE cd.service_id = c.service_id AND cd.date = d.day AND cd.exception_type = 2
+    )
+UNION
+SELECT cd.date
+FROM calendar_dates cd
+JOIN service USING (service_id)
+WHERE cd.exception_type = 1
+ORDER BY date;
@@ -1,14 +1,19 @@
 WITH RECURSIVE
-input(idate, timezone) AS (
-    VALUES ($1::DATE, $2::TEXT)
+input(idate, timezone, time_offset) AS (
+    VALUES ($1::DATE, $2::TEXT, $4::INT)
 ),
 helper(start_of_day) AS (
     -- Time is measured from noon minus 12 hours (relevant for DST change)
     SELECT ((input.idate + INTERVAL '12 hours') AT TIME ZONE input.timezone - INTERVAL '12 hours') FROM input
 )
 SELECT 
-    start_of_day + arrival_time * INTERVAL '1 second' AS arrival_time,
-    start_of_day + departure_time * INTERVAL '1 second' AS departure_time,
-    stop_hierarchy.root_id, stop_hierarchy.stop_id, stops.stop_name, stops.platform_code, stops.stop_lon, stops.stop_lat
-FROM stop_times NATURAL JOIN stop_hierarchy NATURAL JOIN stops, input, helper
+    start_of_day + (arrival_time + time_offset) * INTERVAL '1 second' AS arrival_time,
+    start_of_day + (departure_time + time_offset) * INTERVAL '1 second' AS departure_time,
+    -- Record translations of stop_times need the stop_sequence, so only values are translated
+    translated('stop_times', 'stop_headsign', NULL, stop_headsign, $5) AS stop_headsign,
+    stop_hierarchy.root_id, stop_hierarchy.stop_id, translated('stops', 'stop_name', stops.stop_id, stops.stop_name, $5) AS stop_name, stops.platform_code, stops.stop_lon, stops.stop_lat,
+    -- Platforms without accessibility information inherit it from their station
+    COALESCE(NULLIF(stops.wheelchair_boarding, 0), parent.wheelchair_boarding, 0) AS wheelchair_boarding
+FROM stop_times NATURAL JOIN stop_hierarchy NATURAL JOIN stops
+    LEFT JOIN stops AS parent ON parent.stop_id = stops.parent_station, input, helper
 WHERE trip_id = $3 ORDER BY stop_sequence
\ No newline at end of file
@@ -0,0 +1,287 @@
+use chrono::{Datelike, NaiveDate, Weekday};
+use itertools::Itertools;
+use maud::{html, Markup, PreEscaped};
+use std::collections::HashMap;
+
+use crate::diagram::RouteDiagram;
+use crate::html::route_type_css;
+use crate::i18n::Messages;
+use crate::model::{ColoredRoute, Route, Stop};
+use crate::timetable::{Timetable, TimetableDeparture};
+
+// Sizes in millimetres of an A4 page
+const PAGE_WIDTH: f64 = 210.0;
+const MARGIN: f64 = 10.0;
+const HOUR_WIDTH: f64 = 16.0;
+const MINUTE_WIDTH: f64 = 7.0;
+const LINE_HEIGHT: f64 = 5.0;
+const HEADER_HEIGHT: f64 = 8.0;
+
+// Coordinates are rounded to a tenth of a millimetre
+fn coordinate(value: f64) -> String {
+    format!("{:.1}", value)
+}
+
+fn footnote_letter(footnote: usize) -> char {
+    (b'a' + (footnote % 26) as u8) as char
+}
+
+fn footnote_text(m: &Messages, days: &[Weekday], holidays: &[NaiveDate]) -> String {
+    let names = |days: &[Weekday]| {
+        days.iter()
+            .map(|d| m.weekdays[d.num_days_from_monday() as usize])
+            .join(", ")
+    };
+    // Footnotes only occur in the Monday to Friday column
+    let missing = [
+        Weekday::Mon,
+        Weekday::Tue,
+        Weekday::Wed,
+        Weekday::Thu,
+        Weekday::Fri
+    ]
+    .into_iter()
+    .filter(|d| !days.contains(d) && !holidays.iter().any(|h| h.weekday() == *d))
+    .collect_vec();
+    if missing.len() < days.len() {
+        (m.except_on)(&names(&missing))
+    } else {
+        (m.only_on)(&names(days))
+    }
+}
+
+fn minute_lines(departures: &[&TimetableDeparture], minutes_per_line: usize) -> usize {
+    departures.len().div_ceil(minutes_per_line).max(1)
+}
+
+// Print-ready wall timetable of one route and direction at a stop, with a
+// row per hour and a column each for Monday to Friday, Saturday and Sunday
+pub fn timetable_svg(m: &Messages, stop: &Stop, route: &Route, timetable: &Timetable) -> Markup {
+    let column_width = (PAGE_WIDTH - 2.0 * MARGIN - HOUR_WIDTH) / 3.0;
+    let minutes_per_line = (column_width / MINUTE_WIDTH).floor() as usize;
+    let column_x = |column: usize| MARGIN + HOUR_WIDTH + column as f64 * column_width;
+
+    let hours = timetable
+        .columns
+        .iter()
+        .flatten()
+        .map(|d| d.minutes / 60)
+        .unique()
+        .sorted()
+        .collect_vec();
+    let mut rows = Vec::new();
+    let mut y = 34.0 + HEADER_HEIGHT;
+    for hour in hours {
+        let departures = timetable.columns.each_ref().map(|column| {
+            column
+                .iter()
+                .filter(|d| d.minutes / 60 == hour)
+                .collect_vec()
+        });
+        let lines = departures
+            .iter()
+            .map(|d| minute_lines(d, minutes_per_line))
+            .max()
+            .unwrap_or(1);
+        let height = lines as f64 * LINE_HEIGHT + 1.5;
+        rows.push((hour, y, height, departures));
+        y += height;
+    }
+    let table_end = y;
+    let notes = timetable.footnotes.len() + usize::from(!timetable.holidays.is_empty());
+    let height = table_end + 6.0 + notes as f64 * LINE_HEIGHT + MARGIN;
+
+    html! {
+        svg xmlns="http://www.w3.org/2000/svg"
+            width=(format!("{}mm", PAGE_WIDTH)) height=(format!("{}mm", coordinate(height)))
+            viewBox=(format!("0 0 {} {}", PAGE_WIDTH, coordinate(height)))
+            font-family="sans-serif" font-size="4" {
+            rect x=(coordinate(MARGIN)) y="10" width="22" height="11" rx="1.5" fill="#333" {}
+            text x=(coordinate(MARGIN + 11.0)) y="17.6" text-anchor="middle" font-size="6" font-weight="bold" fill="white" {
+                (route.route_short_name)
+            }
+            text x=(coordinate(MARGIN + 26.0)) y="15" font-size="6" font-weight="bold" {(stop.stop_name)}
+            text x=(coordinate(MARGIN + 26.0)) y="21" font-size="4.5" {"➜ " (timetable.destination)}
+            text x=(coordinate(MARGIN)) y="29" font-size="3.2" fill="#666" {
+                ((m.timetable_week)(&m.format_date(timetable.first_day), &m.format_date(timetable.last_day)))
+            }
+
+            rect x=(coordinate(MARGIN)) y="34" width=(coordinate(PAGE_WIDTH - 2.0 * MARGIN)) height=(coordinate(HEADER_HEIGHT)) fill="#ddd" {}
+            text x=(coordinate(MARGIN + 1.5)) y="39.4" font-weight="bold" {(m.hour)}
+            @for (column, title) in m.timetable_columns.iter().enumerate() {
+                text x=(coordinate(column_x(column) + 1.5)) y="39.4" font-weight="bold" {(title)}
+            }
+
+            @for (i, &(hour, y, height, ref departures)) in rows.iter().enumerate() {
+                @if i % 2 == 1 {
+                    rect x=(coordinate(MARGIN)) y=(coordinate(y)) width=(coordinate(PAGE_WIDTH - 2.0 * MARGIN)) height=(coordinate(height)) fill="#f3f3f3" {}
+                }
+                text x=(coordinate(MARGIN + 1.5)) y=(coordinate(y + 4.2)) font-weight="bold" {(m.format_hour((hour % 24) as u32))}
+                @for (column, column_departures) in departures.iter().enumerate() {
+                    @for (j, d) in column_departures.iter().enumerate() {
+                        text
+                            x=(coordinate(column_x(column) + 1.5 + (j % minutes_per_line) as f64 * MINUTE_WIDTH))
+                            y=(coordinate(y + 4.2 + (j / minutes_per_line) as f64 * LINE_HEIGHT)) {
+                            (format!("{:02}", d.minutes % 60))
+                            @if let Some(footnote) = d.footnote {
+                                tspan font-size="2.6" dy="-1.4" {(footnote_letter(footnote))}
+                            }
+                        }
+                    }
+                }
+            }
+
+            @for column in 0..3 {
+                line x1=(coordinate(column_x(column))) y1="34" x2=(coordinate(column_x(column))) y2=(coordinate(table_end)) stroke="#999" stroke-width="0.3" {}
+            }
+            line x1=(coordinate(MARGIN)) y1=(coordinate(table_end)) x2=(coordinate(PAGE_WIDTH - MARGIN)) y2=(coordinate(table_end)) stroke="#999" stroke-width="0.3" {}
+
+            @for (i, days) in timetable.footnotes.iter().enumerate() {
+                text x=(coordinate(MARGIN)) y=(coordinate(table_end + 6.0 + i as f64 * LINE_HEIGHT)) font-size="3.5" {
+                    tspan font-weight="bold" {(footnote_letter(i))} (PreEscaped("&#160;&#160;")) (footnote_text(m, days, &timetable.holidays))
+                }
+            }
+            @if !timetable.holidays.is_empty() {
+                text x=(coordinate(MARGIN)) y=(coordinate(table_end + 6.0 + timetable.footnotes.len() as f64 * LINE_HEIGHT)) font-size="3.5" {
+                    ((m.holiday_service)(&timetable.holidays.iter().map(|d| m.format_date(*d)).join(", ")))
+                }
+            }
+        }
+    }
+}
+
+// Colors of the route classes in transit.css, for routes without route_color
+fn route_type_color(route_type: i32) -> &'static str {
+    match route_type_css(route_type) {
+        "tram" | "intercity" => "red",
+        "subway" => "darkblue",
+        "suburban" => "darkgreen",
+        "bus" => "purple",
+        _ => "darkgray"
+    }
+}
+
+fn hex_color(color: &Option<String>) -> Option<String> {
+    color
+        .as_deref()
+        .filter(|c| c.len() == 6 && c.chars().all(|ch| ch.is_ascii_hexdigit()))
+        .map(|c| format!("#{}", c))
+}
+
+// Background and text color of a route
+fn route_colors(route: &ColoredRoute) -> (String, String) {
+    (
+        hex_color(&route.route_color)
+            .unwrap_or_else(|| route_type_color(route.route.route_type).to_string()),
+        hex_color(&route.route_text_color).unwrap_or_else(|| "white".to_string())
+    )
+}
+
+// Rough width of a text, the size of fonts is not known before rendering
+fn text_width(text: &str, font_size: f64) -> f64 {
+    text.chars().count() as f64 * font_size * 0.55
+}
+
+fn badge_width(route: &ColoredRoute) -> f64 {
+    text_width(&route.route.route_short_name, 3.0).max(5.0) + 3.0
+}
+
+// Badges of the routes in a row starting at x
+fn badge_positions(x: f64, routes: &[ColoredRoute]) -> Vec<(&ColoredRoute, f64)> {
+    routes
+        .iter()
+        .scan(x, |x, route| {
+            let position = *x;
+            *x += badge_width(route) + 1.0;
+            Some((route, position))
+        })
+        .collect()
+}
+
+const DIAGRAM_TOP: f64 = 34.0;
+const DIAGRAM_ROW_HEIGHT: f64 = 8.0;
+const DIAGRAM_LANE_WIDTH: f64 = 8.0;
+
+// Schematic strip map of a route with a row per station, branches in lanes
+// beside the main line and the other routes serving each station
+pub fn route_diagram_svg(
+    route: &ColoredRoute,
+    destination: &str,
+    diagram: &RouteDiagram,
+    names: &HashMap<u32, String>,
+    interchanges: &HashMap<u32, Vec<ColoredRoute>>
+) -> Markup {
+    let (color, text_color) = route_colors(route);
+    let lane_x = |lane: usize| MARGIN + 4.0 + lane as f64 * DIAGRAM_LANE_WIDTH;
+    let row_y = |row: usize| DIAGRAM_TOP + row as f64 * DIAGRAM_ROW_HEIGHT;
+    let name_x = lane_x(diagram.lanes.max(1) - 1) + 7.0;
+    let no_interchanges = Vec::new();
+    let station_routes = |stop_id: u32| interchanges.get(&stop_id).unwrap_or(&no_interchanges);
+    let station_name = |stop_id: u32| names.get(&stop_id).map(String::as_str).unwrap_or("");
+
+    let width = diagram
+        .stations
+        .iter()
+        .map(|s| {
+            name_x
+                + text_width(station_name(s.stop_id), 4.0)
+                + 3.0
+                + station_routes(s.stop_id)
+                    .iter()
+                    .map(|r| badge_width(r) + 1.0)
+                    .sum::<f64>()
+        })
+        .fold(150.0, f64::max)
+        + MARGIN;
+    let height = row_y(diagram.stations.len()) + MARGIN;
+
+    html! {
+        svg xmlns="http://www.w3.org/2000/svg"
+            width=(format!("{}mm", coordinate(width))) height=(format!("{}mm", coordinate(height)))
+            viewBox=(format!("0 0 {} {}", coordinate(width), coordinate(height)))
+            font-family="sans-serif" font-size="4" {
+            rect x=(coordinate(MARGIN)) y="10" width="22" height="11" rx="1.5" fill=(color) {}
+            text x=(coordinate(MARGIN + 11.0)) y="17.6" text-anchor="middle" font-size="6" font-weight="bold" fill=(text_color) {
+                (route.route.route_short_name)
+            }
+            @if let Some(route_long_name) = route.route.route_long_name.as_ref().filter(|n| !n.is_empty()) {
+                text x=(coordinate(MARGIN + 26.0)) y="15" font-size="5" font-weight="bold" {(route_long_name)}
+            }
+            text x=(coordinate(MARGIN + 26.0)) y="21" font-size="4.5" {"➜ " (destination)}
+
+            @for (from, to) in &diagram.edges {
+                @let (a, b) = (&diagram.stations[*from], &diagram.stations[*to]);
+                @let (x1, y1, x2, y2) = (lane_x(a.lane), row_y(a.row), lane_x(b.lane), row_y(b.row));
+                // Lanes are changed within one row, the rest runs straight in
+                // the outer lane so a returning branch does not pass stations
+                // of the main line
+                @let step = (y2 - y1).clamp(-DIAGRAM_ROW_HEIGHT, DIAGRAM_ROW_HEIGHT);
+                @let (x_turn, y_turn) = if b.lane >= a.lane { (x2, y1 + step) } else { (x1, y2 - step) };
+                path d=(format!("M {} {} L {} {} L {} {}", coordinate(x1), coordinate(y1), coordinate(x_turn), coordinate(y_turn), coordinate(x2), coordinate(y2)))
+                    fill="none" stroke=(color) stroke-width="2.5" stroke-linecap="round" stroke-linejoin="round" {}
+            }
+
+            @for station in &diagram.stations {
+                @let (x, y) = (lane_x(station.lane), row_y(station.row));
+                @let routes = station_routes(station.stop_id);
+                @if routes.is_empty() {
+                    circle cx=(coordinate(x)) cy=(coordinate(y)) r="1.8" fill="white" stroke=(color) stroke-width="1.2" {}
+                } @else {
+                    // Interchanges with other routes
+                    circle cx=(coordinate(x)) cy=(coordinate(y)) r="2.6" fill="white" stroke="black" stroke-width="1.2" {}
+                }
+                text x=(coordinate(name_x)) y=(coordinate(y + 1.4)) font-weight=[station.terminus.then_some("bold")] {
+                    (station_name(station.stop_id))
+                }
+                @for (r, badge_x) in badge_positions(name_x + text_width(station_name(station.stop_id), 4.0) + 3.0, routes) {
+                    @let (badge_color, badge_text_color) = route_colors(r);
+                    @let width = badge_width(r);
+                    rect x=(coordinate(badge_x)) y=(coordinate(y - 2.4)) width=(coordinate(width)) height="4.5" rx="0.8" fill=(badge_color) {}
+                    text x=(coordinate(badge_x + width / 2.0)) y=(coordinate(y + 1.0)) text-anchor="middle" font-size="3" font-weight="bold" fill=(badge_text_color) {
+                        (r.route.route_short_name)
+                    }
+                }
+            }
+        }
+    }
+}
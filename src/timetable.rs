@@ -0,0 +1,151 @@
+use chrono::{Datelike, Duration, NaiveDate, Timelike, Weekday};
+use itertools::Itertools;
+use std::collections::BTreeMap;
+
+use crate::model::StopTimesExtra;
+
+pub struct TimetableDeparture {
+    // Minutes since midnight of the service day, may exceed 24 hours
+    pub minutes: i64,
+    // Index into Timetable::footnotes
+    pub footnote: Option<usize>
+}
+
+pub struct Timetable {
+    pub destination: String,
+    pub first_day: NaiveDate,
+    pub last_day: NaiveDate,
+    // Monday to Friday, Saturday, Sunday
+    pub columns: [Vec<TimetableDeparture>; 3],
+    // Days of the column on which the departures with this footnote run
+    pub footnotes: Vec<Vec<Weekday>>,
+    // Days from Monday to Friday with the Sunday service, left out of the
+    // Monday to Friday column
+    pub holidays: Vec<NaiveDate>
+}
+
+const COLUMN_DAYS: [&[Weekday]; 3] = [
+    &[
+        Weekday::Mon,
+        Weekday::Tue,
+        Weekday::Wed,
+        Weekday::Thu,
+        Weekday::Fri
+    ],
+    &[Weekday::Sat],
+    &[Weekday::Sun]
+];
+
+// Monday of the week of the given date and the following six days
+pub fn representative_week(date: NaiveDate) -> Vec<NaiveDate> {
+    let monday = date - Duration::days(date.weekday().num_days_from_monday() as i64);
+    (0..7).map(|i| monday + Duration::days(i)).collect()
+}
+
+fn minutes_of_service_day(day: NaiveDate, st: &StopTimesExtra) -> i64 {
+    let departure = st.stop_time.departure_time;
+    (departure.date_naive() - day).num_days() * 24 * 60
+        + (departure.time().hour() * 60 + departure.time().minute()) as i64
+}
+
+fn departure_minutes(day: NaiveDate, stop_times: &[StopTimesExtra]) -> Vec<i64> {
+    stop_times
+        .iter()
+        .map(|st| minutes_of_service_day(day, st))
+        .sorted()
+        .dedup()
+        .collect()
+}
+
+// Days from Monday to Friday that have the departures of the Sunday instead
+// of those of most other days from Monday to Friday
+fn holidays(week: &[(NaiveDate, Vec<StopTimesExtra>)]) -> Vec<NaiveDate> {
+    let Some(sunday) = week
+        .iter()
+        .find(|(day, _)| day.weekday() == Weekday::Sun)
+        .map(|(day, stop_times)| departure_minutes(*day, stop_times))
+    else {
+        return Vec::new();
+    };
+    let weekdays = week
+        .iter()
+        .filter(|(day, _)| COLUMN_DAYS[0].contains(&day.weekday()))
+        .map(|(day, stop_times)| (*day, departure_minutes(*day, stop_times)))
+        .collect_vec();
+    let Some(usual) = weekdays
+        .iter()
+        .map(|(_, minutes)| minutes)
+        .counts()
+        .into_iter()
+        .max_by_key(|(_, count)| *count)
+        .map(|(minutes, _)| minutes.clone())
+    else {
+        return Vec::new();
+    };
+    weekdays
+        .into_iter()
+        .filter(|(_, minutes)| *minutes == sunday && *minutes != usual)
+        .map(|(day, _)| day)
+        .collect()
+}
+
+// Merges the departures of each day of a week into the columns of a wall
+// timetable. Departures that do not run on all days of their column get a
+// footnote naming the days they run on.
+pub fn weekly_timetable(week: &[(NaiveDate, Vec<StopTimesExtra>)]) -> Timetable {
+    let destination = week
+        .iter()
+        .flat_map(|(_, stop_times)| stop_times.iter().map(|st| st.headsign()))
+        .counts()
+        .into_iter()
+        .max_by_key(|(_, count)| *count)
+        .map(|(name, _)| name.to_string())
+        .unwrap_or_default();
+
+    let holidays = holidays(week);
+    let mut footnotes: Vec<Vec<Weekday>> = Vec::new();
+    let columns = COLUMN_DAYS.map(|column_days| {
+        let column_days = week
+            .iter()
+            .map(|(day, _)| *day)
+            .filter(|day| column_days.contains(&day.weekday()) && !holidays.contains(day))
+            .map(|day| day.weekday())
+            .collect_vec();
+        let mut days_by_minutes: BTreeMap<i64, Vec<Weekday>> = BTreeMap::new();
+        for (day, stop_times) in week {
+            if !column_days.contains(&day.weekday()) {
+                continue;
+            }
+            for minutes in departure_minutes(*day, stop_times) {
+                days_by_minutes
+                    .entry(minutes)
+                    .or_default()
+                    .push(day.weekday());
+            }
+        }
+        days_by_minutes
+            .into_iter()
+            .map(|(minutes, days)| {
+                let footnote = (days.len() < column_days.len()).then(|| {
+                    footnotes
+                        .iter()
+                        .position(|f| *f == days)
+                        .unwrap_or_else(|| {
+                            footnotes.push(days);
+                            footnotes.len() - 1
+                        })
+                });
+                TimetableDeparture { minutes, footnote }
+            })
+            .collect()
+    });
+
+    Timetable {
+        destination,
+        first_day: week[0].0,
+        last_day: week[week.len() - 1].0,
+        columns,
+        footnotes,
+        holidays
+    }
+}
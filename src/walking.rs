@@ -0,0 +1,123 @@
+// Walking times between the platforms of a station based on pathways.txt
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+
+use crate::model::{Pathway, StationNode};
+
+const WALKING_SPEED: f64 = 1.2; // m/s
+const ELEVATOR_WAITING_TIME: u32 = 60; // s
+
+pub struct WalkingTime {
+    pub seconds: u32,
+    // No path through the pathway graph, the time is a beeline estimate
+    pub estimated: bool
+}
+
+fn distance(a: (f64, f64), b: (f64, f64)) -> f64 {
+    let (lon1, lat1) = (a.0.to_radians(), a.1.to_radians());
+    let (lon2, lat2) = (b.0.to_radians(), b.1.to_radians());
+    let h = ((lat2 - lat1) / 2.0).sin().powi(2)
+        + lat1.cos() * lat2.cos() * ((lon2 - lon1) / 2.0).sin().powi(2);
+    2.0 * 6371000.0 * h.sqrt().asin()
+}
+
+fn pathway_seconds(pathway: &Pathway) -> u32 {
+    if let Some(traversal_time) = pathway.traversal_time {
+        return traversal_time.max(0) as u32;
+    }
+
+    let length = pathway
+        .length
+        .map(|length| length as f64)
+        .unwrap_or_else(|| distance(pathway.from_coords, pathway.to_coords));
+    let seconds = (length / WALKING_SPEED).round() as u32;
+
+    // Elevators
+    if pathway.pathway_mode == 5 {
+        seconds + ELEVATOR_WAITING_TIME
+    } else {
+        seconds
+    }
+}
+
+fn shortest_times(graph: &HashMap<u32, Vec<(u32, u32)>>, start: u32) -> HashMap<u32, u32> {
+    let mut times = HashMap::new();
+    let mut queue = BinaryHeap::new();
+    queue.push(Reverse((0, start)));
+
+    while let Some(Reverse((time, node))) = queue.pop() {
+        if times.contains_key(&node) {
+            continue;
+        }
+        times.insert(node, time);
+
+        for (next, cost) in graph.get(&node).into_iter().flatten() {
+            if !times.contains_key(next) {
+                queue.push(Reverse((time + cost, *next)));
+            }
+        }
+    }
+
+    times
+}
+
+// Returns the walking times between all pairs of platforms, indexed like `platforms`
+pub fn platform_walking_times(
+    platforms: &[&StationNode],
+    nodes: &[StationNode],
+    pathways: &[Pathway]
+) -> Vec<Vec<WalkingTime>> {
+    let mut graph: HashMap<u32, Vec<(u32, u32)>> = HashMap::new();
+
+    for p in pathways {
+        let seconds = pathway_seconds(p);
+        graph
+            .entry(p.from_stop_id)
+            .or_default()
+            .push((p.to_stop_id, seconds));
+        if p.is_bidirectional {
+            graph
+                .entry(p.to_stop_id)
+                .or_default()
+                .push((p.from_stop_id, seconds));
+        }
+    }
+
+    // Boarding areas belong to their platform
+    for node in nodes.iter().filter(|n| n.location_type == 4) {
+        if let Some(platform_id) = node.parent_station {
+            graph
+                .entry(node.stop.stop_id)
+                .or_default()
+                .push((platform_id, 0));
+            graph
+                .entry(platform_id)
+                .or_default()
+                .push((node.stop.stop_id, 0));
+        }
+    }
+
+    platforms
+        .iter()
+        .map(|from| {
+            let times = shortest_times(&graph, from.stop.stop_id);
+            platforms
+                .iter()
+                .map(|to| match times.get(&to.stop.stop_id) {
+                    Some(seconds) => WalkingTime {
+                        seconds: *seconds,
+                        estimated: false
+                    },
+                    None => WalkingTime {
+                        seconds: (distance(
+                            (from.stop.stop_lon, from.stop.stop_lat),
+                            (to.stop.stop_lon, to.stop.stop_lat)
+                        ) / WALKING_SPEED)
+                            .round() as u32,
+                        estimated: true
+                    }
+                })
+                .collect()
+        })
+        .collect()
+}
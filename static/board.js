@@ -0,0 +1,26 @@
+// Reloads the departures of a board page every 30 seconds. Query parameters of
+// the page, e.g. platform and lang, are passed on.
+var refreshInterval = 30000;
+
+function updateClock() {
+  var clock = document.getElementById("board-clock");
+  clock.textContent = new Date().toLocaleTimeString(document.documentElement.lang, {hour: "2-digit", minute: "2-digit"});
+}
+
+function reloadDepartures() {
+  var params = new URLSearchParams(window.location.search);
+  params.set("partial", "true");
+  fetch(window.location.pathname + "?" + params.toString())
+    .then(function(response) {
+      if(!response.ok) throw new Error(response.statusText);
+      return response.text();
+    })
+    .then(function(html) {
+      document.getElementById("board-departures").innerHTML = html;
+    })
+    // Keep showing the last departures while the server is unreachable
+    .catch(function(error) { console.log(error); });
+}
+
+setInterval(updateClock, 1000);
+setInterval(reloadDepartures, refreshInterval);
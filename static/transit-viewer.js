@@ -17,6 +17,24 @@ const frequencyStyles = [
 const urlParams = new URLSearchParams(window.location.search);
 var feed = urlParams.get('feed')
 var date = urlParams.get('date') || new Date().toISOString().slice(0, 10);
+var agency = urlParams.get('agency');
+var accessible = urlParams.get('accessible') == "true";
+
+function filterQuery() {
+  var params = new URLSearchParams();
+  if(agency) params.set("agency", agency);
+  if(accessible) params.set("accessible", "true");
+  var query = params.toString();
+  return query ? "?" + query : "";
+}
+
+// Names are translated into the language given in the URL, otherwise the
+// Accept-Language header of the browser is used
+var lang = urlParams.get('lang');
+$.ajaxPrefilter(function(options) {
+  if(lang && options.url.startsWith("/"))
+    options.url += (options.url.includes("?") ? "&" : "?") + "lang=" + encodeURIComponent(lang);
+});
 
 // ***** OSM base layer *****
 
@@ -55,6 +73,17 @@ function reloadAll() {
   reloadSegments();
 }
 
+// Opens the map on the extent of the feed, returns false if it is unknown
+function fitFeed() {
+  if(!feeds || !feeds[feed] || !feeds[feed].bbox)
+    return false;
+  var bbox = feeds[feed].bbox;
+  map.fitBounds(L.latLngBounds(
+    L.latLng(bbox[1], bbox[0]),
+    L.latLng(bbox[3], bbox[2])));
+  return true;
+}
+
 function fitView(stopMarkers) {
   var coords = Object.values(stopMarkers).map(function(m) { return [m.position.lat, m.position.lng]; })
   var lats = coords.map(function(c) {return c[0];});
@@ -97,7 +126,8 @@ function reloadStops() {
 
     //~ var bounds = pruneCluster.Cluster.ComputeGlobalBounds();
     //~ map.fitBounds(L.latLngBounds(L.latLng(bounds.minLat, bounds.minLng), L.latLng(bounds.maxLat, bounds.maxLng)));
-    fitView(stopMarkers);
+    if(!fitFeed())
+      fitView(stopMarkers);
 
     stopsLayer.addLayer(pruneCluster);
     map.spin(false);
@@ -105,7 +135,7 @@ function reloadStops() {
 }
 
 function loadStopInformation(popup, stop_id) {
-  $.ajax({url: "/" + encodeURIComponent(feed) + "/" + encodeURIComponent(date) + "/stops/" + encodeURIComponent(stop_id)}).done(
+  $.ajax({url: "/" + encodeURIComponent(feed) + "/" + encodeURIComponent(date) + "/stops/" + encodeURIComponent(stop_id) + filterQuery()}).done(
     function(data) {
       popup.setContent(data);
       popup.update();
@@ -126,6 +156,12 @@ function onTripLoaded(data) {
   });
 
   tripSidebar.setContent(data.features[0].properties.trip_info);
+  $("#trip-sidebar .route_diagram").each(function() {
+    var url = "/" + encodeURIComponent(feed) + "/routes/" + encodeURIComponent(this.dataset.routeId) +
+      "/diagram/" + encodeURIComponent(this.dataset.directionId) + (lang ? "?lang=" + encodeURIComponent(lang) : "");
+    $(this).find("img").attr("src", url);
+    $(this).find("a").attr("href", url + (lang ? "&" : "?") + "download=true");
+  });
   tripSidebar.show();
 
   map.addLayer(tripLayer);
@@ -141,18 +177,42 @@ function jumpToStopAndShowInfo(stop_id) {
 }
 
 function onStopDepartureClicked(element) {
-  showTrip(element.dataset.tripId);
+  showTrip(element.dataset.tripId, element.dataset.timeOffset);
 }
 
 function onTripStopClicked(element) {
   jumpToStopAndShowInfo(element.dataset.stopId);
 }
 
-function showTrip(trip_id) {
+// Opens the printable weekly timetable of a route and direction at the stop
+function onTimetableClicked(element) {
+  var url = "/" + encodeURIComponent(feed) + "/" + encodeURIComponent(date) + "/timetables/" +
+    encodeURIComponent(element.dataset.stopId) + "/" + encodeURIComponent(element.dataset.routeId) + "/" +
+    encodeURIComponent(element.dataset.directionId);
+  if(lang) url += "?lang=" + encodeURIComponent(lang);
+  window.open(url, "_blank");
+}
+
+function showTrip(trip_id, time_offset) {
   if(tripLayer) map.removeLayer(tripLayer);
+  if(stationLayer) map.removeLayer(stationLayer);
   if(map) map.closePopup();
 
-  $.ajax({url: "/" + encodeURIComponent(feed) + "/" + encodeURIComponent(date) + "/trips/" + encodeURIComponent(trip_id)}).done(onTripLoaded);
+  var query = time_offset && time_offset != "0" ? "?offset=" + encodeURIComponent(time_offset) : "";
+  $.ajax({url: "/" + encodeURIComponent(feed) + "/" + encodeURIComponent(date) + "/trips/" + encodeURIComponent(trip_id) + query}).done(onTripLoaded);
+}
+
+function onFareStopChanged() {
+  var from = $("#trip-sidebar input[name=fare-from]:checked").val();
+  var to = $("#trip-sidebar input[name=fare-to]:checked").val();
+  if(!from || !to) return;
+
+  var fareElement = $("#trip-fare");
+  var query = "?from=" + encodeURIComponent(from) + "&to=" + encodeURIComponent(to);
+  $.ajax({url: "/" + encodeURIComponent(feed) + "/fares/" + encodeURIComponent(fareElement.data("tripId")) + query}).done(
+    function(data) {
+      fareElement.html(data);
+    });
 }
 
 var tripSidebar = L.control.sidebar('trip-sidebar', {
@@ -162,8 +222,65 @@ var tripSidebar = L.control.sidebar('trip-sidebar', {
 
 tripSidebar.on('hidden', function () {
   if(tripLayer) map.removeLayer(tripLayer);
+  if(stationLayer) map.removeLayer(stationLayer);
 });
 
+// ***** Station detail layer *****
+
+var stationLayer = null;
+
+const locationTypeColors = {0: "#0827FF", 2: "#22BB22", 3: "#888888", 4: "#115588"};
+const pathwayModeNames = {1: "Walkway", 2: "Stairs", 3: "Moving sidewalk", 4: "Escalator", 5: "Elevator", 6: "Fare gate", 7: "Exit gate"};
+
+function onStationLoaded(data) {
+  stationLayer = L.geoJSON(data, {
+    style: function(feature) {
+      return {weight: 4, color: "#555555", opacity: 0.8, dashArray: feature.properties.pathway_mode == 5 ? "4" : null};
+    },
+    pointToLayer: function(feature, latLng) {
+      return L.circleMarker(latLng, {radius: 6, weight: 2, fillOpacity: 0.8,
+        color: locationTypeColors[feature.properties.location_type] || "#888888"});
+    },
+    onEachFeature: function(feature, layer) {
+      var p = feature.properties;
+      if(p.pathway_mode) {
+        var text = pathwayModeNames[p.pathway_mode] || "Pathway";
+        if(p.traversal_time) text += " (" + Math.ceil(p.traversal_time / 60) + " min)";
+        layer.bindTooltip(text);
+      } else {
+        var text = p.platform_code ? p.stop_name + " (" + p.platform_code + ")" : p.stop_name;
+        if(p.level_name) text += ", " + p.level_name;
+        layer.bindTooltip(text);
+      }
+    }
+  });
+
+  tripSidebar.setContent(data.properties.station_info);
+  tripSidebar.show();
+
+  map.addLayer(stationLayer);
+  if(data.features.length > 0) map.fitBounds(stationLayer.getBounds());
+}
+
+function onStationDetailsClicked(element) {
+  showStation(element.dataset.stopId);
+}
+
+// Opens the full-screen departure board of the stop, e.g. for station screens
+function onDepartureBoardClicked(element) {
+  var url = "/" + encodeURIComponent(feed) + "/boards/" + encodeURIComponent(element.dataset.stopId);
+  if(lang) url += "?lang=" + encodeURIComponent(lang);
+  window.open(url, "_blank");
+}
+
+function showStation(stop_id) {
+  if(tripLayer) map.removeLayer(tripLayer);
+  if(stationLayer) map.removeLayer(stationLayer);
+  if(map) map.closePopup();
+
+  $.ajax({url: "/" + encodeURIComponent(feed) + "/stations/" + encodeURIComponent(stop_id)}).done(onStationLoaded);
+}
+
 // ***** Frequency layer *****
 
 function styleForTripFrequency(t) {
@@ -183,7 +300,7 @@ function onFrequencyLayerLoaded(data) {
 
 
 function reloadSegments() {
-  uri = "/" + encodeURIComponent(feed) + "/frequency/" + encodeURIComponent(date) + "/{z}/{x}/{y}/tile.pbf"
+  uri = "/" + encodeURIComponent(feed) + "/frequency/" + encodeURIComponent(date) + "/{z}/{x}/{y}/tile.pbf" + filterQuery();
   geojsonVTLayer = L.vectorGrid.protobuf(uri, {
     maxZoom: 24,
     tolerance: 1,
@@ -263,6 +380,14 @@ function updateURL() {
   var url = new URL(window.location.href);
   url.searchParams.set("feed", feed);
   url.searchParams.set("date", date);
+  if(agency)
+    url.searchParams.set("agency", agency);
+  else
+    url.searchParams.delete("agency");
+  if(accessible)
+    url.searchParams.set("accessible", "true");
+  else
+    url.searchParams.delete("accessible");
   window.history.replaceState({}, '', url);
 }
 
@@ -272,13 +397,63 @@ function updateAttribution() {
   map.attributionControl.addAttribution(attributionText);
 }
 
+// Limits the date picker to the validity of the feed or its calendar
+function updateDateRange() {
+  var input = document.getElementById('date-select');
+  var info = feeds[feed];
+  if(!input || !info)
+    return;
+  input.min = info.feed_start_date || info.calendar_start_date || "";
+  input.max = info.feed_end_date || info.calendar_end_date || "";
+}
+
 function onChangeFeed(newfeed) {
   feed = newfeed;
+  agency = null;
   updateAttribution();
+  updateDateRange();
   updateURL();
+  reloadAgencies();
   reloadAll();
 }
 
+function onChangeAgency(newagency) {
+  agency = newagency || null;
+  map.closePopup();
+  updateURL();
+  reloadSegments();
+}
+
+function onChangeAccessible(newaccessible) {
+  accessible = newaccessible;
+  map.closePopup();
+  updateURL();
+  reloadSegments();
+}
+
+function reloadAgencies() {
+  $.ajax({url: "/" + encodeURIComponent(feed) + "/agencies"}).done(function(data) {
+    var select = document.getElementById('agency-select');
+    select.innerHTML = "";
+
+    var option = document.createElement('option');
+    option.value = "";
+    option.textContent = "All agencies";
+    select.appendChild(option);
+
+    data.forEach(function(entry) {
+      var option = document.createElement('option');
+      option.value = entry.agency_id;
+      option.textContent = entry.agency_name;
+      if(entry.agency_id == agency)
+        option.setAttribute('selected', 'selected');
+      select.appendChild(option);});
+
+    // Feeds with a single agency do not need a filter
+    document.getElementById('agency-label').style.display = data.length > 1 ? "" : "none";
+  });
+}
+
 function onChangeDate(newdate) {
   date = newdate;
   updateURL();
@@ -308,13 +483,30 @@ function onFeedListLoaded(data) {
   date_input.setAttribute('value', date);
   date_input.setAttribute('onchange', "onChangeDate(this.value)");
 
+  var agency_select = document.createElement('select');
+  agency_select.name = agency_select.id = 'agency-select';
+  agency_select.setAttribute('onchange', "onChangeAgency(this.value)");
+
+  var accessible_input = document.createElement('input');
+  accessible_input.type = 'checkbox';
+  accessible_input.name = accessible_input.id = 'accessible-select';
+  if(accessible)
+    accessible_input.setAttribute('checked', 'checked');
+  accessible_input.setAttribute('onchange', "onChangeAccessible(this.checked)");
+
   html = "<div style='margin: 0.5em'><label>Select feed:<br>" + select.outerHTML + "</label>" +
-    "<label>Select date:<br>" + date_input.outerHTML + "</label></div>";
+    "<label>Select date:<br>" + date_input.outerHTML + "</label>" +
+    "<label id='agency-label' style='display: none'><br>Select agency:<br>" + agency_select.outerHTML + "</label>" +
+    "<label><br>" + accessible_input.outerHTML + " Wheelchair accessible only</label></div>";
 
   L.control.custom({
     position: 'bottomright',
     content : html,
     classes : 'leaflet-control-layers'}).addTo(map);
+
+  updateDateRange();
+  fitFeed();
+  reloadAgencies();
 }
 
 $.ajax({url: "/feeds"}).done(onFeedListLoaded);